@@ -0,0 +1,140 @@
+//! Minimal WebSocket client for manually testing the service end-to-end
+//!
+//! Usage:
+//!   cargo run --example client -- [URL] [--api-key KEY] [--format FORMAT]
+//!
+//! Every event received from the server is pretty-printed to stdout. Each
+//! line typed on stdin is sent to the server as a text frame, so control
+//! messages such as `{"action":"subscribe"}` can be entered interactively.
+
+use futures::{SinkExt, StreamExt};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio_tungstenite::connect_async;
+use tungstenite::Message;
+
+const DEFAULT_URL: &str = "ws://localhost:8765";
+
+struct Args {
+    url: String,
+    api_key: Option<String>,
+    format: Option<String>,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut url = None;
+    let mut api_key = None;
+    let mut format = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--api-key" => api_key = Some(args.next().ok_or("--api-key requires a value")?),
+            "--format" => format = Some(args.next().ok_or("--format requires a value")?),
+            "-h" | "--help" => {
+                return Err("usage: client [URL] [--api-key KEY] [--format FORMAT]".to_string())
+            }
+            _ if url.is_none() && !arg.starts_with("--") => url = Some(arg),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+
+    Ok(Args {
+        url: url.unwrap_or_else(|| DEFAULT_URL.to_string()),
+        api_key,
+        format,
+    })
+}
+
+/// Appends the connection options to the server URL as query parameters
+fn build_url(args: &Args) -> String {
+    let mut params = Vec::new();
+    if let Some(key) = &args.api_key {
+        params.push(format!("api_key={}", key));
+    }
+    if let Some(format) = &args.format {
+        params.push(format!("format={}", format));
+    }
+
+    if params.is_empty() {
+        return args.url.clone();
+    }
+
+    // A query string needs a path in front of it, e.g. ws://host:port/?...
+    let mut url = args.url.clone();
+    let authority_start = url.find("://").map(|i| i + 3).unwrap_or(0);
+    if !url[authority_start..].contains('/') {
+        url.push('/');
+    }
+    let separator = if url.contains('?') { '&' } else { '?' };
+    format!("{}{}{}", url, separator, params.join("&"))
+}
+
+/// Pretty-prints JSON payloads and falls back to the raw text otherwise
+fn print_message(text: &str) {
+    match serde_json::from_str::<serde_json::Value>(text) {
+        Ok(value) => match serde_json::to_string_pretty(&value) {
+            Ok(pretty) => println!("{}", pretty),
+            Err(_) => println!("{}", text),
+        },
+        Err(_) => println!("{}", text),
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let args = match parse_args() {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    };
+
+    let url = build_url(&args);
+    let (ws_stream, _) = match connect_async(&url).await {
+        Ok(connection) => connection,
+        Err(e) => {
+            eprintln!("Failed to connect to {}: {}", args.url, e);
+            std::process::exit(1);
+        }
+    };
+    eprintln!("Connected to {}. Type JSON control messages and press enter to send.", args.url);
+
+    let (mut write, mut read) = ws_stream.split();
+    let mut stdin = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdin_open = true;
+
+    loop {
+        tokio::select! {
+            msg = read.next() => match msg {
+                Some(Ok(Message::Text(text))) => print_message(&text),
+                Some(Ok(Message::Binary(data))) => println!("<{} bytes of binary data>", data.len()),
+                Some(Ok(Message::Close(frame))) => {
+                    eprintln!("Server closed the connection: {:?}", frame);
+                    break;
+                }
+                Some(Ok(_)) => {}
+                Some(Err(e)) => {
+                    eprintln!("Read error: {}", e);
+                    break;
+                }
+                None => break,
+            },
+            line = stdin.next_line(), if stdin_open => match line {
+                Ok(Some(line)) if line.trim().is_empty() => {}
+                Ok(Some(line)) => {
+                    if let Err(e) = write.send(Message::Text(line)).await {
+                        eprintln!("Failed to send message: {}", e);
+                        break;
+                    }
+                }
+                // stdin closed: keep streaming events until the server disconnects
+                Ok(None) => stdin_open = false,
+                Err(e) => {
+                    eprintln!("Failed to read stdin: {}", e);
+                    break;
+                }
+            },
+        }
+    }
+}
@@ -0,0 +1,140 @@
+//! Optional recovery of trades missed while the RPC subscription was down, from the
+//! program's transaction history over the HTTP endpoint
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::time::Duration;
+use hyper::Uri;
+use log::{info, warn};
+use serde_json::{json, Value};
+use crate::config::Config;
+use crate::event_channels::EventChannels;
+use crate::event_parser::{self, PUMP_FUN_PROGRAM_ID};
+use crate::metrics;
+use crate::replay_buffer::ReplayBuffer;
+use crate::rpc_http;
+use crate::solana_client;
+use crate::state_index::StateIndex;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Most signatures getSignaturesForAddress returns per page
+const SIGNATURE_PAGE_SIZE: usize = 1000;
+
+/// How far back a backfill reaches and how hard it may use the HTTP RPC
+#[derive(Debug, Clone, Copy)]
+pub struct BackfillLimits {
+    pub max_slots: u64,
+    pub max_transactions: usize,
+    pub requests_per_sec: u32,
+}
+
+// Signatures sent by the last backfill, so the live stream doesn't send them again
+static BACKFILLED: Mutex<Option<HashSet<String>>> = Mutex::new(None);
+
+/// Whether the last backfill already sent this transaction's trades
+pub fn already_sent(signature: &str) -> bool {
+    BACKFILLED.lock().unwrap().as_ref().is_some_and(|sent| sent.contains(signature))
+}
+
+/// A transaction signature from `getSignaturesForAddress`
+struct Signature {
+    signature: String,
+    slot: u64,
+}
+
+/// Sends the trades of successful program transactions in slots after `last_seen_slot`,
+/// oldest first and marked `"source": "backfill"`, returning how many were sent
+///
+/// At most `max_slots` before the current confirmed slot and `max_transactions`
+/// transactions are fetched; anything older is logged as lost.
+pub async fn run(
+    rpc_http: &Uri,
+    last_seen_slot: u64,
+    limits: &BackfillLimits,
+    config: &Config,
+    sender: &EventChannels,
+    replay: &Mutex<ReplayBuffer>,
+    state: &Mutex<StateIndex>,
+) -> Result<usize, String> {
+    let mut pacer = tokio::time::interval(Duration::from_secs(1) / limits.requests_per_sec);
+    pacer.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    pacer.tick().await;
+    let current = rpc_http::call(rpc_http, "getSlot", json!([{"commitment": "confirmed"}]), REQUEST_TIMEOUT).await?
+        .as_u64()
+        .ok_or("getSlot returned no slot")?;
+    let from = last_seen_slot.max(current.saturating_sub(limits.max_slots));
+    if from > last_seen_slot {
+        warn!("Backfill limited to BACKFILL_MAX_SLOTS: slots {} to {} are not recovered", last_seen_slot + 1, from);
+    }
+
+    // Signatures come newest first; page back until the gap is covered
+    let mut signatures = Vec::new();
+    let mut before: Option<String> = None;
+    'pages: loop {
+        pacer.tick().await;
+        let mut options = json!({"limit": SIGNATURE_PAGE_SIZE, "commitment": "confirmed"});
+        if let Some(before) = &before {
+            options["before"] = before.clone().into();
+        }
+        let page = rpc_http::call(rpc_http, "getSignaturesForAddress", json!([PUMP_FUN_PROGRAM_ID, options]), REQUEST_TIMEOUT).await?;
+        let page = page.as_array().ok_or("getSignaturesForAddress returned no list")?;
+        for entry in page {
+            let (Some(signature), Some(slot)) = (entry["signature"].as_str(), entry["slot"].as_u64()) else { continue };
+            before = Some(signature.to_string());
+            if slot <= from {
+                break 'pages;
+            }
+            // Failed transactions rolled their trades back
+            if entry["err"].is_null() {
+                signatures.push(Signature { signature: signature.to_string(), slot });
+            }
+            if signatures.len() >= limits.max_transactions {
+                warn!("Backfill limited to BACKFILL_MAX_TRANSACTIONS: trades before slot {} are not recovered", slot);
+                break 'pages;
+            }
+        }
+        if page.len() < SIGNATURE_PAGE_SIZE {
+            break;
+        }
+    }
+    info!("Backfilling {} transactions from slots {} to {}", signatures.len(), from + 1, current);
+
+    let mut sent = HashSet::new();
+    let mut events_sent = 0;
+    for Signature { signature, slot } in signatures.into_iter().rev() {
+        pacer.tick().await;
+        let params = json!([signature, {"encoding": "json", "commitment": "confirmed", "maxSupportedTransactionVersion": 0}]);
+        let transaction = match rpc_http::call(rpc_http, "getTransaction", params, REQUEST_TIMEOUT).await {
+            Ok(transaction) => transaction,
+            Err(e) => {
+                warn!("Backfill skipped transaction {}: {}", signature, e);
+                continue;
+            }
+        };
+        // Shaped like a logs notification so trades decode exactly as they do live
+        let logs = json!({
+            "context": {"slot": slot},
+            "value": {"signature": signature, "err": transaction["meta"]["err"], "logs": transaction["meta"]["logMessages"]},
+        });
+        let Some((creates, events)) = event_parser::decode_logs(&logs) else { continue };
+        for create in creates {
+            solana_client::record_launch(create, state);
+        }
+        for event in events {
+            if config.volume_leaders.is_some() {
+                state.lock().unwrap().record_trade(&event.mint_address, event.sol_amount);
+            }
+            let Ok(json) = event.to_json(config.output_format()) else { continue };
+            let Ok(mut value) = serde_json::from_str::<Value>(&json) else { continue };
+            value["source"] = "backfill".into();
+            solana_client::broadcast(value.to_string(), event.timestamp, config, sender, replay);
+            metrics::record_backfilled_event();
+            events_sent += 1;
+        }
+        sent.insert(signature);
+    }
+    *BACKFILLED.lock().unwrap() = Some(sent);
+    Ok(events_sent)
+}
@@ -0,0 +1,82 @@
+//! Watches for a broadcast buffer that stays nearly full, meaning clients are
+//! constantly lagging, and grows it, logs it or sheds the slowest clients
+
+use std::time::Duration;
+use log::{info, warn};
+use crate::connection_registry;
+use crate::event_channels::EventChannels;
+use crate::metrics;
+
+/// Fraction of the buffer that counts as saturated
+pub const SATURATED_FRACTION: f64 = 0.9;
+
+/// What to do once the buffer is chronically saturated and can't grow any further
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaturationAction {
+    /// Log a warning when saturation starts and a note when it ends
+    Log,
+    /// Disconnect the WebSocket clients at least half the buffer behind, or that took
+    /// no event at all while it stayed saturated
+    Shed,
+}
+
+/// When and how to respond to a saturated buffer
+#[derive(Debug, Clone, Copy)]
+pub struct SaturationSettings {
+    /// How long the buffer must stay saturated before the service acts
+    pub after: Duration,
+    pub action: SaturationAction,
+}
+
+/// Samples the main channel's depth every second and acts each time it has been
+/// saturated for `settings.after`
+///
+/// An adaptive buffer is grown first; the configured action applies once it is at
+/// its maximum.
+pub async fn run(sender: EventChannels, settings: SaturationSettings) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut saturated_since: Option<tokio::time::Instant> = None;
+    let mut reported = false;
+    loop {
+        let now = ticker.tick().await;
+        let capacity = sender.buffer_capacity();
+        let (depth, _) = sender.queue_depths();
+        if (depth as f64) < capacity as f64 * SATURATED_FRACTION {
+            if reported {
+                info!("Broadcast buffer no longer saturated: {} of {} events queued", depth, capacity);
+            }
+            saturated_since = None;
+            reported = false;
+            continue;
+        }
+        let since = *saturated_since.get_or_insert(now);
+        if now - since < settings.after {
+            continue;
+        }
+        saturated_since = Some(now);
+        if !reported {
+            metrics::record_buffer_saturation();
+        }
+        if let Some(grown) = sender.grow_buffer() {
+            warn!("Broadcast buffer saturated for {:?}: grew it from {} to {} events", settings.after, capacity, grown);
+            reported = true;
+            continue;
+        }
+        match settings.action {
+            SaturationAction::Log if !reported => {
+                warn!("Broadcast buffer saturated for {:?}: {} of {} events queued, clients are falling behind. \
+                       Consider BROADCAST_BUFFER=adaptive, a larger BROADCAST_BUFFER_MAX or BUFFER_SATURATION_ACTION=shed",
+                      settings.after, depth, capacity);
+            }
+            SaturationAction::Log => {}
+            SaturationAction::Shed => {
+                let shed = connection_registry::shed_slow_connections(capacity as u64 / 2, settings.after);
+                metrics::record_clients_shed(shed as u64);
+                warn!("Broadcast buffer saturated for {:?}: disconnecting {} clients at least {} events behind or stalled",
+                      settings.after, shed, capacity / 2);
+            }
+        }
+        reported = true;
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::Mutex;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use crate::config::Config;
+use crate::creator_tracking::TrackedCreators;
+use crate::handshake::Capabilities;
+use crate::projection::Projection;
+use crate::state_index::{MintSort, StateIndex};
+
+// Page size limits for `list_mints`
+const DEFAULT_LIST_MINTS_LIMIT: usize = 50;
+const MAX_LIST_MINTS_LIMIT: usize = 200;
+
+/// Actions accepted by `handle_command`
+pub const COMMANDS: &[&str] = &["list_mints", "capabilities", "pause", "resume", "project", "ack", "track_creator", "untrack_creator", "sync"];
+
+/// Per-connection state that control messages can change
+#[derive(Default)]
+pub struct Session {
+    /// Events are consumed but not sent while set
+    pub paused: bool,
+    /// Events discarded since the connection was last paused
+    pub skipped_while_paused: u64,
+    /// Fields each event is reduced to, if the client set a projection
+    pub projection: Option<Projection>,
+    /// Unacknowledged events allowed in flight, for connections in ack mode
+    pub ack_window: Option<u64>,
+    /// `seq` of the last event sent, and of the last one the client acknowledged
+    pub last_sent_seq: u64,
+    pub last_acked_seq: u64,
+    /// Creators whose mints this connection is limited to, once it tracks any
+    pub tracked_creators: TrackedCreators,
+    /// Whether events go out in slot batches, which cannot be numbered for a sync
+    pub slot_batches: bool,
+    /// Set by `sync` until the connection sends the snapshot
+    pub sync_requested: bool,
+    /// Whether every event now carries a `seq`, continuing from the last snapshot
+    pub synced: bool,
+}
+
+impl Session {
+    /// Returns true while an ack mode connection has a full window of unacknowledged events
+    pub fn awaiting_acks(&self) -> bool {
+        self.ack_window.is_some_and(|window| self.last_sent_seq - self.last_acked_seq >= window)
+    }
+}
+
+/// Control messages a client can send over its WebSocket connection
+#[derive(Deserialize, Debug)]
+#[serde(tag = "action", rename_all = "snake_case")]
+pub enum ClientCommand {
+    /// Page through the mints currently tracked in the state index
+    ListMints {
+        limit: Option<usize>,
+        offset: Option<usize>,
+        sort: Option<String>,
+    },
+    /// Report what this server supports
+    Capabilities,
+    /// Stop sending events to this connection until `resume`
+    Pause,
+    /// Start sending events again after `pause`
+    Resume,
+    /// Send only these fields of each event; an empty list sends whole events again
+    Project {
+        fields: Vec<String>,
+    },
+    /// Acknowledge every event up to and including `seq`, in ack mode
+    Ack {
+        seq: u64,
+    },
+    /// Receive only events for mints launched by this creator and the others tracked
+    TrackCreator {
+        creator: String,
+    },
+    /// Stop tracking a creator; untracking the last one sends every event again
+    UntrackCreator {
+        creator: String,
+    },
+    /// Send the current state as `snapshot` pages, then number every live event
+    Sync,
+}
+
+/// Parses and executes a client control message, returning the reply to send back
+///
+/// Successful acks get no reply, so ack mode does not double the traffic. Nor does
+/// `sync`, whose reply is the snapshot the connection sends when `sync_requested` is set.
+pub fn handle_command(text: &str, state: &Mutex<StateIndex>, config: &Config, session: &mut Session) -> Option<Value> {
+    let command: ClientCommand = match serde_json::from_str(text) {
+        Ok(command) => command,
+        Err(e) => return Some(error_reply(&format!("invalid command: {}", e))),
+    };
+
+    let reply = match command {
+        ClientCommand::ListMints { limit, offset, sort } => list_mints(state, config, limit, offset, sort.as_deref()),
+        ClientCommand::Capabilities => json!({
+            "type": "capabilities",
+            "capabilities": Capabilities::new(config),
+        }),
+        ClientCommand::Pause => {
+            if !session.paused {
+                session.paused = true;
+                session.skipped_while_paused = 0;
+            }
+            json!({ "type": "paused" })
+        }
+        ClientCommand::Resume => {
+            let skipped = std::mem::take(&mut session.skipped_while_paused);
+            session.paused = false;
+            json!({ "type": "resumed", "skipped": skipped })
+        }
+        ClientCommand::Project { fields } => {
+            if fields.is_empty() {
+                session.projection = None;
+            } else {
+                match Projection::new(&fields) {
+                    Ok(projection) => session.projection = Some(projection),
+                    Err(e) => return Some(error_reply(&e)),
+                }
+            }
+            json!({ "type": "projection", "fields": fields })
+        }
+        ClientCommand::Ack { seq } => {
+            if session.ack_window.is_none() {
+                return Some(error_reply("ack mode is off; connect with ack_window to enable it"));
+            }
+            if seq > session.last_sent_seq {
+                return Some(error_reply(&format!("ack for seq {} which has not been sent", seq)));
+            }
+            // Acks are cumulative, so a late or repeated one changes nothing
+            session.last_acked_seq = session.last_acked_seq.max(seq);
+            return None;
+        }
+        ClientCommand::TrackCreator { creator } => {
+            if let Err(e) = session.tracked_creators.track(&creator, config.creator_tracking.as_ref()) {
+                return Some(error_reply(&e));
+            }
+            json!({ "type": "tracked_creators", "creators": session.tracked_creators.list() })
+        }
+        ClientCommand::UntrackCreator { creator } => {
+            if !session.tracked_creators.untrack(&creator) {
+                return Some(error_reply(&format!("not tracking creator '{}'", creator)));
+            }
+            json!({ "type": "tracked_creators", "creators": session.tracked_creators.list() })
+        }
+        ClientCommand::Sync => {
+            if config.sync.is_none() {
+                return Some(error_reply("sync is disabled on this server"));
+            }
+            if session.slot_batches {
+                return Some(error_reply("sync cannot be used with slot_batches"));
+            }
+            session.sync_requested = true;
+            return None;
+        }
+    };
+    Some(reply)
+}
+
+fn list_mints(state: &Mutex<StateIndex>, config: &Config, limit: Option<usize>, offset: Option<usize>, sort: Option<&str>) -> Value {
+    let sort_name = sort.unwrap_or("recency");
+    let sort: MintSort = match sort_name.parse() {
+        Ok(sort) => sort,
+        Err(e) => return error_reply(&e),
+    };
+    let limit = limit.unwrap_or(DEFAULT_LIST_MINTS_LIMIT).min(MAX_LIST_MINTS_LIMIT);
+    let offset = offset.unwrap_or(0);
+
+    let (total, mints) = state.lock().unwrap().list(sort, offset, limit, config.output_format());
+    json!({
+        "type": "mint_list",
+        "sort": sort_name,
+        "offset": offset,
+        "limit": limit,
+        "total": total,
+        "mints": mints,
+    })
+}
+
+fn error_reply(message: &str) -> Value {
+    json!({
+        "type": "error",
+        "message": message,
+    })
+}
@@ -0,0 +1,131 @@
+//! Named client groups: operator-defined filters a client joins with `?group=`
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::RwLock;
+use serde::Deserialize;
+use serde_json::Value;
+use crate::event_parser;
+
+/// Longest group name accepted
+pub const MAX_GROUP_NAME_LEN: usize = 64;
+
+// Groups currently defined, replaced as a whole on reload
+static GROUPS: RwLock<BTreeMap<String, GroupFilter>> = RwLock::new(BTreeMap::new());
+
+/// Conditions an event must meet to reach a group; unset conditions always pass
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct GroupFilter {
+    pub event_types: Option<Vec<String>>,
+    pub min_market_cap_sol: Option<f64>,
+    pub max_market_cap_sol: Option<f64>,
+    pub min_sol_amount: Option<f64>,
+}
+
+impl GroupFilter {
+    /// An event without the field a bound applies to, such as a `buy` under a market cap bound, is dropped
+    fn accepts(&self, event: &Value) -> bool {
+        let event_type = event["event_type"].as_str().unwrap_or_default();
+        if self.event_types.as_ref().is_some_and(|types| !types.iter().any(|t| t == event_type)) {
+            return false;
+        }
+        if self.min_market_cap_sol.is_some() || self.max_market_cap_sol.is_some() {
+            let Some(market_cap) = event_parser::event_market_cap_sol(event) else { return false };
+            if self.min_market_cap_sol.is_some_and(|min| market_cap < min)
+                || self.max_market_cap_sol.is_some_and(|max| market_cap > max) {
+                return false;
+            }
+        }
+        if let Some(min) = self.min_sol_amount {
+            if !event_parser::event_sol_amount(event).is_some_and(|amount| amount >= min) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+/// Checks a group name's shape: 1 to 64 lowercase letters, digits, `_` or `-`
+pub fn validate_name(name: &str) -> Result<(), String> {
+    let valid_chars = name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '-');
+    if name.is_empty() || name.len() > MAX_GROUP_NAME_LEN || !valid_chars {
+        return Err(format!("invalid group name '{}', expected 1 to {} of a-z, 0-9, _ or -", name, MAX_GROUP_NAME_LEN));
+    }
+    Ok(())
+}
+
+/// Reads and validates a groups file, a JSON object of group name to filter
+fn read(path: &str) -> Result<BTreeMap<String, GroupFilter>, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("cannot read {}: {}", path, e))?;
+    let groups: BTreeMap<String, GroupFilter> = serde_json::from_str(&text).map_err(|e| format!("invalid {}: {}", path, e))?;
+    for name in groups.keys() {
+        validate_name(name)?;
+    }
+    Ok(groups)
+}
+
+/// Replaces the defined groups with the contents of `path`, keeping the current ones on error
+pub fn load(path: &str) -> Result<usize, String> {
+    let groups = read(path)?;
+    let count = groups.len();
+    *GROUPS.write().unwrap() = groups;
+    Ok(count)
+}
+
+/// Names of the defined groups, sorted
+pub fn names() -> Vec<String> {
+    GROUPS.read().unwrap().keys().cloned().collect()
+}
+
+pub fn exists(name: &str) -> bool {
+    GROUPS.read().unwrap().contains_key(name)
+}
+
+/// Whether a message should reach a client in `group`
+///
+/// Messages without an `event_type`, such as `stream_stalled`, always pass. Events
+/// for a group that a reload removed are dropped until it is defined again.
+pub fn accepts(group: &str, message: &str) -> bool {
+    let Ok(event) = serde_json::from_str::<Value>(message) else { return true };
+    if event.get("event_type").is_none() {
+        return true;
+    }
+    GROUPS.read().unwrap().get(group).is_some_and(|filter| filter.accepts(&event))
+}
+
+/// Whether a mint with this market cap falls within `group`'s market cap bounds
+///
+/// Used for state snapshots, where the other conditions have no event to apply to.
+pub fn accepts_market_cap(group: &str, market_cap_sol: f64) -> bool {
+    GROUPS.read().unwrap().get(group).is_some_and(|filter| {
+        filter.min_market_cap_sol.is_none_or(|min| market_cap_sol >= min)
+            && filter.max_market_cap_sol.is_none_or(|max| market_cap_sol <= max)
+    })
+}
+
+/// Reloads the groups file on every `SIGHUP`; where there is no `SIGHUP`, groups load once
+pub async fn reload_on_sighup(path: String) {
+    #[cfg(unix)]
+    {
+        use log::{error, info};
+        use tokio::signal::unix::{signal, SignalKind};
+
+        let mut hangups = match signal(SignalKind::hangup()) {
+            Ok(hangups) => hangups,
+            Err(e) => {
+                error!("Unable to listen for SIGHUP, client groups will not reload: {}", e);
+                return;
+            }
+        };
+        while hangups.recv().await.is_some() {
+            match load(&path) {
+                Ok(count) => info!("Reloaded {} client groups from {}", count, path),
+                Err(e) => error!("Keeping current client groups, reload failed: {}", e),
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    log::warn!("Client groups from {} cannot be reloaded on this platform; restart to apply changes", path);
+}
@@ -0,0 +1,224 @@
+use std::fmt;
+use std::str::FromStr;
+use crate::client_groups;
+use crate::output_schema::OutputSchema;
+
+/// Connection parameters understood by `from_query`
+pub const PARAMETERS: &[&str] = &["slot_batches", "stream_health", "reserve_deltas", "verbosity", "format", "ack_window", "batch_sort", "group", "transitions_only", "sync", "schema"];
+
+/// Largest `ack_window` a client may ask for
+pub const MAX_ACK_WINDOW: u32 = 1000;
+
+/// How much of each event a client receives
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    /// Event type, mint, price, slot, timestamp and a per-connection `seq`
+    Minimal,
+    /// Events exactly as broadcast
+    #[default]
+    Standard,
+    /// Standard plus enrichment; the same as standard until enrichment sources exist
+    Full,
+}
+
+impl FromStr for Verbosity {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "minimal" => Ok(Verbosity::Minimal),
+            "standard" => Ok(Verbosity::Standard),
+            "full" => Ok(Verbosity::Full),
+            other => Err(format!("invalid value '{}' for 'verbosity', expected minimal, standard or full", other)),
+        }
+    }
+}
+
+/// Encoding of events sent to a client
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    #[default]
+    Json,
+    /// One CSV row per event after a header row; control messages stay JSON
+    Csv,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            other => Err(format!("invalid value '{}' for 'format', expected json or csv", other)),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Format::Json => "json",
+            Format::Csv => "csv",
+        })
+    }
+}
+
+/// Order of events inside a slot batch
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum BatchSort {
+    /// The order in which the RPC node delivered them
+    #[default]
+    Insertion,
+    /// Highest market cap first; events without a price come last
+    MarketCapDesc,
+    /// Lowest slot first, for late events added to a later slot's batch
+    SlotAsc,
+}
+
+impl FromStr for BatchSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "insertion" => Ok(BatchSort::Insertion),
+            "market_cap_desc" => Ok(BatchSort::MarketCapDesc),
+            "slot_asc" => Ok(BatchSort::SlotAsc),
+            other => Err(format!("invalid value '{}' for 'batch_sort', expected insertion, market_cap_desc or slot_asc", other)),
+        }
+    }
+}
+
+impl fmt::Display for BatchSort {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            BatchSort::Insertion => "insertion",
+            BatchSort::MarketCapDesc => "market_cap_desc",
+            BatchSort::SlotAsc => "slot_asc",
+        })
+    }
+}
+
+impl fmt::Display for Verbosity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Verbosity::Minimal => "minimal",
+            Verbosity::Standard => "standard",
+            Verbosity::Full => "full",
+        })
+    }
+}
+
+/// Per-connection options a client selects with query parameters on the WebSocket URL
+///
+/// e.g. `ws://host:8765/?slot_batches=true`
+#[derive(Debug, Default, Clone)]
+pub struct ClientOptions {
+    /// Deliver events in per-slot batches instead of one frame per event
+    pub slot_batches: bool,
+    /// Receive a periodic `stream_health` summary
+    pub stream_health: bool,
+    /// Receive `trade` updates as reserve deltas between periodic full keyframes
+    pub reserve_deltas: bool,
+    pub verbosity: Verbosity,
+    pub format: Format,
+    /// Events that may be sent without an `ack` before delivery pauses; `None` disables acks
+    pub ack_window: Option<u32>,
+    pub batch_sort: BatchSort,
+    /// Operator-defined group whose filter applies to this client's events
+    pub group: Option<String>,
+    /// Receive per-mint `transition` messages instead of events
+    pub transitions_only: bool,
+    /// Start with a `snapshot` of the current state instead of the replay buffer
+    pub sync: bool,
+    /// Shape events are converted to, as for the Unix socket and webhook sinks
+    pub schema: OutputSchema,
+}
+
+impl ClientOptions {
+    /// Parses the query string of the handshake request, rejecting unknown or malformed values
+    pub fn from_query(query: Option<&str>) -> Result<Self, String> {
+        let mut options = ClientOptions::default();
+        let Some(query) = query else {
+            return Ok(options);
+        };
+
+        for (key, value) in form_urlencoded::parse(query.as_bytes()) {
+            match key.as_ref() {
+                "slot_batches" => options.slot_batches = parse_bool(&key, &value)?,
+                "stream_health" => options.stream_health = parse_bool(&key, &value)?,
+                "reserve_deltas" => options.reserve_deltas = parse_bool(&key, &value)?,
+                "verbosity" => options.verbosity = value.parse()?,
+                "format" => options.format = value.parse()?,
+                "ack_window" => options.ack_window = parse_ack_window(&value)?,
+                "batch_sort" => options.batch_sort = value.parse()?,
+                "transitions_only" => options.transitions_only = parse_bool(&key, &value)?,
+                "sync" => options.sync = parse_bool(&key, &value)?,
+                "schema" => options.schema = value.parse()?,
+                "group" => {
+                    client_groups::validate_name(&value)?;
+                    options.group = Some(value.into_owned());
+                }
+                // Accepted for forward compatibility with clients that always send it
+                "api_key" => {}
+                _ => return Err(format!("unknown connection parameter '{}'", key)),
+            }
+        }
+        // CSV rows cannot be grouped into batches or rewritten as deltas
+        if options.format == Format::Csv && (options.slot_batches || options.reserve_deltas) {
+            return Err("format=csv cannot be combined with slot_batches or reserve_deltas".to_string());
+        }
+        // Sync numbers single events, which batches are not
+        if options.sync && options.slot_batches {
+            return Err("sync cannot be combined with slot_batches".to_string());
+        }
+        // Acks refer to single JSON events, which batches and CSV rows are not
+        if options.ack_window.is_some() && (options.format == Format::Csv || options.slot_batches) {
+            return Err("ack_window cannot be combined with format=csv or slot_batches".to_string());
+        }
+        if options.batch_sort != BatchSort::Insertion && !options.slot_batches {
+            return Err("batch_sort requires slot_batches=true".to_string());
+        }
+        // Rows, deltas, batches and minimal events all read the envelope's fields
+        if options.schema != OutputSchema::Envelope
+            && (options.format == Format::Csv || options.reserve_deltas || options.slot_batches || options.verbosity == Verbosity::Minimal) {
+            return Err("schema cannot be combined with format=csv, reserve_deltas, slot_batches or verbosity=minimal".to_string());
+        }
+        // Geyser updates have no event_type, so they can't be numbered
+        if options.schema == OutputSchema::Geyser && (options.ack_window.is_some() || options.sync) {
+            return Err("schema=geyser cannot be combined with ack_window or sync".to_string());
+        }
+        Ok(options)
+    }
+}
+
+fn parse_ack_window(value: &str) -> Result<Option<u32>, String> {
+    match value.parse::<u32>() {
+        Ok(0) => Ok(None),
+        Ok(window) if window <= MAX_ACK_WINDOW => Ok(Some(window)),
+        _ => Err(format!("invalid value '{}' for 'ack_window', expected 0 to {}", value, MAX_ACK_WINDOW)),
+    }
+}
+
+fn parse_bool(key: &str, value: &str) -> Result<bool, String> {
+    match value {
+        "true" | "1" => Ok(true),
+        "false" | "0" => Ok(false),
+        _ => Err(format!("invalid value '{}' for '{}', expected true or false", value, key)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schema_is_chosen_per_connection() {
+        assert_eq!(ClientOptions::from_query(None).unwrap().schema, OutputSchema::Envelope);
+        assert_eq!(ClientOptions::from_query(Some("schema=flat&ack_window=10")).unwrap().schema, OutputSchema::Flat);
+        assert_eq!(ClientOptions::from_query(Some("schema=geyser")).unwrap().schema, OutputSchema::Geyser);
+        assert!(ClientOptions::from_query(Some("schema=avro")).is_err());
+        assert!(ClientOptions::from_query(Some("schema=flat&format=csv")).is_err());
+        assert!(ClientOptions::from_query(Some("schema=geyser&sync=true")).is_err());
+    }
+}
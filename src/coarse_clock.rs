@@ -0,0 +1,103 @@
+//! Optional coarse clock for event timestamps: with `TIMESTAMP_RESOLUTION_MS` set,
+//! a background task reads the system clock once per tick, each thread renders it
+//! once, and every event in that tick reuses both
+
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+// Current tick in ms since the Unix epoch
+static NOW_MS: AtomicI64 = AtomicI64::new(0);
+
+thread_local! {
+    // The last tick this thread rendered, so events don't contend on a shared rendering
+    static RENDERED: RefCell<Option<(DateTime<Utc>, String)>> = const { RefCell::new(None) };
+}
+
+/// The time to stamp on an event: the current tick, or the system clock when disabled
+pub fn now() -> DateTime<Utc> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return Utc::now();
+    }
+    DateTime::from_timestamp_millis(NOW_MS.load(Ordering::Relaxed)).unwrap_or_else(Utc::now)
+}
+
+/// Renders a timestamp as RFC 3339, reusing the current tick's rendering when it matches
+pub fn rfc3339(timestamp: DateTime<Utc>) -> String {
+    if ENABLED.load(Ordering::Relaxed) {
+        return cached_rfc3339(timestamp);
+    }
+    timestamp.to_rfc3339()
+}
+
+fn cached_rfc3339(timestamp: DateTime<Utc>) -> String {
+    RENDERED.with(|cell| {
+        let mut rendered = cell.borrow_mut();
+        match rendered.as_ref() {
+            Some((tick, text)) if *tick == timestamp => text.clone(),
+            _ => {
+                let text = timestamp.to_rfc3339();
+                *rendered = Some((timestamp, text.clone()));
+                text
+            }
+        }
+    })
+}
+
+fn advance() {
+    NOW_MS.store(Utc::now().timestamp_millis(), Ordering::Relaxed);
+}
+
+/// Advances the clock every `resolution`, switching event timestamps over to it
+pub async fn run(resolution: Duration) {
+    let mut ticker = tokio::time::interval(resolution);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+    advance();
+    ENABLED.store(true, Ordering::Relaxed);
+    loop {
+        ticker.tick().await;
+        advance();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn cached_rendering_matches_a_fresh_one() {
+        let first = DateTime::from_timestamp_millis(1_700_000_000_123).unwrap();
+        let second = DateTime::from_timestamp_millis(1_700_000_000_124).unwrap();
+        assert_eq!(cached_rfc3339(first), first.to_rfc3339());
+        assert_eq!(cached_rfc3339(first), first.to_rfc3339());
+        assert_eq!(cached_rfc3339(second), second.to_rfc3339());
+        assert_eq!(cached_rfc3339(first), first.to_rfc3339());
+    }
+
+    /// Checks that reusing the tick's rendering beats rendering every event; run with
+    /// `cargo test --release coarse_clock -- --ignored`
+    #[test]
+    #[ignore]
+    fn rendering_cost() {
+        const EVENTS: u32 = 1_000_000;
+        let tick = DateTime::from_timestamp_millis(Utc::now().timestamp_millis()).unwrap();
+
+        let start = Instant::now();
+        for _ in 0..EVENTS {
+            std::hint::black_box(std::hint::black_box(tick).to_rfc3339());
+        }
+        let fresh = start.elapsed() / EVENTS;
+
+        let start = Instant::now();
+        for _ in 0..EVENTS {
+            std::hint::black_box(cached_rfc3339(std::hint::black_box(tick)));
+        }
+        let cached = start.elapsed() / EVENTS;
+
+        assert!(cached < fresh, "cached rendering took {:?}/event, to_rfc3339 {:?}/event", cached, fresh);
+    }
+}
@@ -1,15 +1,600 @@
 use std::env;
+use ed25519_dalek::SigningKey;
+use crate::event_parser::{OutputFormat, SanityLimits, TimestampFormat, Units};
+use crate::output_schema::OutputSchema;
+use crate::parse_watchdog::WatchdogLimits;
+use crate::proxy::{Proxy, ProxyKind};
+use crate::reconnect_storm::StormLimits;
+use crate::backfill::BackfillLimits;
+use crate::buffer_saturation::{SaturationAction, SaturationSettings};
+use crate::creator_tracking::CreatorTracking;
+use crate::delivery_hours::Schedule;
+use crate::signing;
+use crate::solana_client::Cluster;
+use crate::webhooks::Webhook;
+use crate::state_index::{CreatorLimit, GraduationAlert, TriggerField, WashTradeDetection};
+use crate::state_sync::SyncLimits;
+use crate::transitions::TransitionSettings;
+use crate::volume_leaders::LeaderBoard;
 
 pub struct Config {
     pub solana_rpc_ws: String,
+    pub cluster: Cluster,
+    pub solana_rpc_http: Option<hyper::Uri>,
+    pub verify_program_on_start: bool,
+    pub startup_snapshot: bool,
+    pub reconcile_processed: bool,
     pub server_port: u16,
+    pub trigger_fields: Vec<TriggerField>,
+    pub max_tracked_mints: usize,
+    pub seen_mints_capacity: usize,
+    pub seen_mints_false_positive_rate: f64,
+    pub creator_limit: Option<CreatorLimit>,
+    pub graduation_alert: Option<GraduationAlert>,
+    pub creator_tracking: Option<CreatorTracking>,
+    pub volume_leaders: Option<LeaderBoard>,
+    pub backfill: Option<BackfillLimits>,
+    pub wash_trade_detection: Option<WashTradeDetection>,
+    pub profile_timestamps: bool,
+    pub transitions: Option<TransitionSettings>,
+    pub sync: Option<SyncLimits>,
+    pub suppress_creator_spam: bool,
+    pub max_new_tokens_per_sec: Option<u32>,
+    pub new_token_queue_size: usize,
+    pub replay_buffer_size: usize,
+    pub max_event_age_secs: Option<u64>,
+    pub replay_ttl_secs: Option<u64>,
+    pub critical_channel_capacity: Option<usize>,
+    pub broadcast_shards: usize,
+    pub broadcast_buffer_max: Option<usize>,
+    pub buffer_saturation: SaturationSettings,
+    pub delivery_hours: Option<Schedule>,
+    pub combined_replay: bool,
+    pub otel_exporter_endpoint: Option<String>,
+    pub client_groups_file: Option<String>,
+    pub units: Units,
+    pub http_port: u16,
+    pub grpc_port: Option<u16>,
+    pub admin_api_key: Option<String>,
+    pub drain_timeout_secs: u64,
+    pub graceful_restart: bool,
+    pub sanity_limits: SanityLimits,
+    pub validate_pubkeys: bool,
+    pub parse_failure_samples: Option<usize>,
+    pub parse_watchdog: Option<WatchdogLimits>,
+    pub resubscribe_dedup_ms: u64,
+    pub emit_global_updates: bool,
+    pub emit_instruction_events: bool,
+    pub log_scan_max_lines: usize,
+    pub log_scan_max_bytes: usize,
+    pub slot_batch_timeout_ms: u64,
+    pub client_message_timeout_ms: u64,
+    pub stream_health_interval_secs: u64,
+    pub delta_keyframe_interval: u32,
+    pub rpc_proxy: Option<Proxy>,
+    pub split_token_created: bool,
+    pub emit_first_trades: bool,
+    pub timestamp_format: TimestampFormat,
+    pub timestamp_resolution_ms: u64,
+    pub price_precision: Option<u32>,
+    pub stream_stall_timeout_secs: u64,
+    pub stall_reconnect_delay_ms: u64,
+    pub reconnect_initial_backoff_ms: u64,
+    pub reconnect_max_backoff_secs: u64,
+    pub upstream_down_after_failures: Option<u32>,
+    pub reconnect_storm: Option<StormLimits>,
+    pub full_handshake: bool,
+    pub uds_path: Option<String>,
+    pub uds_schema: OutputSchema,
+    pub signing_key: Option<SigningKey>,
+    pub webhooks: Vec<Webhook>,
+    pub webhook_secret: Option<String>,
+    pub webhook_concurrency: usize,
+    pub webhook_max_retries: u32,
+    pub webhook_dead_letter_file: Option<String>,
+    pub webhook_schema: OutputSchema,
+    /// Whether WebSocket clients may ask for `schema=geyser`
+    pub ws_geyser_schema: bool,
 }
 
 impl Config {
     pub fn from_env() -> Self {
         dotenv::dotenv().ok();
         let solana_rpc_ws = env::var("SOLANA_RPC_WS").expect("SOLANA_RPC_WS must be set");
+        if solana_rpc_ws.starts_with("http://") || solana_rpc_ws.starts_with("https://") {
+            panic!("SOLANA_RPC_WS: '{}' is an HTTP URL; use the provider's WebSocket endpoint (ws:// or wss://), and SOLANA_RPC_HTTP for the HTTP one", solana_rpc_ws);
+        }
+        let cluster = match env::var("CLUSTER").ok().filter(|name| !name.is_empty()) {
+            Some(name) => name.parse().unwrap_or_else(|e| panic!("CLUSTER: {}", e)),
+            None => Cluster::detect(&solana_rpc_ws),
+        };
+        let solana_rpc_http = env::var("SOLANA_RPC_HTTP").ok().filter(|url| !url.is_empty())
+            .map(|url| url.parse().unwrap_or_else(|e| panic!("SOLANA_RPC_HTTP: invalid URL '{}': {}", url, e)));
+        let verify_program_on_start = match env::var("VERIFY_PROGRAM_ON_START").unwrap_or("false".to_string()).as_str() {
+            "true" => true,
+            "false" => false,
+            other => panic!("VERIFY_PROGRAM_ON_START: unknown value '{}', expected 'true' or 'false'", other),
+        };
+        if verify_program_on_start && solana_rpc_http.is_none() {
+            panic!("VERIFY_PROGRAM_ON_START requires SOLANA_RPC_HTTP");
+        }
+        let startup_snapshot = match env::var("STARTUP_SNAPSHOT").unwrap_or("skip".to_string()).as_str() {
+            "skip" => false,
+            "emit" => true,
+            other => panic!("STARTUP_SNAPSHOT: unknown value '{}', expected 'skip' or 'emit'", other),
+        };
+        if startup_snapshot && solana_rpc_http.is_none() {
+            panic!("STARTUP_SNAPSHOT requires SOLANA_RPC_HTTP");
+        }
+        let reconcile_processed = match env::var("RECONCILE_PROCESSED").unwrap_or("false".to_string()).as_str() {
+            "true" => true,
+            "false" => false,
+            other => panic!("RECONCILE_PROCESSED: unknown value '{}', expected 'true' or 'false'", other),
+        };
+        if reconcile_processed && solana_rpc_http.is_none() {
+            panic!("RECONCILE_PROCESSED requires SOLANA_RPC_HTTP");
+        }
         let server_port = env::var("SERVER_PORT").unwrap_or("8765".to_string()).parse().unwrap();
-        Config { solana_rpc_ws, server_port }
+        let trigger_fields = env::var("TRIGGER_FIELDS")
+            .unwrap_or("virtual_sol_reserves,virtual_token_reserves".to_string())
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(|name| name.parse().unwrap_or_else(|e| panic!("TRIGGER_FIELDS: {}", e)))
+            .collect();
+        let max_tracked_mints = env::var("MAX_TRACKED_MINTS").unwrap_or("100000".to_string()).parse()
+            .expect("MAX_TRACKED_MINTS must be a non-negative integer");
+        let seen_mints_capacity = env::var("SEEN_MINTS_FILTER_CAPACITY").unwrap_or("0".to_string()).parse()
+            .expect("SEEN_MINTS_FILTER_CAPACITY must be a non-negative integer");
+        let seen_mints_false_positive_rate = env::var("SEEN_MINTS_FILTER_FP_RATE").unwrap_or("0.001".to_string()).parse()
+            .ok().filter(|rate| *rate > 0.0 && *rate < 1.0)
+            .expect("SEEN_MINTS_FILTER_FP_RATE must be a number between 0 and 1");
+        let creator_launch_limit: usize = env::var("CREATOR_LAUNCH_LIMIT").unwrap_or("0".to_string()).parse()
+            .expect("CREATOR_LAUNCH_LIMIT must be a non-negative integer");
+        let creator_launch_window_secs = env::var("CREATOR_LAUNCH_WINDOW_SECS").unwrap_or("3600".to_string()).parse()
+            .ok().filter(|secs| *secs > 0)
+            .expect("CREATOR_LAUNCH_WINDOW_SECS must be a positive number of seconds");
+        let creator_limit = (creator_launch_limit > 0).then(|| CreatorLimit {
+            max_launches: creator_launch_limit,
+            window: std::time::Duration::from_secs(creator_launch_window_secs),
+        });
+        let max_tracked_creators: usize = env::var("MAX_TRACKED_CREATORS").unwrap_or("0".to_string()).parse()
+            .expect("MAX_TRACKED_CREATORS must be a non-negative integer");
+        let max_tracked_creators_per_connection = env::var("MAX_TRACKED_CREATORS_PER_CONNECTION").unwrap_or("20".to_string()).parse()
+            .ok().filter(|max| *max > 0)
+            .expect("MAX_TRACKED_CREATORS_PER_CONNECTION must be a positive integer");
+        let creator_summary_interval_secs = env::var("CREATOR_SUMMARY_INTERVAL_SECS").unwrap_or("60".to_string()).parse()
+            .ok().filter(|secs| *secs > 0)
+            .expect("CREATOR_SUMMARY_INTERVAL_SECS must be a positive number of seconds");
+        let creator_tracking = (max_tracked_creators > 0).then(|| CreatorTracking {
+            max_per_connection: max_tracked_creators_per_connection,
+            max_total: max_tracked_creators,
+            summary_interval: std::time::Duration::from_secs(creator_summary_interval_secs),
+        });
+        let volume_leaders_interval_secs: u64 = env::var("VOLUME_LEADERS_INTERVAL_SECS").unwrap_or("0".to_string()).parse()
+            .expect("VOLUME_LEADERS_INTERVAL_SECS must be a number of seconds");
+        let volume_leaders_window_secs = env::var("VOLUME_LEADERS_WINDOW_SECS").unwrap_or("300".to_string()).parse()
+            .ok().filter(|secs| *secs > 0)
+            .expect("VOLUME_LEADERS_WINDOW_SECS must be a positive number of seconds");
+        let volume_leaders_top = env::var("VOLUME_LEADERS_TOP").unwrap_or("10".to_string()).parse()
+            .ok().filter(|top| (1..=100).contains(top))
+            .expect("VOLUME_LEADERS_TOP must be an integer from 1 to 100");
+        let volume_leaders = (volume_leaders_interval_secs > 0).then(|| LeaderBoard {
+            window: std::time::Duration::from_secs(volume_leaders_window_secs),
+            top: volume_leaders_top,
+            interval: std::time::Duration::from_secs(volume_leaders_interval_secs),
+        });
+        let graduation_alert = env::var("NEAR_GRADUATION_ALERT_PROGRESS").ok().filter(|progress| !progress.is_empty()).map(|progress| {
+            let threshold: f64 = progress.parse().ok().filter(|progress| *progress > 0.0 && *progress < 100.0)
+                .expect("NEAR_GRADUATION_ALERT_PROGRESS must be a percentage between 0 and 100");
+            let hysteresis = env::var("NEAR_GRADUATION_HYSTERESIS").unwrap_or("5".to_string()).parse()
+                .ok().filter(|band| *band >= 0.0 && *band < threshold)
+                .expect("NEAR_GRADUATION_HYSTERESIS must be a number of percentage points below NEAR_GRADUATION_ALERT_PROGRESS");
+            GraduationAlert { threshold, hysteresis }
+        });
+        let transitions = match env::var("TRANSITIONS").unwrap_or("false".to_string()).as_str() {
+            "true" => true,
+            "false" => false,
+            other => panic!("TRANSITIONS: unknown value '{}', expected 'true' or 'false'", other),
+        };
+        let transitions = transitions.then(|| {
+            let mut market_caps_sol: Vec<f64> = env::var("TRANSITION_MARKET_CAPS_SOL")
+                .unwrap_or("50,100,200".to_string())
+                .split(',')
+                .map(str::trim)
+                .filter(|cap| !cap.is_empty())
+                .map(|cap| cap.parse().ok().filter(|cap: &f64| *cap > 0.0)
+                    .unwrap_or_else(|| panic!("TRANSITION_MARKET_CAPS_SOL: invalid market cap '{}', expected a positive number of SOL", cap)))
+                .collect();
+            market_caps_sol.sort_by(f64::total_cmp);
+            market_caps_sol.dedup();
+            let stall_secs: u64 = env::var("TRANSITION_STALL_SECS").unwrap_or("300".to_string()).parse()
+                .expect("TRANSITION_STALL_SECS must be a number of seconds");
+            TransitionSettings {
+                market_caps_sol,
+                stall_after: (stall_secs > 0).then(|| std::time::Duration::from_secs(stall_secs)),
+            }
+        });
+        let suppress_creator_spam = match env::var("CREATOR_SPAM_ACTION").unwrap_or("flag".to_string()).as_str() {
+            "flag" => false,
+            "suppress" => true,
+            other => panic!("CREATOR_SPAM_ACTION: unknown value '{}', expected 'flag' or 'suppress'", other),
+        };
+        let max_new_tokens_per_sec = env::var("MAX_NEW_TOKENS_PER_SEC").unwrap_or("0".to_string()).parse()
+            .map(|rate| (rate > 0).then_some(rate))
+            .expect("MAX_NEW_TOKENS_PER_SEC must be a non-negative integer");
+        let new_token_queue_size = env::var("NEW_TOKEN_QUEUE_SIZE").unwrap_or("1000".to_string()).parse()
+            .ok().filter(|size| *size > 0)
+            .expect("NEW_TOKEN_QUEUE_SIZE must be a positive integer");
+        let replay_buffer_size = env::var("REPLAY_BUFFER_SIZE").unwrap_or("0".to_string()).parse()
+            .expect("REPLAY_BUFFER_SIZE must be a non-negative integer");
+        let max_event_age_secs = env::var("MAX_EVENT_AGE_SECS").ok()
+            .map(|secs| secs.parse().expect("MAX_EVENT_AGE_SECS must be a number of seconds"));
+        let replay_ttl_secs = env::var("REPLAY_TTL_SECS").unwrap_or("0".to_string()).parse()
+            .map(|secs| (secs > 0).then_some(secs))
+            .expect("REPLAY_TTL_SECS must be a non-negative number of seconds");
+        let priority_channels = match env::var("BROADCAST_CHANNELS").unwrap_or("single".to_string()).as_str() {
+            "single" => false,
+            "priority" => true,
+            other => panic!("BROADCAST_CHANNELS: unknown value '{}', expected 'single' or 'priority'", other),
+        };
+        let critical_channel_capacity = env::var("CRITICAL_CHANNEL_CAPACITY").unwrap_or("1000".to_string()).parse()
+            .ok().filter(|capacity| *capacity > 0)
+            .expect("CRITICAL_CHANNEL_CAPACITY must be a positive integer");
+        let critical_channel_capacity = priority_channels.then_some(critical_channel_capacity);
+        let broadcast_shards = env::var("BROADCAST_SHARDS").unwrap_or("1".to_string()).parse()
+            .ok().filter(|shards| (1..=64).contains(shards))
+            .expect("BROADCAST_SHARDS must be an integer from 1 to 64");
+        let adaptive_buffer = match env::var("BROADCAST_BUFFER").unwrap_or("static".to_string()).as_str() {
+            "static" => false,
+            "adaptive" => true,
+            other => panic!("BROADCAST_BUFFER: unknown value '{}', expected 'static' or 'adaptive'", other),
+        };
+        let broadcast_buffer_max = env::var("BROADCAST_BUFFER_MAX").unwrap_or("16000".to_string()).parse()
+            .ok().filter(|max| (1000..=1_000_000).contains(max))
+            .expect("BROADCAST_BUFFER_MAX must be an integer from 1000 to 1000000");
+        let broadcast_buffer_max = adaptive_buffer.then_some(broadcast_buffer_max);
+        let buffer_saturation = SaturationSettings {
+            after: env::var("BUFFER_SATURATION_SECS").unwrap_or("10".to_string()).parse()
+                .ok().filter(|secs| *secs > 0)
+                .map(std::time::Duration::from_secs)
+                .expect("BUFFER_SATURATION_SECS must be a positive number of seconds"),
+            action: match env::var("BUFFER_SATURATION_ACTION").unwrap_or("log".to_string()).as_str() {
+                "log" => SaturationAction::Log,
+                "shed" => SaturationAction::Shed,
+                other => panic!("BUFFER_SATURATION_ACTION: unknown value '{}', expected 'log' or 'shed'", other),
+            },
+        };
+        let delivery_hours = env::var("DELIVERY_HOURS").ok().filter(|spec| !spec.is_empty())
+            .map(|spec| Schedule::parse(&spec).unwrap_or_else(|e| panic!("DELIVERY_HOURS: {}", e)));
+        let combined_replay = match env::var("REPLAY_DELIVERY").unwrap_or("separate".to_string()).as_str() {
+            "separate" => false,
+            "combined" => true,
+            other => panic!("REPLAY_DELIVERY: unknown value '{}', expected 'separate' or 'combined'", other),
+        };
+        let otel_exporter_endpoint = env::var("OTEL_EXPORTER_OTLP_ENDPOINT").ok().filter(|url| !url.is_empty());
+        let client_groups_file = env::var("CLIENT_GROUPS_FILE").ok().filter(|path| !path.is_empty());
+        let units = env::var("UNITS").unwrap_or("lamports".to_string()).parse()
+            .unwrap_or_else(|e| panic!("UNITS: {}", e));
+        let http_port = env::var("HTTP_PORT").unwrap_or("8766".to_string()).parse()
+            .expect("HTTP_PORT must be a valid port number");
+        let grpc_port = env::var("GRPC_PORT").ok().filter(|port| !port.is_empty())
+            .map(|port| port.parse().expect("GRPC_PORT must be a valid port number"));
+        let admin_api_key = env::var("ADMIN_API_KEY").ok().filter(|key| !key.is_empty());
+        let drain_timeout_secs = env::var("DRAIN_TIMEOUT_SECS").unwrap_or("300".to_string()).parse()
+            .expect("DRAIN_TIMEOUT_SECS must be a number of seconds");
+        let graceful_restart = match env::var("GRACEFUL_RESTART").unwrap_or("false".to_string()).as_str() {
+            "true" => true,
+            "false" => false,
+            other => panic!("GRACEFUL_RESTART: unknown value '{}', expected 'true' or 'false'", other),
+        };
+        let sanity_limits = SanityLimits {
+            // Real curves hold on the order of 100 SOL and ~1.07B tokens (6 decimals)
+            max_sol_reserves: env::var("MAX_SOL_RESERVES").unwrap_or("10000000000000".to_string()).parse()
+                .expect("MAX_SOL_RESERVES must be a number of lamports"),
+            max_token_reserves: env::var("MAX_TOKEN_RESERVES").unwrap_or("2000000000000000".to_string()).parse()
+                .expect("MAX_TOKEN_RESERVES must be a number of token base units"),
+        };
+        let validate_pubkeys = match env::var("VALIDATE_PUBKEYS").unwrap_or("false".to_string()).as_str() {
+            "true" => true,
+            "false" => false,
+            other => panic!("VALIDATE_PUBKEYS: unknown value '{}', expected 'true' or 'false'", other),
+        };
+        let parse_failure_samples = env::var("PARSE_FAILURE_SAMPLES").unwrap_or("0".to_string()).parse::<usize>()
+            .ok().filter(|samples| *samples <= 1000)
+            .expect("PARSE_FAILURE_SAMPLES must be an integer from 0 to 1000");
+        let parse_failure_samples = (parse_failure_samples > 0).then_some(parse_failure_samples);
+        let parse_watchdog = env::var("PARSE_WATCHDOG_FAILURE_RATIO").ok().filter(|ratio| !ratio.is_empty()).map(|ratio| {
+            let failure_ratio = ratio.parse().ok().filter(|ratio| *ratio > 0.0 && *ratio <= 1.0)
+                .expect("PARSE_WATCHDOG_FAILURE_RATIO must be a number above 0 and at most 1");
+            let window_secs = env::var("PARSE_WATCHDOG_WINDOW_SECS").unwrap_or("60".to_string()).parse()
+                .ok().filter(|secs| *secs > 0)
+                .expect("PARSE_WATCHDOG_WINDOW_SECS must be a positive number of seconds");
+            let min_messages = env::var("PARSE_WATCHDOG_MIN_MESSAGES").unwrap_or("100".to_string()).parse()
+                .ok().filter(|messages| *messages > 0)
+                .expect("PARSE_WATCHDOG_MIN_MESSAGES must be a positive integer");
+            let restart = match env::var("PARSE_WATCHDOG_RESTART").unwrap_or("true".to_string()).as_str() {
+                "true" => true,
+                "false" => false,
+                other => panic!("PARSE_WATCHDOG_RESTART: unknown value '{}', expected 'true' or 'false'", other),
+            };
+            WatchdogLimits { failure_ratio, window: std::time::Duration::from_secs(window_secs), min_messages, restart }
+        });
+        let resubscribe_dedup_ms = env::var("RESUBSCRIBE_DEDUP_MS").unwrap_or("0".to_string()).parse()
+            .expect("RESUBSCRIBE_DEDUP_MS must be a number of milliseconds");
+        let emit_global_updates = match env::var("GLOBAL_ACCOUNT_UPDATES").unwrap_or("skip".to_string()).as_str() {
+            "skip" => false,
+            "emit" => true,
+            other => panic!("GLOBAL_ACCOUNT_UPDATES: unknown value '{}', expected 'skip' or 'emit'", other),
+        };
+        let emit_instruction_events = match env::var("INSTRUCTION_EVENTS").unwrap_or("skip".to_string()).as_str() {
+            "skip" => false,
+            "emit" => true,
+            other => panic!("INSTRUCTION_EVENTS: unknown value '{}', expected 'skip' or 'emit'", other),
+        };
+        if volume_leaders.is_some() && !emit_instruction_events {
+            panic!("VOLUME_LEADERS_INTERVAL_SECS requires INSTRUCTION_EVENTS=emit");
+        }
+        if creator_limit.is_some() && !emit_instruction_events {
+            panic!("CREATOR_LAUNCH_LIMIT requires INSTRUCTION_EVENTS=emit");
+        }
+        if creator_tracking.is_some() && !emit_instruction_events {
+            panic!("MAX_TRACKED_CREATORS requires INSTRUCTION_EVENTS=emit");
+        }
+        let log_scan_max_lines = env::var("LOG_SCAN_MAX_LINES").unwrap_or("1000".to_string()).parse()
+            .ok().filter(|lines| *lines > 0)
+            .expect("LOG_SCAN_MAX_LINES must be a positive number of log lines");
+        let log_scan_max_bytes = env::var("LOG_SCAN_MAX_BYTES").unwrap_or("100000".to_string()).parse()
+            .ok().filter(|bytes| *bytes > 0)
+            .expect("LOG_SCAN_MAX_BYTES must be a positive number of bytes");
+        let backfill_on_reconnect = match env::var("BACKFILL_ON_RECONNECT").unwrap_or("false".to_string()).as_str() {
+            "true" => true,
+            "false" => false,
+            other => panic!("BACKFILL_ON_RECONNECT: unknown value '{}', expected 'true' or 'false'", other),
+        };
+        if backfill_on_reconnect && solana_rpc_http.is_none() {
+            panic!("BACKFILL_ON_RECONNECT requires SOLANA_RPC_HTTP");
+        }
+        if backfill_on_reconnect && !emit_instruction_events {
+            panic!("BACKFILL_ON_RECONNECT requires INSTRUCTION_EVENTS=emit");
+        }
+        let backfill = backfill_on_reconnect.then(|| BackfillLimits {
+            max_slots: env::var("BACKFILL_MAX_SLOTS").unwrap_or("1500".to_string()).parse()
+                .ok().filter(|slots| *slots > 0)
+                .expect("BACKFILL_MAX_SLOTS must be a positive number of slots"),
+            max_transactions: env::var("BACKFILL_MAX_TRANSACTIONS").unwrap_or("500".to_string()).parse()
+                .ok().filter(|transactions| *transactions > 0)
+                .expect("BACKFILL_MAX_TRANSACTIONS must be a positive integer"),
+            requests_per_sec: env::var("BACKFILL_REQUESTS_PER_SEC").unwrap_or("20".to_string()).parse()
+                .ok().filter(|per_sec| *per_sec > 0)
+                .expect("BACKFILL_REQUESTS_PER_SEC must be a positive integer"),
+        });
+        let detect_wash_trades = match env::var("WASH_TRADE_DETECTION").unwrap_or("false".to_string()).as_str() {
+            "true" => true,
+            "false" => false,
+            other => panic!("WASH_TRADE_DETECTION: unknown value '{}', expected 'true' or 'false'", other),
+        };
+        if detect_wash_trades && !emit_instruction_events {
+            panic!("WASH_TRADE_DETECTION requires INSTRUCTION_EVENTS=emit");
+        }
+        let wash_trade_detection = detect_wash_trades.then(|| WashTradeDetection {
+            window: std::time::Duration::from_secs(env::var("WASH_TRADE_WINDOW_SECS").unwrap_or("60".to_string()).parse()
+                .ok().filter(|secs| *secs > 0)
+                .expect("WASH_TRADE_WINDOW_SECS must be a positive number of seconds")),
+            min_trades: env::var("WASH_TRADE_MIN_TRADES").unwrap_or("6".to_string()).parse()
+                .ok().filter(|trades| (2..=64).contains(trades))
+                .expect("WASH_TRADE_MIN_TRADES must be an integer from 2 to 64"),
+            max_wallets: env::var("WASH_TRADE_MAX_WALLETS").unwrap_or("2".to_string()).parse()
+                .ok().filter(|wallets| *wallets > 0)
+                .expect("WASH_TRADE_MAX_WALLETS must be a positive integer"),
+            size_tolerance: env::var("WASH_TRADE_SIZE_TOLERANCE").unwrap_or("0.1".to_string()).parse()
+                .ok().filter(|tolerance| (0.0..=1.0).contains(tolerance))
+                .expect("WASH_TRADE_SIZE_TOLERANCE must be a fraction from 0 to 1"),
+            min_confidence: env::var("WASH_TRADE_MIN_CONFIDENCE").unwrap_or("0.8".to_string()).parse()
+                .ok().filter(|confidence| *confidence > 0.0 && *confidence <= 1.0)
+                .expect("WASH_TRADE_MIN_CONFIDENCE must be a fraction above 0 and at most 1"),
+        });
+        let sync_max_mints: usize = env::var("SYNC_MAX_MINTS").unwrap_or("0".to_string()).parse()
+            .expect("SYNC_MAX_MINTS must be a number of mints");
+        let sync = (sync_max_mints > 0).then(|| SyncLimits {
+            max_mints: sync_max_mints,
+            page_size: env::var("SYNC_PAGE_SIZE").unwrap_or("500".to_string()).parse()
+                .ok().filter(|size| (1..=5000).contains(size))
+                .expect("SYNC_PAGE_SIZE must be an integer from 1 to 5000"),
+        });
+        let profile_timestamps = match env::var("PROFILE_TIMESTAMPS").unwrap_or("false".to_string()).as_str() {
+            "true" => true,
+            "false" => false,
+            other => panic!("PROFILE_TIMESTAMPS: unknown value '{}', expected 'true' or 'false'", other),
+        };
+        let client_message_timeout_ms = env::var("CLIENT_MESSAGE_TIMEOUT_MS").unwrap_or("10000".to_string()).parse()
+            .expect("CLIENT_MESSAGE_TIMEOUT_MS must be a number of milliseconds");
+        let slot_batch_timeout_ms = env::var("SLOT_BATCH_TIMEOUT_MS").unwrap_or("500".to_string()).parse()
+            .expect("SLOT_BATCH_TIMEOUT_MS must be a number of milliseconds");
+        let stream_health_interval_secs = env::var("STREAM_HEALTH_INTERVAL_SECS").unwrap_or("10".to_string()).parse()
+            .ok().filter(|secs| *secs > 0)
+            .expect("STREAM_HEALTH_INTERVAL_SECS must be a positive number of seconds");
+        let delta_keyframe_interval = env::var("DELTA_KEYFRAME_INTERVAL").unwrap_or("20".to_string()).parse()
+            .ok().filter(|interval| *interval > 0)
+            .expect("DELTA_KEYFRAME_INTERVAL must be a positive number of updates");
+        let https_proxy = env::var("HTTPS_PROXY").ok().filter(|url| !url.is_empty());
+        let socks_proxy = env::var("SOCKS_PROXY").ok().filter(|url| !url.is_empty());
+        let rpc_proxy = match (https_proxy, socks_proxy) {
+            (Some(_), Some(_)) => panic!("Set only one of HTTPS_PROXY and SOCKS_PROXY"),
+            (Some(url), None) => Some(Proxy::parse(ProxyKind::Http, &url).unwrap_or_else(|e| panic!("HTTPS_PROXY: {}", e))),
+            (None, Some(url)) => Some(Proxy::parse(ProxyKind::Socks5, &url).unwrap_or_else(|e| panic!("SOCKS_PROXY: {}", e))),
+            (None, None) => None,
+        };
+        let split_token_created = match env::var("TOKEN_CREATED_FORMAT").unwrap_or("combined".to_string()).as_str() {
+            "combined" => false,
+            "split" => true,
+            other => panic!("TOKEN_CREATED_FORMAT: unknown value '{}', expected 'combined' or 'split'", other),
+        };
+        let emit_first_trades = match env::var("FIRST_TRADE_EVENTS").unwrap_or("skip".to_string()).as_str() {
+            "skip" => false,
+            "emit" => true,
+            other => panic!("FIRST_TRADE_EVENTS: unknown value '{}', expected 'skip' or 'emit'", other),
+        };
+        let timestamp_format = env::var("TIMESTAMP_FORMAT").unwrap_or("rfc3339".to_string()).parse()
+            .unwrap_or_else(|e| panic!("TIMESTAMP_FORMAT: {}", e));
+        let timestamp_resolution_ms = env::var("TIMESTAMP_RESOLUTION_MS").unwrap_or("0".to_string()).parse()
+            .ok().filter(|ms| *ms <= 1000)
+            .expect("TIMESTAMP_RESOLUTION_MS must be a number of milliseconds from 0 to 1000");
+        let price_precision = env::var("PRICE_PRECISION").unwrap_or("9".to_string()).parse::<u32>()
+            .ok().filter(|digits| *digits <= 17)
+            .expect("PRICE_PRECISION must be a number of significant digits from 0 to 17");
+        let price_precision = (price_precision > 0).then_some(price_precision);
+        let stream_stall_timeout_secs = env::var("STREAM_STALL_TIMEOUT_SECS").unwrap_or("0".to_string()).parse()
+            .expect("STREAM_STALL_TIMEOUT_SECS must be a number of seconds");
+        let stall_reconnect_delay_ms = env::var("STALL_RECONNECT_DELAY_MS").unwrap_or("500".to_string()).parse()
+            .expect("STALL_RECONNECT_DELAY_MS must be a number of milliseconds");
+        let reconnect_initial_backoff_ms = env::var("RECONNECT_INITIAL_BACKOFF_MS").unwrap_or("1000".to_string()).parse()
+            .expect("RECONNECT_INITIAL_BACKOFF_MS must be a number of milliseconds");
+        let reconnect_max_backoff_secs = env::var("RECONNECT_MAX_BACKOFF_SECS").unwrap_or("60".to_string()).parse()
+            .expect("RECONNECT_MAX_BACKOFF_SECS must be a number of seconds");
+        let upstream_down_after_failures = env::var("UPSTREAM_DOWN_AFTER_FAILURES").unwrap_or("3".to_string()).parse()
+            .map(|failures| (failures > 0).then_some(failures))
+            .expect("UPSTREAM_DOWN_AFTER_FAILURES must be a non-negative integer");
+        let reconnect_storm_threshold: usize = env::var("RECONNECT_STORM_THRESHOLD").unwrap_or("0".to_string()).parse()
+            .expect("RECONNECT_STORM_THRESHOLD must be a non-negative integer");
+        let reconnect_storm_window_secs = env::var("RECONNECT_STORM_WINDOW_SECS").unwrap_or("60".to_string()).parse()
+            .expect("RECONNECT_STORM_WINDOW_SECS must be a number of seconds");
+        let reconnect_storm_cooldown_secs = env::var("RECONNECT_STORM_COOLDOWN_SECS").unwrap_or("30".to_string()).parse()
+            .expect("RECONNECT_STORM_COOLDOWN_SECS must be a number of seconds");
+        let reconnect_stable_secs = env::var("RECONNECT_STABLE_SECS").unwrap_or("120".to_string()).parse()
+            .expect("RECONNECT_STABLE_SECS must be a number of seconds");
+        let reconnect_storm = (reconnect_storm_threshold > 0).then(|| StormLimits {
+            reconnects: reconnect_storm_threshold,
+            window: std::time::Duration::from_secs(reconnect_storm_window_secs),
+            cooldown: std::time::Duration::from_secs(reconnect_storm_cooldown_secs),
+            stable_after: std::time::Duration::from_secs(reconnect_stable_secs),
+        });
+        let full_handshake = match env::var("HANDSHAKE_FORMAT").unwrap_or("basic".to_string()).as_str() {
+            "basic" => false,
+            "full" => true,
+            other => panic!("HANDSHAKE_FORMAT: unknown value '{}', expected 'basic' or 'full'", other),
+        };
+        let uds_path = env::var("UDS_PATH").ok().filter(|path| !path.is_empty());
+        let uds_schema = env::var("UDS_SCHEMA").unwrap_or("envelope".to_string()).parse()
+            .unwrap_or_else(|e| panic!("UDS_SCHEMA: {}", e));
+        let signing_key = env::var("EVENT_SIGNING_KEY").ok().filter(|key| !key.is_empty())
+            .map(|key| signing::parse_signing_key(&key).unwrap_or_else(|e| panic!("EVENT_SIGNING_KEY: {}", e)));
+        let webhooks = env::var("WEBHOOK_URLS").unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| Webhook::parse(entry).unwrap_or_else(|e| panic!("WEBHOOK_URLS: {}", e)))
+            .collect();
+        let webhook_secret = env::var("WEBHOOK_SECRET").ok().filter(|secret| !secret.is_empty());
+        let webhook_concurrency = env::var("WEBHOOK_CONCURRENCY").unwrap_or("8".to_string()).parse()
+            .ok().filter(|n| *n > 0)
+            .expect("WEBHOOK_CONCURRENCY must be a positive integer");
+        let webhook_max_retries = env::var("WEBHOOK_MAX_RETRIES").unwrap_or("5".to_string()).parse()
+            .expect("WEBHOOK_MAX_RETRIES must be a non-negative integer");
+        let webhook_dead_letter_file = env::var("WEBHOOK_DEAD_LETTER_FILE").ok().filter(|path| !path.is_empty());
+        let webhook_schema = env::var("WEBHOOK_SCHEMA").unwrap_or("envelope".to_string()).parse()
+            .unwrap_or_else(|e| panic!("WEBHOOK_SCHEMA: {}", e));
+        let ws_geyser_schema = match env::var("WS_GEYSER_SCHEMA").unwrap_or("false".to_string()).as_str() {
+            "true" => true,
+            "false" => false,
+            other => panic!("WS_GEYSER_SCHEMA: unknown value '{}', expected 'true' or 'false'", other),
+        };
+        Config {
+            solana_rpc_ws,
+            cluster,
+            solana_rpc_http,
+            verify_program_on_start,
+            startup_snapshot,
+            reconcile_processed,
+            server_port,
+            trigger_fields,
+            max_tracked_mints,
+            seen_mints_capacity,
+            seen_mints_false_positive_rate,
+            creator_limit,
+            graduation_alert,
+            creator_tracking,
+            volume_leaders,
+            backfill,
+            wash_trade_detection,
+            profile_timestamps,
+            transitions,
+            sync,
+            suppress_creator_spam,
+            max_new_tokens_per_sec,
+            new_token_queue_size,
+            replay_buffer_size,
+            max_event_age_secs,
+            replay_ttl_secs,
+            critical_channel_capacity,
+            broadcast_shards,
+            broadcast_buffer_max,
+            buffer_saturation,
+            delivery_hours,
+            combined_replay,
+            otel_exporter_endpoint,
+            client_groups_file,
+            units,
+            http_port,
+            grpc_port,
+            admin_api_key,
+            drain_timeout_secs,
+            graceful_restart,
+            sanity_limits,
+            validate_pubkeys,
+            parse_failure_samples,
+            parse_watchdog,
+            resubscribe_dedup_ms,
+            emit_global_updates,
+            emit_instruction_events,
+            log_scan_max_lines,
+            log_scan_max_bytes,
+            slot_batch_timeout_ms,
+            client_message_timeout_ms,
+            stream_health_interval_secs,
+            delta_keyframe_interval,
+            rpc_proxy,
+            split_token_created,
+            emit_first_trades,
+            timestamp_format,
+            timestamp_resolution_ms,
+            price_precision,
+            stream_stall_timeout_secs,
+            stall_reconnect_delay_ms,
+            reconnect_initial_backoff_ms,
+            reconnect_max_backoff_secs,
+            upstream_down_after_failures,
+            reconnect_storm,
+            full_handshake,
+            uds_path,
+            uds_schema,
+            signing_key,
+            webhooks,
+            webhook_secret,
+            webhook_concurrency,
+            webhook_max_retries,
+            webhook_dead_letter_file,
+            webhook_schema,
+            ws_geyser_schema,
+        }
+    }
+
+    /// Units and timestamp format applied to every emitted event
+    pub fn output_format(&self) -> OutputFormat {
+        OutputFormat {
+            cluster: self.cluster,
+            units: self.units,
+            timestamps: self.timestamp_format,
+            price_digits: self.price_precision,
+        }
+    }
+}
+
+#[cfg(test)]
+impl Config {
+    /// Every setting at its default, with an RPC URL that tests never dial
+    pub fn for_tests() -> Self {
+        env::set_var("SOLANA_RPC_WS", "ws://127.0.0.1:1");
+        Config::from_env()
     }
 }
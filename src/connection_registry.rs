@@ -0,0 +1,190 @@
+//! Live per-connection statistics for the `/admin/connections` endpoint
+
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::ops::Deref;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use tokio::sync::Notify;
+use serde_json::{json, Value};
+use crate::client_options::ClientOptions;
+
+// Every open WebSocket connection, by connection id
+static CONNECTIONS: Mutex<BTreeMap<usize, Arc<ConnectionStats>>> = Mutex::new(BTreeMap::new());
+
+/// Counters one connection handler updates as it runs
+pub struct ConnectionStats {
+    id: usize,
+    addr: SocketAddr,
+    connected_at: DateTime<Utc>,
+    options: ClientOptions,
+    messages_sent: AtomicU64,
+    /// Events waiting in this connection's broadcast receiver when it last took one
+    queued_events: AtomicU64,
+    registered: Instant,
+    /// When it last took an event, in ms after `registered`
+    last_taken_ms: AtomicU64,
+    paused: AtomicBool,
+    /// Whether an ack mode connection is holding back events until the client acks
+    awaiting_acks: AtomicBool,
+    shed: Notify,
+}
+
+impl ConnectionStats {
+    /// Adds to the number of messages sent, returning the new total
+    pub fn add_sent(&self, messages: u64) -> u64 {
+        self.messages_sent.fetch_add(messages, Ordering::Relaxed) + messages
+    }
+
+    pub fn messages_sent(&self) -> u64 {
+        self.messages_sent.load(Ordering::Relaxed)
+    }
+
+    /// Records that the connection took an event, leaving `queued` waiting
+    pub fn set_queued_events(&self, queued: usize) {
+        self.queued_events.store(queued as u64, Ordering::Relaxed);
+        self.last_taken_ms.store(self.registered.elapsed().as_millis() as u64, Ordering::Relaxed);
+    }
+
+    fn taken_within(&self, period: Duration) -> bool {
+        let since_taken = self.registered.elapsed().as_millis() as u64 - self.last_taken_ms.load(Ordering::Relaxed);
+        since_taken < period.as_millis() as u64
+    }
+
+    pub fn set_paused(&self, paused: bool) {
+        self.paused.store(paused, Ordering::Relaxed);
+    }
+
+    pub fn set_awaiting_acks(&self, awaiting: bool) {
+        self.awaiting_acks.store(awaiting, Ordering::Relaxed);
+    }
+
+    /// Whether the client, not the connection, is why it takes no events
+    fn held_by_client(&self) -> bool {
+        self.paused.load(Ordering::Relaxed) || self.awaiting_acks.load(Ordering::Relaxed)
+    }
+
+    /// Completes once the connection has been shed for falling behind a saturated buffer
+    pub async fn shed_requested(&self) {
+        self.shed.notified().await
+    }
+
+    fn to_json(&self, now: DateTime<Utc>) -> Value {
+        json!({
+            "id": self.id,
+            "remote_ip": self.addr.ip().to_string(),
+            "connected_at": self.connected_at.to_rfc3339(),
+            "connected_secs": (now - self.connected_at).num_seconds(),
+            "messages_sent": self.messages_sent(),
+            "queued_events": self.queued_events.load(Ordering::Relaxed),
+            "paused": self.paused.load(Ordering::Relaxed),
+            "options": {
+                "slot_batches": self.options.slot_batches,
+                "stream_health": self.options.stream_health,
+                "reserve_deltas": self.options.reserve_deltas,
+                "verbosity": self.options.verbosity.to_string(),
+                "format": self.options.format.to_string(),
+                "ack_window": self.options.ack_window,
+                "batch_sort": self.options.batch_sort.to_string(),
+                "group": self.options.group,
+            },
+        })
+    }
+}
+
+/// A connection's entry in the registry, removed when dropped
+pub struct Registration(Arc<ConnectionStats>);
+
+impl Deref for Registration {
+    type Target = ConnectionStats;
+
+    fn deref(&self) -> &ConnectionStats {
+        &self.0
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        CONNECTIONS.lock().unwrap().remove(&self.0.id);
+    }
+}
+
+/// Adds a connection to the registry for as long as the returned registration lives
+pub fn register(id: usize, addr: SocketAddr, options: &ClientOptions) -> Registration {
+    let stats = Arc::new(ConnectionStats {
+        id,
+        addr,
+        connected_at: Utc::now(),
+        options: options.clone(),
+        messages_sent: AtomicU64::new(0),
+        queued_events: AtomicU64::new(0),
+        registered: Instant::now(),
+        last_taken_ms: AtomicU64::new(0),
+        paused: AtomicBool::new(false),
+        awaiting_acks: AtomicBool::new(false),
+        shed: Notify::new(),
+    });
+    CONNECTIONS.lock().unwrap().insert(id, stats.clone());
+    Registration(stats)
+}
+
+/// Returns the number of registered connections and one page of them, oldest first
+pub fn list(offset: usize, limit: usize) -> (usize, Vec<Value>) {
+    let now = Utc::now();
+    let connections = CONNECTIONS.lock().unwrap();
+    let page = connections.values().skip(offset).take(limit).map(|stats| stats.to_json(now)).collect();
+    (connections.len(), page)
+}
+
+/// Asks every connection that had at least `min_queued` events waiting when it last
+/// took one, or that hasn't taken one within `stalled_for`, to disconnect, returning
+/// how many were asked
+///
+/// A connection stuck writing to a client that doesn't read never takes another
+/// event, so its waiting count goes stale; while events keep arriving, not taking
+/// any marks it as just as slow. Paused connections and ack mode connections waiting
+/// on acks are left alone, as the client asked them to hold back.
+pub fn shed_slow_connections(min_queued: u64, stalled_for: Duration) -> usize {
+    let connections = CONNECTIONS.lock().unwrap();
+    let slow: Vec<_> = connections.values()
+        .filter(|stats| !stats.held_by_client())
+        .filter(|stats| stats.queued_events.load(Ordering::Relaxed) >= min_queued || !stats.taken_within(stalled_for))
+        .collect();
+    for stats in &slow {
+        stats.shed.notify_one();
+    }
+    slow.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::FutureExt;
+
+    fn shed_requested(stats: &ConnectionStats) -> bool {
+        stats.shed_requested().now_or_never().is_some()
+    }
+
+    #[test]
+    fn connections_held_by_their_client_are_not_shed() {
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let stalled = register(1_000_001, addr, &ClientOptions::default());
+        let paused = register(1_000_002, addr, &ClientOptions::default());
+        paused.set_paused(true);
+        let awaiting_acks = register(1_000_003, addr, &ClientOptions::default());
+        awaiting_acks.set_awaiting_acks(true);
+
+        // None of them has taken an event in the last zero seconds
+        shed_slow_connections(u64::MAX, Duration::ZERO);
+        assert!(shed_requested(&stalled));
+        assert!(!shed_requested(&paused));
+        assert!(!shed_requested(&awaiting_acks));
+
+        // Once acks arrive, the connection is as slow as any other
+        awaiting_acks.set_awaiting_acks(false);
+        shed_slow_connections(u64::MAX, Duration::ZERO);
+        assert!(shed_requested(&awaiting_acks));
+    }
+}
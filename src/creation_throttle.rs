@@ -0,0 +1,55 @@
+//! Optional global rate limit on `token_created` events, queueing launches that exceed it
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use tokio::sync::Notify;
+use crate::config::Config;
+use crate::event_channels::EventChannels;
+use crate::metrics;
+use crate::replay_buffer::ReplayBuffer;
+use crate::solana_client;
+
+/// Serialized events announcing one launch, sent together when released
+struct Creation {
+    events: Vec<String>,
+    timestamp: DateTime<Utc>,
+}
+
+// Launches waiting to be released, oldest first
+static QUEUE: Mutex<VecDeque<Creation>> = Mutex::new(VecDeque::new());
+static QUEUED: Notify = Notify::const_new();
+
+/// Number of launches waiting to be released
+pub fn queue_depth() -> usize {
+    QUEUE.lock().unwrap().len()
+}
+
+/// Queues a launch's events for release by `run_release`, dropping the oldest launch if the queue is full
+pub fn enqueue(events: Vec<String>, timestamp: DateTime<Utc>, max_queued: usize) {
+    let mut queue = QUEUE.lock().unwrap();
+    if queue.len() >= max_queued {
+        queue.pop_front();
+        metrics::record_creation_dropped();
+    }
+    queue.push_back(Creation { events, timestamp });
+    drop(queue);
+    QUEUED.notify_one();
+}
+
+/// Broadcasts queued launches in order, at most `per_sec` a second
+pub async fn run_release(per_sec: u32, config: Arc<Config>, sender: EventChannels, replay: Arc<Mutex<ReplayBuffer>>) {
+    let spacing = Duration::from_secs(1) / per_sec;
+    loop {
+        let next = QUEUE.lock().unwrap().pop_front();
+        let Some(creation) = next else {
+            QUEUED.notified().await;
+            continue;
+        };
+        for json in creation.events {
+            solana_client::broadcast(json, creation.timestamp, &config, &sender, &replay);
+        }
+        tokio::time::sleep(spacing).await;
+    }
+}
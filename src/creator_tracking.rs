@@ -0,0 +1,113 @@
+//! Creator-focused delivery: a connection that sends `track_creator` receives only
+//! events for mints launched by the creators it tracks, plus periodic rollups
+
+use std::collections::BTreeSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use serde_json::{json, Value};
+use crate::event_parser::{self, Units};
+use crate::state_index::StateIndex;
+
+// Creators tracked across all connections, counting a creator once per connection
+static TRACKED: AtomicUsize = AtomicUsize::new(0);
+
+/// Bounds on creator tracking and how often rollups are sent
+#[derive(Debug, Clone, Copy)]
+pub struct CreatorTracking {
+    pub max_per_connection: usize,
+    pub max_total: usize,
+    pub summary_interval: Duration,
+}
+
+/// Creators one connection tracks, released from the global count when dropped
+#[derive(Default)]
+pub struct TrackedCreators {
+    creators: BTreeSet<String>,
+}
+
+impl TrackedCreators {
+    /// Starts tracking `creator`, returning false if it already was
+    pub fn track(&mut self, creator: &str, limits: Option<&CreatorTracking>) -> Result<bool, String> {
+        let Some(limits) = limits else {
+            return Err("creator tracking is disabled on this server".to_string());
+        };
+        if !event_parser::is_valid_pubkey(creator) {
+            return Err(format!("invalid creator '{}', expected a base58 public key", creator));
+        }
+        if self.creators.contains(creator) {
+            return Ok(false);
+        }
+        if self.creators.len() >= limits.max_per_connection {
+            return Err(format!("already tracking {} creators, the most one connection may track", limits.max_per_connection));
+        }
+        let reserved = TRACKED.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |total| {
+            (total < limits.max_total).then_some(total + 1)
+        });
+        if reserved.is_err() {
+            return Err("server is tracking the most creators it allows, try again later".to_string());
+        }
+        self.creators.insert(creator.to_string());
+        Ok(true)
+    }
+
+    /// Stops tracking `creator`, returning false if it was not tracked
+    pub fn untrack(&mut self, creator: &str) -> bool {
+        let removed = self.creators.remove(creator);
+        if removed {
+            TRACKED.fetch_sub(1, Ordering::SeqCst);
+        }
+        removed
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.creators.is_empty()
+    }
+
+    pub fn contains(&self, creator: &str) -> bool {
+        self.creators.contains(creator)
+    }
+
+    pub fn list(&self) -> Vec<&str> {
+        self.creators.iter().map(String::as_str).collect()
+    }
+
+    /// Whether a message reaches a connection tracking these creators
+    ///
+    /// Messages that are not about a mint, such as `stream_stalled`, always pass.
+    /// Events that name only the mint, such as buys and sells, are matched through
+    /// the state index and dropped if the mint is not tracked there.
+    pub fn accepts(&self, message: &str, state: &Mutex<StateIndex>) -> bool {
+        let Ok(event) = serde_json::from_str::<Value>(message) else { return true };
+        if let Some(creator) = event["token"]["creator"].as_str() {
+            return self.creators.contains(creator);
+        }
+        let Some(mint) = event["mint_address"].as_str().or(event["mint"].as_str()) else { return true };
+        state.lock().unwrap().creator_of(mint).is_some_and(|creator| self.creators.contains(creator))
+    }
+
+    /// One `creator_summary` message per tracked creator
+    pub fn summaries(&self, state: &Mutex<StateIndex>, units: Units) -> Vec<Value> {
+        let state = state.lock().unwrap();
+        self.creators.iter().filter_map(|creator| {
+            let summary = state.creator_summary(creator)?;
+            Some(json!({
+                "type": "creator_summary",
+                "creator": creator,
+                "tokens_launched": summary.tokens_launched,
+                "total_volume": units.render_lamports(summary.volume_lamports),
+            }))
+        }).collect()
+    }
+}
+
+impl Drop for TrackedCreators {
+    fn drop(&mut self) {
+        TRACKED.fetch_sub(self.creators.len(), Ordering::SeqCst);
+    }
+}
+
+/// Creators tracked across all connections
+pub fn tracked_total() -> usize {
+    TRACKED.load(Ordering::SeqCst)
+}
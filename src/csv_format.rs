@@ -0,0 +1,63 @@
+use serde_json::Value;
+
+/// Columns of each CSV event row, in order
+pub const COLUMNS: &[&str] = &[
+    "seq",
+    "timestamp",
+    "event_type",
+    "mint_address",
+    "symbol",
+    "name",
+    "price_sol",
+    "virtual_sol_reserves",
+    "virtual_token_reserves",
+];
+
+/// Header row sent once before the first event row
+pub fn header() -> String {
+    COLUMNS.join(",")
+}
+
+/// Converts a broadcast event to a CSV row, numbering it with `seq` unless it already has one
+///
+/// Returns None for messages that are not events, which stay JSON.
+pub fn to_row(message: &str, seq: &mut u64) -> Option<String> {
+    let event: Value = serde_json::from_str(message).ok()?;
+    event.get("event_type")?;
+    let row_seq = match event.get("seq").and_then(Value::as_u64) {
+        Some(existing) => existing,
+        None => {
+            *seq += 1;
+            *seq
+        }
+    };
+
+    let token = event.get("token");
+    let pump_data = event.get("pump_data");
+    let cell = |value: Option<&Value>| match value {
+        Some(Value::String(text)) => escape(text),
+        Some(Value::Null) | None => String::new(),
+        Some(other) => other.to_string(),
+    };
+    let row = [
+        row_seq.to_string(),
+        cell(event.get("timestamp")),
+        cell(event.get("event_type")),
+        cell(event.get("mint_address").or_else(|| token?.get("mint_address"))),
+        cell(token.and_then(|token| token.get("symbol"))),
+        cell(token.and_then(|token| token.get("name"))),
+        cell(pump_data.and_then(|data| data.get("price_sol")).or_else(|| event.get("price_sol"))),
+        cell(pump_data.and_then(|data| data.get("virtual_sol_reserves")).or_else(|| event.get("virtual_sol_reserves"))),
+        cell(pump_data.and_then(|data| data.get("virtual_token_reserves")).or_else(|| event.get("virtual_token_reserves"))),
+    ];
+    Some(row.join(","))
+}
+
+/// Quotes a text field if it contains a comma, quote or line break (RFC 4180)
+fn escape(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
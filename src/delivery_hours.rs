@@ -0,0 +1,106 @@
+//! Optional UTC schedule outside which events are ingested but not delivered
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use chrono::{DateTime, NaiveTime, Utc};
+use log::info;
+use serde_json::{json, Value};
+use crate::event_channels::EventChannels;
+
+// Set while outside every configured delivery window
+static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// Returns true while event delivery is paused by the schedule
+pub fn is_paused() -> bool {
+    PAUSED.load(Ordering::Relaxed)
+}
+
+/// Daily UTC time ranges during which events are delivered
+#[derive(Debug, Clone)]
+pub struct Schedule {
+    /// Start and end of each window; a window whose end is before its start runs past midnight
+    windows: Vec<(NaiveTime, NaiveTime)>,
+}
+
+impl Schedule {
+    /// Parses comma-separated `HH:MM-HH:MM` ranges, e.g. `13:30-20:00,22:00-02:00`
+    pub fn parse(spec: &str) -> Result<Self, String> {
+        let windows = spec.split(',')
+            .map(str::trim)
+            .filter(|range| !range.is_empty())
+            .map(|range| {
+                let (start, end) = range.split_once('-')
+                    .ok_or_else(|| format!("invalid range '{}', expected HH:MM-HH:MM", range))?;
+                let time = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M")
+                    .map_err(|_| format!("invalid time '{}' in '{}', expected HH:MM", t.trim(), range));
+                let (start, end) = (time(start)?, time(end)?);
+                if start == end {
+                    return Err(format!("range '{}' is empty", range));
+                }
+                Ok((start, end))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if windows.is_empty() {
+            return Err("no time ranges given".to_string());
+        }
+        Ok(Schedule { windows })
+    }
+
+    /// Whether `now` falls inside any window
+    pub fn is_open(&self, now: DateTime<Utc>) -> bool {
+        let time = now.time();
+        self.windows.iter().any(|(start, end)| match start < end {
+            true => *start <= time && time < *end,
+            false => time >= *start || time < *end,
+        })
+    }
+
+    /// Start of the next window after `now`
+    pub fn next_open(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        self.windows.iter()
+            .map(|(start, _)| {
+                let today = now.date_naive().and_time(*start).and_utc();
+                if today > now { today } else { today + chrono::Duration::days(1) }
+            })
+            .min()
+            .expect("a schedule has at least one window")
+    }
+}
+
+impl std::fmt::Display for Schedule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let ranges: Vec<String> = self.windows.iter()
+            .map(|(start, end)| format!("{}-{}", start.format("%H:%M"), end.format("%H:%M")))
+            .collect();
+        f.write_str(&ranges.join(","))
+    }
+}
+
+/// Notice sent to clients while delivery is paused
+pub fn outside_hours_notice(schedule: &Schedule) -> Value {
+    let now = Utc::now();
+    json!({
+        "type": "outside_hours",
+        "resumes_at": schedule.next_open(now).to_rfc3339(),
+    })
+}
+
+/// Pauses and resumes delivery as the schedule opens and closes, notifying clients on pause
+///
+/// Runs until the task is aborted.
+pub async fn run_schedule(schedule: Schedule, sender: EventChannels) {
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    loop {
+        ticker.tick().await;
+        let paused = !schedule.is_open(Utc::now());
+        if PAUSED.swap(paused, Ordering::Relaxed) == paused {
+            continue;
+        }
+        if paused {
+            info!("Outside delivery hours; pausing event delivery until {}", schedule.next_open(Utc::now()).to_rfc3339());
+            let _ = sender.send(outside_hours_notice(&schedule).to_string());
+        } else {
+            info!("Inside delivery hours; resuming event delivery");
+        }
+    }
+}
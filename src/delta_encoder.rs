@@ -0,0 +1,111 @@
+use std::collections::HashMap;
+use serde_json::{json, Value};
+
+/// Last full or delta state sent to one client for a mint
+struct Baseline {
+    seq: u64,
+    sol: Value,
+    token: Value,
+    /// Fields other than the reserves; any change to them forces a full event
+    rest: Value,
+    deltas_since_keyframe: u32,
+}
+
+/// Rewrites one client's `trade` events as reserve deltas against the previous event for the mint
+///
+/// Every event that touches a mint is numbered with a per-connection `seq`. The
+/// first event for a mint, every `keyframe_interval`-th update and any update
+/// that changes more than the reserves are sent in full as keyframes.
+pub struct DeltaEncoder {
+    keyframe_interval: u32,
+    max_mints: usize,
+    seq: u64,
+    baselines: HashMap<String, Baseline>,
+}
+
+impl DeltaEncoder {
+    pub fn new(keyframe_interval: u32, max_mints: usize) -> Self {
+        DeltaEncoder {
+            keyframe_interval,
+            max_mints,
+            seq: 0,
+            baselines: HashMap::new(),
+        }
+    }
+
+    /// Returns the event to send: a `reserves_delta` message, or the event itself with a `seq`
+    ///
+    /// Events without a mint and reserves are returned unchanged.
+    pub fn encode(&mut self, mut event: Value) -> Value {
+        let Some((mint, sol, token)) = reserves_of(&event) else {
+            return event;
+        };
+        self.seq += 1;
+        let rest = without_reserves(&event);
+
+        if let Some(baseline) = self.baselines.get_mut(&mint) {
+            let delta = (event["event_type"] == "trade"
+                && baseline.rest == rest
+                && baseline.deltas_since_keyframe + 1 < self.keyframe_interval)
+                .then(|| Some((difference(&baseline.sol, &sol)?, difference(&baseline.token, &token)?)))
+                .flatten();
+            if let Some((d_sol, d_token)) = delta {
+                let message = json!({
+                    "event_type": "reserves_delta",
+                    "seq": self.seq,
+                    "base_seq": baseline.seq,
+                    "mint_address": mint,
+                    "slot": event["slot"],
+                    "timestamp": event["timestamp"],
+                    "d_sol": d_sol,
+                    "d_token": d_token,
+                });
+                *baseline = Baseline { seq: self.seq, sol, token, rest, deltas_since_keyframe: baseline.deltas_since_keyframe + 1 };
+                return message;
+            }
+        } else if self.max_mints > 0 && self.baselines.len() >= self.max_mints {
+            // Forgetting every baseline only costs one keyframe per mint on its next update
+            self.baselines.clear();
+        }
+
+        self.baselines.insert(mint, Baseline { seq: self.seq, sol, token, rest, deltas_since_keyframe: 0 });
+        event["seq"] = self.seq.into();
+        event
+    }
+}
+
+/// Mint address and reserve values of an event that carries curve state
+fn reserves_of(event: &Value) -> Option<(String, Value, Value)> {
+    let mint = event.get("token")?.get("mint_address")?.as_str()?;
+    let pump_data = event.get("pump_data")?;
+    Some((
+        mint.to_string(),
+        pump_data.get("virtual_sol_reserves")?.clone(),
+        pump_data.get("virtual_token_reserves")?.clone(),
+    ))
+}
+
+/// The event minus the reserves and the fields that change with every update
+fn without_reserves(event: &Value) -> Value {
+    let mut rest = event.clone();
+    if let Some(fields) = rest.as_object_mut() {
+        for field in ["timestamp", "slot", "transaction_signature", "event_type", "signature", "trace_id", "span_id"] {
+            fields.remove(field);
+        }
+    }
+    if let Some(pump_data) = rest.get_mut("pump_data").and_then(Value::as_object_mut) {
+        // The price follows from the reserves, so clients recompute it
+        for field in ["virtual_sol_reserves", "virtual_token_reserves", "price_sol"] {
+            pump_data.remove(field);
+        }
+    }
+    rest
+}
+
+/// `current - previous`, exact for integer amounts and approximate for decimal ones
+fn difference(previous: &Value, current: &Value) -> Option<Value> {
+    match (previous.as_i64(), current.as_i64()) {
+        (Some(previous), Some(current)) => Some(current.checked_sub(previous)?.into()),
+        _ => Some((current.as_f64()? - previous.as_f64()?).into()),
+    }
+}
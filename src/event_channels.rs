@@ -0,0 +1,218 @@
+//! Broadcast fan-out from the listener to every consumer, optionally split by priority and sharded
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use tokio::sync::broadcast::error::{RecvError, SendError};
+use tokio::sync::broadcast::{self, Receiver, Sender};
+
+/// Event types sent on the critical channel in priority mode
+pub const CRITICAL_EVENT_TYPES: &[&str] = &[
+    "token_created",
+    "token_metadata",
+    "curve_created",
+    "creator_spam_flagged",
+    "global_update",
+];
+
+/// Senders for the broadcast channels every consumer subscribes to
+///
+/// With a single channel, every message shares it and a consumer that lags
+/// behind it misses events of every type. In priority mode, launches and
+/// control messages go on a separate critical channel, so lag on the busy
+/// channel of trades only costs trades.
+///
+/// With more than one shard, every message is sent to each shard's channels and
+/// each new consumer subscribes to the next shard in turn, so consumers waiting on
+/// one channel contend only with the others on their shard.
+///
+/// With an adaptive buffer the main channel is allocated at its largest size, and
+/// consumers treat only the current effective capacity as buffered; falling further
+/// behind counts as lag. `grow_buffer` raises that capacity.
+#[derive(Clone)]
+pub struct EventChannels {
+    shards: Arc<[Shard]>,
+    next_shard: Arc<AtomicUsize>,
+    buffer: Arc<BufferLimit>,
+}
+
+/// Effective capacity of the main channel
+struct BufferLimit {
+    current: AtomicUsize,
+    max: usize,
+}
+
+/// One copy of the channels
+struct Shard {
+    events: Sender<String>,
+    critical: Option<Sender<String>>,
+}
+
+impl Shard {
+    fn channel(&self, critical: bool) -> &Sender<String> {
+        match &self.critical {
+            Some(channel) if critical => channel,
+            _ => &self.events,
+        }
+    }
+}
+
+/// Why `Subscription::recv` returned no message
+#[derive(Debug)]
+pub enum Missed {
+    /// The consumer fell behind and `skipped` messages were dropped for it
+    ///
+    /// `lossy` is true when they were dropped from the trade channel in
+    /// priority mode, where consumers are expected to carry on.
+    Lagged { skipped: u64, lossy: bool },
+    Closed,
+}
+
+impl EventChannels {
+    /// Creates `shards` copies of the channels; `critical_capacity` enables priority mode
+    ///
+    /// The main channel holds `capacity` events, and can grow to `max_capacity` when that is larger.
+    pub fn new(capacity: usize, max_capacity: usize, critical_capacity: Option<usize>, shards: usize) -> Self {
+        let max_capacity = max_capacity.max(capacity);
+        let shards = (0..shards.max(1))
+            .map(|_| Shard {
+                events: broadcast::channel(max_capacity).0,
+                critical: critical_capacity.map(|capacity| broadcast::channel(capacity).0),
+            })
+            .collect();
+        let buffer = BufferLimit { current: AtomicUsize::new(capacity), max: max_capacity };
+        EventChannels { shards, next_shard: Arc::new(AtomicUsize::new(0)), buffer: Arc::new(buffer) }
+    }
+
+    /// Sends a serialized message on the channel for its priority, in every shard
+    ///
+    /// Fails, returning the message, only when nothing is subscribed to that channel in any shard.
+    pub fn send(&self, message: String) -> Result<usize, SendError<String>> {
+        let critical = self.shards[0].critical.is_some() && is_critical(&message);
+        let (last, rest) = self.shards.split_last().expect("at least one shard");
+        let receivers: usize = rest.iter()
+            .filter_map(|shard| shard.channel(critical).send(message.clone()).ok())
+            .sum();
+        match last.channel(critical).send(message) {
+            Ok(count) => Ok(receivers + count),
+            Err(_) if receivers > 0 => Ok(receivers),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Subscribes to the next shard in turn
+    pub fn subscribe(&self) -> Subscription {
+        let index = self.next_shard.fetch_add(1, Ordering::Relaxed) % self.shards.len();
+        let shard = &self.shards[index];
+        Subscription {
+            events: shard.events.subscribe(),
+            critical: shard.critical.as_ref().map(Sender::subscribe),
+            shard: index,
+            buffer: (self.buffer.max > self.buffer_capacity()).then(|| self.buffer.clone()),
+        }
+    }
+
+    /// Events the main channel currently holds for a slow consumer
+    pub fn buffer_capacity(&self) -> usize {
+        self.buffer.current.load(Ordering::Relaxed)
+    }
+
+    /// Doubles the main channel's effective capacity, up to its maximum, returning the
+    /// new capacity, or `None` when it is already at the maximum
+    pub fn grow_buffer(&self) -> Option<usize> {
+        let current = self.buffer_capacity();
+        let grown = current.saturating_mul(2).min(self.buffer.max);
+        (grown > current).then(|| {
+            self.buffer.current.store(grown, Ordering::Relaxed);
+            grown
+        })
+    }
+
+    pub fn shard_count(&self) -> usize {
+        self.shards.len()
+    }
+
+    /// Messages still queued for at least one consumer on the main channel, and on the
+    /// critical channel if any, in whichever shard has the most
+    pub fn queue_depths(&self) -> (usize, Option<usize>) {
+        let depth = self.shards.iter().map(|shard| shard.events.len()).max().unwrap_or(0);
+        let critical_depth = self.shards.iter()
+            .filter_map(|shard| shard.critical.as_ref().map(Sender::len))
+            .max();
+        (depth, critical_depth)
+    }
+
+    /// Consumers subscribed to each shard
+    pub fn shard_receiver_counts(&self) -> Vec<usize> {
+        self.shards.iter().map(|shard| shard.events.receiver_count()).collect()
+    }
+}
+
+/// One consumer's receivers for every channel
+pub struct Subscription {
+    events: Receiver<String>,
+    critical: Option<Receiver<String>>,
+    shard: usize,
+    buffer: Option<Arc<BufferLimit>>,
+}
+
+impl Subscription {
+    /// Receives the next message, taking critical messages first when both channels have one
+    ///
+    /// Cancel safe, like `broadcast::Receiver::recv`.
+    pub async fn recv(&mut self) -> Result<String, Missed> {
+        let lossy = self.critical.is_some();
+        if let Some(skipped) = self.skip_past_buffer() {
+            return Err(Missed::Lagged { skipped, lossy });
+        }
+        let result = match self.critical.as_mut() {
+            Some(critical) => tokio::select! {
+                biased;
+                message = critical.recv() => return message.map_err(|e| missed(e, false)),
+                message = self.events.recv() => message,
+            },
+            None => self.events.recv().await,
+        };
+        result.map_err(|e| missed(e, lossy))
+    }
+
+    /// The shard this consumer was assigned
+    pub fn shard(&self) -> usize {
+        self.shard
+    }
+
+    /// With an adaptive buffer, drops main channel messages beyond its effective capacity,
+    /// as the channel itself would have, returning how many
+    fn skip_past_buffer(&mut self) -> Option<u64> {
+        let capacity = self.buffer.as_ref()?.current.load(Ordering::Relaxed);
+        let excess = self.events.len().checked_sub(capacity).filter(|excess| *excess > 0)?;
+        let skipped = (0..excess).take_while(|_| self.events.try_recv().is_ok()).count();
+        Some(skipped as u64)
+    }
+
+    /// Messages waiting for this consumer across its channels
+    pub fn len(&self) -> usize {
+        self.events.len() + self.critical.as_ref().map_or(0, Receiver::len)
+    }
+}
+
+fn missed(error: RecvError, lossy: bool) -> Missed {
+    match error {
+        RecvError::Lagged(skipped) => Missed::Lagged { skipped, lossy },
+        RecvError::Closed => Missed::Closed,
+    }
+}
+
+/// Launches and control messages; control messages carry a `type` rather than an `event_type`
+fn is_critical(message: &str) -> bool {
+    #[derive(serde::Deserialize)]
+    struct Kind<'a> {
+        #[serde(borrow)]
+        event_type: Option<&'a str>,
+        #[serde(borrow, rename = "type")]
+        control_type: Option<&'a str>,
+    }
+    serde_json::from_str::<Kind>(message).is_ok_and(|kind| match kind.event_type {
+        Some(event_type) => CRITICAL_EVENT_TYPES.contains(&event_type),
+        None => kind.control_type.is_some(),
+    })
+}
@@ -1,17 +1,278 @@
+use std::fmt;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use base64::Engine;
+use log::warn;
+use crate::coarse_clock;
+use crate::solana_client::Cluster;
+use crate::metrics;
+
+pub const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+
+/// The pump.fun program; only accounts it owns are parsed
+pub const PUMP_FUN_PROGRAM_ID: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
+
+// Anchor account discriminators: first 8 bytes of sha256("account:<Name>")
+pub const BONDING_CURVE_DISCRIMINATOR: [u8; 8] = [23, 183, 248, 55, 96, 216, 172, 96];
+const GLOBAL_DISCRIMINATOR: [u8; 8] = [167, 232, 232, 177, 200, 108, 114, 127];
+
+// Anchor event discriminators: first 8 bytes of sha256("event:<Name>")
+const TRADE_EVENT_DISCRIMINATOR: [u8; 8] = [189, 219, 127, 211, 78, 230, 97, 238];
+const CREATE_EVENT_DISCRIMINATOR: [u8; 8] = [27, 114, 169, 77, 222, 235, 99, 118];
+
+/// Decimals of every pump.fun mint
+pub const PUMP_TOKEN_DECIMALS: i32 = 6;
+
+// Whether account events carry their `raw_account`, and the last write version handed out
+static KEEP_RAW_ACCOUNTS: AtomicBool = AtomicBool::new(false);
+static WRITE_VERSION: AtomicU64 = AtomicU64::new(0);
+
+// Most log lines, and bytes of them, scanned per logs notification
+static LOG_SCAN_MAX_LINES: AtomicUsize = AtomicUsize::new(1000);
+static LOG_SCAN_MAX_BYTES: AtomicUsize = AtomicUsize::new(100_000);
+
+/// Attaches the notification's account fields to every account event parsed from now on
+pub fn keep_raw_accounts() {
+    KEEP_RAW_ACCOUNTS.store(true, Ordering::Relaxed);
+}
+
+/// Units used for SOL and token amounts in emitted events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Units {
+    /// Raw integer amounts: lamports for SOL, base units for tokens
+    Lamports,
+    /// Floating-point SOL, and token amounts scaled by the mint's decimals
+    Sol,
+}
+
+impl FromStr for Units {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lamports" => Ok(Units::Lamports),
+            "sol" => Ok(Units::Sol),
+            other => Err(format!("unknown units '{}', expected 'lamports' or 'sol'", other)),
+        }
+    }
+}
+
+impl Units {
+    /// Renders a SOL amount as a JSON value in these units
+    pub fn render_lamports(&self, lamports: u64) -> Value {
+        match self {
+            Units::Lamports => lamports.into(),
+            Units::Sol => (lamports as f64 / LAMPORTS_PER_SOL).into(),
+        }
+    }
+}
+
+impl fmt::Display for Units {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Units::Lamports => "lamports",
+            Units::Sol => "sol",
+        })
+    }
+}
+
+/// How timestamps are written in emitted events
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampFormat {
+    /// RFC 3339 string, e.g. `2024-01-15T10:30:45.123+00:00`
+    Rfc3339,
+    /// Integer milliseconds since the Unix epoch
+    EpochMs,
+    /// Integer seconds since the Unix epoch
+    EpochS,
+}
+
+impl TimestampFormat {
+    /// Renders a timestamp as a JSON value in this format
+    pub fn render(&self, timestamp: DateTime<Utc>) -> Value {
+        match self {
+            TimestampFormat::Rfc3339 => coarse_clock::rfc3339(timestamp).into(),
+            TimestampFormat::EpochMs => timestamp.timestamp_millis().into(),
+            TimestampFormat::EpochS => timestamp.timestamp().into(),
+        }
+    }
+}
+
+impl FromStr for TimestampFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rfc3339" => Ok(TimestampFormat::Rfc3339),
+            "epoch_ms" => Ok(TimestampFormat::EpochMs),
+            "epoch_s" => Ok(TimestampFormat::EpochS),
+            other => Err(format!("unknown timestamp format '{}', expected 'rfc3339', 'epoch_ms' or 'epoch_s'", other)),
+        }
+    }
+}
+
+impl fmt::Display for TimestampFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TimestampFormat::Rfc3339 => "rfc3339",
+            TimestampFormat::EpochMs => "epoch_ms",
+            TimestampFormat::EpochS => "epoch_s",
+        })
+    }
+}
+
+/// Serialization settings shared by every emitted event
+#[derive(Debug, Clone, Copy)]
+pub struct OutputFormat {
+    /// Stamped on every event as `cluster`
+    pub cluster: Cluster,
+    pub units: Units,
+    pub timestamps: TimestampFormat,
+    /// Significant digits kept in prices and market caps; `None` writes them unrounded
+    pub price_digits: Option<u32>,
+}
+
+impl OutputFormat {
+    /// Rounds a price or market cap to the configured significant digits
+    pub fn round_price(&self, value: f64) -> f64 {
+        match self.price_digits {
+            Some(digits) => round_significant(value, digits),
+            None => value,
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug)]
 pub struct TokenEvent {
     pub event_type: String,
-    pub timestamp: String,
+    pub timestamp: DateTime<Utc>,
     pub transaction_signature: String,
+    pub slot: u64,
     pub token: TokenDetails,
     pub pump_data: PumpData,
+    /// OpenTelemetry trace id of the pipeline span that produced this event
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub span_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_account: Option<RawAccount>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// The account fields of the notification an event was decoded from, for the `geyser` output schema
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RawAccount {
+    pub pubkey: String,
+    pub lamports: u64,
+    pub owner: String,
+    pub executable: bool,
+    pub rent_epoch: u64,
+    /// Account data, base64
+    pub data: String,
+    /// Order in which this service parsed the update; the validator's write version is not sent over RPC
+    pub write_version: u64,
+}
+
+impl RawAccount {
+    /// Reads the fields of a notification's account, if raw accounts are kept
+    fn read(pubkey: &str, account: &serde_json::Map<String, Value>) -> Option<Self> {
+        if !KEEP_RAW_ACCOUNTS.load(Ordering::Relaxed) {
+            return None;
+        }
+        Some(RawAccount {
+            pubkey: pubkey.to_string(),
+            lamports: account.get("lamports")?.as_u64()?,
+            owner: account.get("owner")?.as_str()?.to_string(),
+            executable: account.get("executable")?.as_bool()?,
+            rent_epoch: account.get("rentEpoch")?.as_u64()?,
+            data: account.get("data")?.get(0)?.as_str()?.to_string(),
+            write_version: WRITE_VERSION.fetch_add(1, Ordering::Relaxed) + 1,
+        })
+    }
+}
+
+/// Change to the program's global config account (fees and initial curve parameters)
+#[derive(Serialize, Debug)]
+pub struct GlobalUpdate {
+    pub event_type: String,
+    pub timestamp: DateTime<Utc>,
+    pub account: String,
+    pub slot: u64,
+    pub authority: String,
+    pub fee_recipient: String,
+    pub initial_virtual_token_reserves: u64,
+    pub initial_virtual_sol_reserves: u64,
+    pub initial_real_token_reserves: u64,
+    pub token_total_supply: u64,
+    pub fee_basis_points: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub raw_account: Option<RawAccount>,
+}
+
+/// A buy or sell decoded from the `TradeEvent` the program logs for each trade instruction
+///
+/// Unlike account snapshots, this carries the exact executed amounts and the trader's wallet.
+#[derive(Serialize, Debug)]
+pub struct InstructionEvent {
+    /// `buy` or `sell`
+    pub event_type: String,
+    pub timestamp: DateTime<Utc>,
+    pub transaction_signature: String,
+    pub slot: u64,
+    pub mint_address: String,
+    pub trader: String,
+    pub sol_amount: u64,
+    pub token_amount: u64,
+    /// On-chain time the program logged for the trade
+    pub block_time: DateTime<Utc>,
+    /// The curve's virtual reserves once the trade executed
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+}
+
+/// Result of parsing a pump.fun program notification
+#[derive(Debug)]
+pub enum ProgramEvent {
+    /// A bonding curve account changed
+    Token(TokenEvent),
+    /// The global config account changed
+    GlobalUpdate(GlobalUpdate),
+    /// Any other account owned by the program; carries no token event
+    OtherAccount { pubkey: String },
+    /// Launches and trades decoded from a transaction's logs; both empty for failed or
+    /// unrelated transactions
+    Instructions { creates: Vec<CreateEvent>, trades: Vec<InstructionEvent> },
+}
+
+/// A launch's metadata, decoded from the `CreateEvent` the program logs when a mint is created
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateEvent {
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    pub mint: String,
+    pub bonding_curve: String,
+    pub creator: String,
+}
+
+/// `creator` of a curve update whose launch has not been seen
+pub const PLACEHOLDER_CREATOR: &str = "DEF456...";
+
+impl CreateEvent {
+    /// Replaces the token details a bonding curve account can't provide with this launch's
+    pub fn fill(&self, token: &mut TokenDetails) {
+        token.mint_address = self.mint.clone();
+        token.name = self.name.clone();
+        token.symbol = self.symbol.clone();
+        token.creator = self.creator.clone();
+        token.uri = Some(self.uri.clone());
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct TokenDetails {
     pub mint_address: String,
     pub name: String,
@@ -19,17 +280,269 @@ pub struct TokenDetails {
     pub creator: String,
     pub supply: u64,
     pub decimals: u8,
+    /// Metadata URI from the launch's `CreateEvent`, when it was seen
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uri: Option<String>,
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+impl TokenDetails {
+    /// Whether `creator` is the real one from the launch rather than a placeholder
+    pub fn creator_known(&self) -> bool {
+        self.creator != PLACEHOLDER_CREATOR
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct PumpData {
     pub bonding_curve: String,
     pub virtual_sol_reserves: u64,
     pub virtual_token_reserves: u64,
+    #[serde(default)]
+    pub real_sol_reserves: u64,
+    #[serde(default)]
+    pub real_token_reserves: u64,
+    /// Whether the curve has completed and its liquidity migrated
+    #[serde(default)]
+    pub complete: bool,
+    /// Spot price in SOL per whole token, derived from the virtual reserves
+    pub price_sol: f64,
+}
+
+/// Upper bounds beyond which decoded reserves are treated as a decode anomaly
+#[derive(Debug, Clone, Copy)]
+pub struct SanityLimits {
+    pub max_sol_reserves: u64,
+    pub max_token_reserves: u64,
+}
+
+/// Computes the spot price in SOL per whole token from the virtual reserves
+pub fn price_sol(virtual_sol_reserves: u64, virtual_token_reserves: u64, decimals: u8) -> f64 {
+    let sol = virtual_sol_reserves as f64 / LAMPORTS_PER_SOL;
+    let tokens = virtual_token_reserves as f64 / 10f64.powi(decimals as i32);
+    sol / tokens
 }
 
-pub fn parse_event(raw_message: &str) -> Option<String> {
+/// Rounds `value` to `digits` significant digits
+///
+/// Significant digits rather than decimal places, so tiny prices such as
+/// 2.8e-8 SOL keep their precision instead of rounding to zero.
+pub fn round_significant(value: f64, digits: u32) -> f64 {
+    if value == 0.0 || !value.is_finite() {
+        return value;
+    }
+    let magnitude = value.abs().log10().floor() as i32;
+    let exponent = digits as i32 - 1 - magnitude;
+    // Multiply or divide by an exact power of ten; 10^-n is inexact and would leave 123499999.99999999
+    let scale = 10f64.powi(exponent.abs());
+    if !scale.is_finite() {
+        return value;
+    }
+    let rounded = match exponent >= 0 {
+        true => (value * scale).round() / scale,
+        false => (value / scale).round() * scale,
+    };
+    // Rounding up near f64::MAX can overflow
+    if rounded.is_finite() { rounded } else { value }
+}
+
+// pump.fun curves start with 1.073B virtual tokens, of which 793.1M are real and sellable
+const INITIAL_VIRTUAL_TOKEN_RESERVES: u64 = 1_073_000_000_000_000;
+const INITIAL_REAL_TOKEN_RESERVES: u64 = 793_100_000_000_000;
+
+/// Returns how far along its bonding curve a token is, from 0 to 100 percent
+pub fn bonding_curve_progress(virtual_token_reserves: u64) -> f64 {
+    let unsellable = INITIAL_VIRTUAL_TOKEN_RESERVES - INITIAL_REAL_TOKEN_RESERVES;
+    let real_token_reserves = virtual_token_reserves.saturating_sub(unsellable).min(INITIAL_REAL_TOKEN_RESERVES);
+    100.0 * (1.0 - real_token_reserves as f64 / INITIAL_REAL_TOKEN_RESERVES as f64)
+}
+
+/// Returns the market cap in SOL: spot price times total supply in whole tokens
+pub fn market_cap_sol(price_sol: f64, supply: u64, decimals: u8) -> f64 {
+    price_sol * supply as f64 / 10f64.powi(decimals as i32)
+}
+
+/// Market cap in SOL of a broadcast event carrying both a price and the token's supply
+///
+/// Supply is an integer in base units by default, or whole tokens with `UNITS=sol`.
+pub fn event_market_cap_sol(event: &Value) -> Option<f64> {
+    let price_sol = event["pump_data"]["price_sol"].as_f64()?;
+    let supply = &event["token"]["supply"];
+    match supply.as_u64() {
+        Some(supply) => {
+            let decimals = event["token"]["decimals"].as_u64()? as u8;
+            Some(market_cap_sol(price_sol, supply, decimals))
+        }
+        None => Some(price_sol * supply.as_f64()?),
+    }
+}
+
+/// `sol_amount` of a broadcast buy or sell in SOL, whether sent as lamports or with `UNITS=sol`
+pub fn event_sol_amount(event: &Value) -> Option<f64> {
+    let amount = &event["sol_amount"];
+    match amount.as_u64() {
+        Some(lamports) => Some(lamports as f64 / LAMPORTS_PER_SOL),
+        None => amount.as_f64(),
+    }
+}
+
+/// Checks decoded reserves and price for values no real bonding curve can have
+///
+/// Misdecoded accounts (layout mismatches, provider bugs) tend to produce absurd
+/// reserves such as `u64::MAX` or a zero token reserve with an infinite price.
+pub fn check_sanity(event: &TokenEvent, limits: &SanityLimits) -> Result<(), String> {
+    let pump_data = &event.pump_data;
+    if pump_data.virtual_sol_reserves > limits.max_sol_reserves {
+        return Err(format!("virtual_sol_reserves {} exceeds {}", pump_data.virtual_sol_reserves, limits.max_sol_reserves));
+    }
+    if pump_data.virtual_token_reserves > limits.max_token_reserves {
+        return Err(format!("virtual_token_reserves {} exceeds {}", pump_data.virtual_token_reserves, limits.max_token_reserves));
+    }
+    if !pump_data.price_sol.is_finite() || pump_data.price_sol < 0.0 {
+        return Err(format!("implausible price_sol {}", pump_data.price_sol));
+    }
+    Ok(())
+}
+
+/// Checks a trade decoded from logs for amounts and reserves no real bonding curve can have
+pub fn check_trade_sanity(event: &InstructionEvent, limits: &SanityLimits) -> Result<(), String> {
+    if event.sol_amount > limits.max_sol_reserves {
+        return Err(format!("sol_amount {} exceeds {}", event.sol_amount, limits.max_sol_reserves));
+    }
+    if event.token_amount > limits.max_token_reserves {
+        return Err(format!("token_amount {} exceeds {}", event.token_amount, limits.max_token_reserves));
+    }
+    if event.virtual_sol_reserves > limits.max_sol_reserves {
+        return Err(format!("virtual_sol_reserves {} exceeds {}", event.virtual_sol_reserves, limits.max_sol_reserves));
+    }
+    if event.virtual_token_reserves > limits.max_token_reserves {
+        return Err(format!("virtual_token_reserves {} exceeds {}", event.virtual_token_reserves, limits.max_token_reserves));
+    }
+    // No price follows from an empty token reserve
+    if event.virtual_token_reserves == 0 {
+        return Err("virtual_token_reserves is 0".to_string());
+    }
+    Ok(())
+}
+
+// Longest name, symbol and URI the token metadata program accepts for a launch
+const MAX_NAME_LEN: usize = 32;
+const MAX_SYMBOL_LEN: usize = 10;
+const MAX_URI_LEN: usize = 200;
+
+/// Checks a launch decoded from logs for metadata the token metadata program would have refused
+pub fn check_create_sanity(create: &CreateEvent) -> Result<(), String> {
+    for (field, value, max) in [("name", &create.name, MAX_NAME_LEN), ("symbol", &create.symbol, MAX_SYMBOL_LEN), ("uri", &create.uri, MAX_URI_LEN)] {
+        if value.len() > max {
+            return Err(format!("{} of {} bytes exceeds {}", field, value.len(), max));
+        }
+    }
+    Ok(())
+}
+
+impl GlobalUpdate {
+    /// Serializes the update with timestamps in the given format
+    pub fn to_json(&self, format: OutputFormat) -> serde_json::Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        value["timestamp"] = format.timestamps.render(self.timestamp);
+        value["cluster"] = format.cluster.to_string().into();
+        serde_json::to_string(&value)
+    }
+}
+
+impl InstructionEvent {
+    /// Serializes the event with amounts and timestamps in the given format
+    pub fn to_json(&self, format: OutputFormat) -> serde_json::Result<String> {
+        let mut value = serde_json::to_value(self)?;
+        value["timestamp"] = format.timestamps.render(self.timestamp);
+        value["block_time"] = format.timestamps.render(self.block_time);
+        value["cluster"] = format.cluster.to_string().into();
+        if format.units == Units::Sol {
+            value["sol_amount"] = (self.sol_amount as f64 / LAMPORTS_PER_SOL).into();
+            value["token_amount"] = (self.token_amount as f64 / 10f64.powi(PUMP_TOKEN_DECIMALS)).into();
+            value["virtual_sol_reserves"] = (self.virtual_sol_reserves as f64 / LAMPORTS_PER_SOL).into();
+            value["virtual_token_reserves"] = (self.virtual_token_reserves as f64 / 10f64.powi(PUMP_TOKEN_DECIMALS)).into();
+        }
+        serde_json::to_string(&value)
+    }
+}
+
+impl TokenEvent {
+    /// Serializes the event with amounts and timestamps in the given format
+    pub fn to_json(&self, format: OutputFormat) -> serde_json::Result<String> {
+        serde_json::to_string(&self.to_value(format)?)
+    }
+
+    /// Serializes the event as a `token_metadata` event followed by a `curve_created` event
+    ///
+    /// Both carry the mint address and a shared `event_id` so consumers can rejoin them.
+    pub fn to_split_json(&self, format: OutputFormat) -> serde_json::Result<[String; 2]> {
+        let value = self.to_value(format)?;
+        let header = |event_type: &str| {
+            let mut event = serde_json::json!({
+                "event_type": event_type,
+                "cluster": value["cluster"],
+                "event_id": self.transaction_signature,
+                "timestamp": value["timestamp"],
+                "slot": self.slot,
+                "mint_address": self.token.mint_address,
+            });
+            for field in ["trace_id", "span_id"] {
+                if let Some(id) = value.get(field) {
+                    event[field] = id.clone();
+                }
+            }
+            event
+        };
+
+        let mut metadata = header("token_metadata");
+        metadata["token"] = value["token"].clone();
+        let mut curve = header("curve_created");
+        curve["pump_data"] = value["pump_data"].clone();
+        Ok([serde_json::to_string(&metadata)?, serde_json::to_string(&curve)?])
+    }
+
+    /// Serializes the event as a `first_trade` event, `since_creation` after the mint was first seen
+    pub fn to_first_trade_json(&self, format: OutputFormat, since_creation: chrono::Duration) -> serde_json::Result<String> {
+        let mut value = self.to_value(format)?;
+        value["event_type"] = "first_trade".into();
+        value["ms_since_creation"] = since_creation.num_milliseconds().into();
+        serde_json::to_string(&value)
+    }
+
+    /// Serializes the event as a `creator_spam_flagged` event for a creator with `launches` within `window`
+    pub fn to_creator_spam_json(&self, format: OutputFormat, launches: usize, window: std::time::Duration) -> serde_json::Result<String> {
+        let mut value = self.to_value(format)?;
+        value["event_type"] = "creator_spam_flagged".into();
+        value["creator_launches"] = launches.into();
+        value["window_secs"] = window.as_secs().into();
+        serde_json::to_string(&value)
+    }
+
+    fn to_value(&self, format: OutputFormat) -> serde_json::Result<Value> {
+        let mut value = serde_json::to_value(self)?;
+        value["timestamp"] = format.timestamps.render(self.timestamp);
+        value["pump_data"]["price_sol"] = format.round_price(self.pump_data.price_sol).into();
+        value["cluster"] = format.cluster.to_string().into();
+        if format.units == Units::Sol {
+            let token_scale = 10f64.powi(self.token.decimals as i32);
+            value["token"]["supply"] = (self.token.supply as f64 / token_scale).into();
+            value["pump_data"]["virtual_sol_reserves"] = (self.pump_data.virtual_sol_reserves as f64 / LAMPORTS_PER_SOL).into();
+            value["pump_data"]["virtual_token_reserves"] = (self.pump_data.virtual_token_reserves as f64 / token_scale).into();
+            value["pump_data"]["real_sol_reserves"] = (self.pump_data.real_sol_reserves as f64 / LAMPORTS_PER_SOL).into();
+            value["pump_data"]["real_token_reserves"] = (self.pump_data.real_token_reserves as f64 / token_scale).into();
+        }
+        Ok(value)
+    }
+}
+
+pub fn parse_event(raw_message: &str) -> Option<ProgramEvent> {
     let parsed: Value = serde_json::from_str(raw_message).ok()?;
+
+    // Transaction logs from the logsSubscribe subscription
+    if parsed["method"] == "logsNotification" {
+        let (creates, trades) = decode_logs(&parsed["params"]["result"])?;
+        return Some(ProgramEvent::Instructions { creates, trades });
+    }
     
     // Check if this is a program notification (account change)
     if parsed["method"] != "programNotification" {
@@ -40,52 +553,306 @@ pub fn parse_event(raw_message: &str) -> Option<String> {
     let account_data = &parsed["params"]["result"]["value"];
     let pubkey = account_data["pubkey"].as_str()?;
     let slot = parsed["params"]["result"]["context"]["slot"].as_u64()?;
-    
+    parse_program_account(pubkey, &account_data["account"], slot)
+}
+
+/// Explains why `parse_event` rejected a message, or `None` for RPC replies that are not meant to parse
+pub fn parse_failure_reason(raw_message: &str) -> Option<String> {
+    let parsed: Value = match serde_json::from_str(raw_message) {
+        Ok(parsed) => parsed,
+        Err(e) => return Some(format!("invalid JSON: {}", e)),
+    };
+    let method = parsed["method"].as_str()?;
+    let result = &parsed["params"]["result"];
+    let reason = match method {
+        "logsNotification" if result["context"]["slot"].as_u64().is_none() => "logs notification has no slot",
+        "logsNotification" => "logs notification has no signature or undecodable trade events",
+        "programNotification" => {
+            let account = &result["value"]["account"];
+            let owner = account["owner"].as_str();
+            if result["value"]["pubkey"].as_str().is_none() {
+                "program notification has no pubkey"
+            } else if result["context"]["slot"].as_u64().is_none() {
+                "program notification has no slot"
+            } else if owner.is_none() {
+                "account has no owner"
+            } else if owner != Some(PUMP_FUN_PROGRAM_ID) {
+                return Some(format!("account is owned by {}, not the pump.fun program", owner.unwrap_or_default()));
+            } else {
+                match account.as_object().and_then(decode_account_data) {
+                    None => "account data is not base64",
+                    Some(data) if data.starts_with(&GLOBAL_DISCRIMINATOR) => "global account data is too short",
+                    Some(_) => "bonding curve data is too short or its complete flag is not a bool",
+                }
+            }
+        }
+        other if other.ends_with("Notification") => return Some(format!("unexpected notification method {}", other)),
+        _ => return None,
+    };
+    Some(reason.to_string())
+}
+
+/// Returns true if `address` is base58 that decodes to a 32-byte Solana public key
+pub fn is_valid_pubkey(address: &str) -> bool {
+    let mut bytes = [0u8; 32];
+    matches!(bs58::decode(address).onto(&mut bytes), Ok(32))
+}
+
+/// Decodes one program account, as found in notifications and `getProgramAccounts` results
+pub fn parse_program_account(pubkey: &str, account: &Value, slot: u64) -> Option<ProgramEvent> {
     // Check if this is a pump.fun program account change
-    if let Some(account) = account_data["account"].as_object() {
-        let owner = account["owner"].as_str()?;
+    if let Some(account) = account.as_object() {
+        let owner = account.get("owner")?.as_str()?;
         
         // Only process pump.fun program account changes
-        if owner == "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P" {
-            return extract_pump_fun_account_data(pubkey, account, slot);
+        if owner == PUMP_FUN_PROGRAM_ID {
+            // The program owns several account types; only bonding curves describe tokens
+            let data = decode_account_data(account)?;
+            return match data.get(..8) {
+                Some(discriminator) if discriminator == BONDING_CURVE_DISCRIMINATOR => {
+                    extract_pump_fun_account_data(pubkey, &data, slot).map(|event| ProgramEvent::Token(TokenEvent {
+                        raw_account: RawAccount::read(pubkey, account),
+                        ..event
+                    }))
+                }
+                Some(discriminator) if discriminator == GLOBAL_DISCRIMINATOR => {
+                    decode_global_account(pubkey, &data, slot).map(|update| ProgramEvent::GlobalUpdate(GlobalUpdate {
+                        raw_account: RawAccount::read(pubkey, account),
+                        ..update
+                    }))
+                }
+                _ => Some(ProgramEvent::OtherAccount { pubkey: pubkey.to_string() }),
+            };
         }
     }
     
     None
 }
 
-fn extract_pump_fun_account_data(pubkey: &str, account: &serde_json::Map<String, Value>, slot: u64) -> Option<String> {
-    // Generate a mock transaction signature based on the pubkey and slot
-    let transaction_signature = format!("{}_{}", pubkey[..8].to_string(), slot);
-    
-    // Extract data from account (this is a simplified example since we don't have the exact data structure)
-    // In a real implementation, you would decode the base64 data according to pump.fun's account structure
+/// Decodes every `CreateEvent` and `TradeEvent` in a logs notification result
+pub fn decode_logs(result: &Value) -> Option<(Vec<CreateEvent>, Vec<InstructionEvent>)> {
+    let slot = result["context"]["slot"].as_u64()?;
+    let value = &result["value"];
+    let signature = value["signature"].as_str()?;
+    let logs = value["logs"].as_array()?;
+    // A failed transaction's events were rolled back with it
+    if !value["err"].is_null() {
+        return Some((Vec::new(), Vec::new()));
+    }
+
+    let mut creates = Vec::new();
+    let mut trades = Vec::new();
+    for data in program_data(logs) {
+        if let Some(create) = decode_create_event(&data) {
+            creates.push(create);
+        } else if let Some(trade) = decode_trade_event(&data, signature, slot) {
+            trades.push(trade);
+        }
+    }
+    Some((creates, trades))
+}
+
+/// Sets how many log lines, and how many bytes of them, are scanned per logs notification
+pub fn set_log_scan_limits(max_lines: usize, max_bytes: usize) {
+    LOG_SCAN_MAX_LINES.store(max_lines, Ordering::Relaxed);
+    LOG_SCAN_MAX_BYTES.store(max_bytes, Ordering::Relaxed);
+}
+
+/// The decoded payload of every `Program data: ` log line, which is how Anchor emits events
+///
+/// Stops at the scan limits, so an oversized log array costs no more than a full one.
+fn program_data(logs: &[Value]) -> Vec<Vec<u8>> {
+    let max_lines = LOG_SCAN_MAX_LINES.load(Ordering::Relaxed);
+    let max_bytes = LOG_SCAN_MAX_BYTES.load(Ordering::Relaxed);
+    let mut bytes = 0;
+    let mut payloads = Vec::new();
+    for (index, line) in logs.iter().enumerate() {
+        let line = line.as_str().unwrap_or_default();
+        bytes += line.len();
+        if index == max_lines || bytes > max_bytes {
+            let truncated = metrics::record_logs_truncated();
+            // Log the first truncation and then every 100th to keep the log readable
+            if truncated % 100 == 1 {
+                warn!("Scanned only {} of {} log lines, the limit is {} lines or {} bytes", index, logs.len(), max_lines, max_bytes);
+            }
+            break;
+        }
+        if let Some(data) = line.strip_prefix("Program data: ") {
+            payloads.extend(base64::engine::general_purpose::STANDARD.decode(data).ok());
+        }
+    }
+    payloads
+}
+
+/// Decodes the leading fields of an Anchor `CreateEvent`, or returns None for any other event
+fn decode_create_event(data: &[u8]) -> Option<CreateEvent> {
+    if data.get(..8)? != CREATE_EVENT_DISCRIMINATOR {
+        return None;
+    }
+    let mut offset = 8;
+    // Borsh strings are a u32 length followed by that many UTF-8 bytes
+    let mut read_string = || {
+        let len = u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().unwrap()) as usize;
+        let bytes = data.get(offset + 4..(offset + 4).checked_add(len)?)?;
+        offset += 4 + len;
+        String::from_utf8(bytes.to_vec()).ok()
+    };
+
+    // Layout after the discriminator: name, symbol, uri, then mint, bonding_curve and user pubkeys
+    let name = read_string()?;
+    let symbol = read_string()?;
+    let uri = read_string()?;
+    let read_pubkey = |index: usize| {
+        let start = offset + index * 32;
+        data.get(start..start + 32).map(|key| bs58::encode(key).into_string())
+    };
+    Some(CreateEvent {
+        name,
+        symbol,
+        uri,
+        mint: read_pubkey(0)?,
+        bonding_curve: read_pubkey(1)?,
+        creator: read_pubkey(2)?,
+    })
+}
+
+/// Decodes the leading fields of an Anchor `TradeEvent`, or returns None for any other event
+fn decode_trade_event(data: &[u8], signature: &str, slot: u64) -> Option<InstructionEvent> {
+    if data.get(..8)? != TRADE_EVENT_DISCRIMINATOR {
+        return None;
+    }
+    let read_pubkey = |offset: usize| data.get(offset..offset + 32).map(|key| bs58::encode(key).into_string());
+    let read_u64 = |offset: usize| data.get(offset..offset + 8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+
+    // Layout after the discriminator: mint, sol_amount, token_amount, is_buy (bool), user, timestamp (i64),
+    // virtual_sol_reserves, virtual_token_reserves
+    let is_buy = match *data.get(56)? {
+        0 => false,
+        1 => true,
+        _ => return None,
+    };
+    let block_time = data.get(89..97).map(|bytes| i64::from_le_bytes(bytes.try_into().unwrap()))?;
+    Some(InstructionEvent {
+        event_type: if is_buy { "buy" } else { "sell" }.to_string(),
+        timestamp: coarse_clock::now(),
+        transaction_signature: signature.to_string(),
+        slot,
+        mint_address: read_pubkey(8)?,
+        trader: read_pubkey(57)?,
+        sol_amount: read_u64(40)?,
+        token_amount: read_u64(48)?,
+        block_time: DateTime::from_timestamp(block_time, 0)?,
+        virtual_sol_reserves: read_u64(97)?,
+        virtual_token_reserves: read_u64(105)?,
+    })
+}
+
+/// Decodes the raw account bytes, sent as `["<base64>", "base64"]` for program-owned accounts
+fn decode_account_data(account: &serde_json::Map<String, Value>) -> Option<Vec<u8>> {
+    let data = account.get("data")?.as_array()?;
+    if data.get(1)?.as_str()? != "base64" {
+        return None;
+    }
+    base64::engine::general_purpose::STANDARD.decode(data.first()?.as_str()?).ok()
+}
+
+/// The program's `BondingCurve` account
+struct BondingCurve {
+    virtual_token_reserves: u64,
+    virtual_sol_reserves: u64,
+    real_token_reserves: u64,
+    real_sol_reserves: u64,
+    token_total_supply: u64,
+    complete: bool,
+}
+
+/// Decodes the Borsh-serialized fields of a `BondingCurve` account, ignoring any added after `complete`
+fn decode_bonding_curve(data: &[u8]) -> Option<BondingCurve> {
+    let read_u64 = |offset: usize| data.get(offset..offset + 8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+
+    // Layout after the discriminator: five u64 amounts, then complete (bool)
+    let complete = match *data.get(48)? {
+        0 => false,
+        1 => true,
+        _ => return None,
+    };
+    Some(BondingCurve {
+        virtual_token_reserves: read_u64(8)?,
+        virtual_sol_reserves: read_u64(16)?,
+        real_token_reserves: read_u64(24)?,
+        real_sol_reserves: read_u64(32)?,
+        token_total_supply: read_u64(40)?,
+        complete,
+    })
+}
+
+/// Decodes the leading fields of the program's `Global` account
+fn decode_global_account(pubkey: &str, data: &[u8], slot: u64) -> Option<GlobalUpdate> {
+    let read_pubkey = |offset: usize| data.get(offset..offset + 32).map(|key| bs58::encode(key).into_string());
+    let read_u64 = |offset: usize| data.get(offset..offset + 8).map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+
+    // Layout after the discriminator: initialized (bool), authority, fee_recipient, then u64 parameters
+    Some(GlobalUpdate {
+        event_type: "global_update".to_string(),
+        timestamp: coarse_clock::now(),
+        account: pubkey.to_string(),
+        slot,
+        authority: read_pubkey(9)?,
+        fee_recipient: read_pubkey(41)?,
+        initial_virtual_token_reserves: read_u64(73)?,
+        initial_virtual_sol_reserves: read_u64(81)?,
+        initial_real_token_reserves: read_u64(89)?,
+        token_total_supply: read_u64(97)?,
+        fee_basis_points: read_u64(105)?,
+        raw_account: None,
+    })
+}
+
+/// Builds a token event for a bonding curve account update
+///
+/// Reserves, supply and `complete` are decoded from the account; the mint, name,
+/// symbol and creator are not stored in it and are placeholders until
+/// `launch_metadata` fills them in from the launch's `CreateEvent`.
+fn extract_pump_fun_account_data(pubkey: &str, data: &[u8], slot: u64) -> Option<TokenEvent> {
+    // Account notifications carry no signature, so this one is synthetic: pubkey prefix and slot
+    let prefix = pubkey.get(..8).unwrap_or(pubkey);
+    let transaction_signature = format!("{}_{}", prefix, slot);
+    let curve = decode_bonding_curve(data)?;
+
     let token_details = TokenDetails {
         mint_address: pubkey.to_string(),
-        name: format!("Token_{}", &pubkey[..8]),
+        name: format!("Token_{}", prefix),
         symbol: "MTK".to_string(),
-        creator: "DEF456...".to_string(),
-        supply: 1_000_000_000,
-        decimals: 6,
+        creator: PLACEHOLDER_CREATOR.to_string(),
+        supply: curve.token_total_supply,
+        decimals: PUMP_TOKEN_DECIMALS as u8,
+        uri: None,
     };
-    
+
     let pump_data = PumpData {
-        bonding_curve: "GHI789...".to_string(),
-        virtual_sol_reserves: 30_000_000_000,
-        virtual_token_reserves: 1_073_000_000_000_000,
+        bonding_curve: pubkey.to_string(),
+        virtual_sol_reserves: curve.virtual_sol_reserves,
+        virtual_token_reserves: curve.virtual_token_reserves,
+        real_sol_reserves: curve.real_sol_reserves,
+        real_token_reserves: curve.real_token_reserves,
+        complete: curve.complete,
+        price_sol: price_sol(curve.virtual_sol_reserves, curve.virtual_token_reserves, token_details.decimals),
     };
-    
-    let event = TokenEvent {
+
+    Some(TokenEvent {
         event_type: "token_created".to_string(),
-        timestamp: Utc::now().to_rfc3339(),
+        timestamp: coarse_clock::now(),
         transaction_signature,
+        slot,
         token: token_details,
         pump_data,
-    };
-    
-    serde_json::to_string(&event).ok()
+        trace_id: None,
+        span_id: None,
+        raw_account: None,
+    })
 }
 
+#[allow(dead_code)] // log-based parsing is not wired into the subscription yet
 fn extract_pump_fun_data(log_message: &str, signature: String, slot: u64) -> Option<String> {
     // Extract mint address from logs (this is a simplified example)
     let mint_address = extract_mint_address(log_message)
@@ -99,20 +866,31 @@ fn extract_pump_fun_data(log_message: &str, signature: String, slot: u64) -> Opt
         creator: extract_creator(log_message).unwrap_or_else(|| "unknown".to_string()),
         supply: extract_supply(log_message).unwrap_or(1_000_000_000),
         decimals: extract_decimals(log_message).unwrap_or(6),
+        uri: None,
     };
     
+    let virtual_sol_reserves = extract_virtual_sol_reserves(log_message).unwrap_or(30_000_000_000);
+    let virtual_token_reserves = extract_virtual_token_reserves(log_message).unwrap_or(1_073_000_000_000_000);
     let pump_data = PumpData {
         bonding_curve: extract_bonding_curve(log_message).unwrap_or_else(|| "curve_unknown".to_string()),
-        virtual_sol_reserves: extract_virtual_sol_reserves(log_message).unwrap_or(30_000_000_000),
-        virtual_token_reserves: extract_virtual_token_reserves(log_message).unwrap_or(1_073_000_000_000_000),
+        virtual_sol_reserves,
+        virtual_token_reserves,
+        real_sol_reserves: 0,
+        real_token_reserves: 0,
+        complete: false,
+        price_sol: price_sol(virtual_sol_reserves, virtual_token_reserves, token_details.decimals),
     };
     
     let event = TokenEvent {
         event_type: "token_created".to_string(),
-        timestamp: Utc::now().to_rfc3339(),
+        timestamp: coarse_clock::now(),
         transaction_signature: signature,
+        slot,
         token: token_details,
         pump_data,
+        trace_id: None,
+        span_id: None,
+        raw_account: None,
     };
     
     serde_json::to_string(&event).ok()
@@ -216,4 +994,397 @@ fn extract_virtual_token_reserves(log: &str) -> Option<u64> {
         }
     }
     None
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    const CURVE: &str = "4Hw1dSRnaN5ryEaBUyTXGcNEmqSmLcuVhpgmTTdcLRGM";
+
+    /// A `BondingCurve` account's data: discriminator, five u64 amounts and `complete`
+    fn curve_data(reserves: [u64; 5], complete: u8) -> Vec<u8> {
+        let mut data = BONDING_CURVE_DISCRIMINATOR.to_vec();
+        for amount in reserves {
+            data.extend_from_slice(&amount.to_le_bytes());
+        }
+        data.push(complete);
+        data
+    }
+
+    fn program_notification(account: Value) -> String {
+        json!({
+            "jsonrpc": "2.0",
+            "method": "programNotification",
+            "params": {"result": {"context": {"slot": 100}, "value": {"pubkey": CURVE, "account": account}}, "subscription": 42},
+        }).to_string()
+    }
+
+    fn curve_account(owner: Option<&str>) -> Value {
+        let data = curve_data([1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, 0, 1_000_000_000_000_000], 0);
+        let mut account = json!({
+            "data": [base64::engine::general_purpose::STANDARD.encode(data), "base64"],
+            "executable": false,
+            "lamports": 1_000_000,
+            "rentEpoch": 0,
+        });
+        if let Some(owner) = owner {
+            account["owner"] = owner.into();
+        }
+        account
+    }
+
+    /// A `TradeEvent` as logged for a buy of 17,241,379.310344 tokens for 0.5 SOL
+    fn trade_event_data() -> Vec<u8> {
+        let mut data = TRADE_EVENT_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&[1; 32]);
+        data.extend_from_slice(&500_000_000u64.to_le_bytes());
+        data.extend_from_slice(&17_241_379_310_344u64.to_le_bytes());
+        data.push(1);
+        data.extend_from_slice(&[2; 32]);
+        data.extend_from_slice(&1_700_000_000i64.to_le_bytes());
+        data.extend_from_slice(&31_000_000_000u64.to_le_bytes());
+        data.extend_from_slice(&1_038_275_862_068_966u64.to_le_bytes());
+        data
+    }
+
+    fn logs_result(err: Value, logs: Vec<String>) -> Value {
+        json!({"context": {"slot": 300}, "value": {"signature": "5x7K8", "err": err, "logs": logs}})
+    }
+
+    fn program_data_line(data: &[u8]) -> String {
+        format!("Program data: {}", base64::engine::general_purpose::STANDARD.encode(data))
+    }
+
+    #[test]
+    fn account_owned_by_another_program_is_ignored() {
+        let message = program_notification(curve_account(Some("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA")));
+        assert!(parse_event(&message).is_none());
+    }
+
+    #[test]
+    fn account_without_owner_is_ignored() {
+        assert!(parse_event(&program_notification(curve_account(None))).is_none());
+    }
+
+    #[test]
+    fn pump_fun_curve_account_becomes_token_event() {
+        let message = program_notification(curve_account(Some(PUMP_FUN_PROGRAM_ID)));
+        let Some(ProgramEvent::Token(event)) = parse_event(&message) else { panic!("expected a token event") };
+        assert_eq!(event.slot, 100);
+        assert_eq!(event.pump_data.bonding_curve, CURVE);
+    }
+
+    #[test]
+    fn trade_event_fields_decode_at_their_offsets() {
+        let logs = vec!["Program log: Instruction: Buy".to_string(), program_data_line(&trade_event_data())];
+        let events = decode_logs(&logs_result(Value::Null, logs)).unwrap().1;
+        assert_eq!(events.len(), 1);
+        let trade = &events[0];
+        assert_eq!(trade.event_type, "buy");
+        assert_eq!(trade.transaction_signature, "5x7K8");
+        assert_eq!(trade.slot, 300);
+        assert_eq!(trade.mint_address, bs58::encode([1; 32]).into_string());
+        assert_eq!(trade.trader, bs58::encode([2; 32]).into_string());
+        assert_eq!(trade.sol_amount, 500_000_000);
+        assert_eq!(trade.token_amount, 17_241_379_310_344);
+        assert_eq!(trade.block_time.timestamp(), 1_700_000_000);
+        assert_eq!(trade.virtual_sol_reserves, 31_000_000_000);
+        assert_eq!(trade.virtual_token_reserves, 1_038_275_862_068_966);
+    }
+
+    #[test]
+    fn sell_has_is_buy_unset() {
+        let mut data = trade_event_data();
+        data[56] = 0;
+        let events = decode_logs(&logs_result(Value::Null, vec![program_data_line(&data)])).unwrap().1;
+        assert_eq!(events[0].event_type, "sell");
+    }
+
+    #[test]
+    fn is_buy_other_than_0_or_1_is_not_a_trade() {
+        let mut data = trade_event_data();
+        data[56] = 2;
+        let (creates, trades) = decode_logs(&logs_result(Value::Null, vec![program_data_line(&data)])).unwrap();
+        assert!(creates.is_empty() && trades.is_empty());
+    }
+
+    #[test]
+    fn failed_transaction_has_no_trades() {
+        let logs = vec![program_data_line(&trade_event_data())];
+        let (creates, trades) = decode_logs(&logs_result(json!({"InstructionError": [0, {"Custom": 6002}]}), logs)).unwrap();
+        assert!(creates.is_empty() && trades.is_empty());
+    }
+
+    #[test]
+    fn truncated_trade_event_is_skipped() {
+        let data = trade_event_data();
+        for len in [8, 56, 97, data.len() - 1] {
+            let events = decode_logs(&logs_result(Value::Null, vec![program_data_line(&data[..len])])).unwrap().1;
+            assert!(events.is_empty(), "decoded a trade from {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn oversized_log_array_is_scanned_only_up_to_the_limits() {
+        // The default limits: 1000 lines or 100,000 bytes
+        let trade = program_data_line(&trade_event_data());
+        let mut logs = vec![trade.clone()];
+        logs.extend(std::iter::repeat_n("Program log: Instruction: Buy".to_string(), 998));
+        logs.push(trade.clone());
+        logs.extend(std::iter::repeat_n(trade.clone(), 500_000));
+        let truncated = metrics::stats()["logs_truncated"].as_u64().unwrap();
+        let started = std::time::Instant::now();
+        let trades = decode_logs(&logs_result(Value::Null, logs)).unwrap().1;
+        assert_eq!(trades.len(), 2);
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+        assert!(metrics::stats()["logs_truncated"].as_u64().unwrap() > truncated);
+
+        // A few huge lines hit the byte limit before the line limit
+        let mut logs = vec![trade.clone()];
+        logs.extend(std::iter::repeat_n("x".repeat(60_000), 100));
+        logs.push(trade);
+        assert_eq!(decode_logs(&logs_result(Value::Null, logs)).unwrap().1.len(), 1);
+    }
+
+    // An 81-byte curve account after a first buy, with the creator newer accounts append after `complete`
+    const CURVE_ACCOUNT_BASE64: &str = "F7f4N2DYrGA9YlTfA7MDADH8vjIHAAAAPcpBk3K0AgAxUJs2AAAAAACAxqR+jQMAAAABAgMEBQYHCAkKCwwNDg8QERITFBUWFxgZGhscHR4f";
+
+    #[test]
+    fn bonding_curve_fields_decode_at_their_offsets() {
+        let data = base64::engine::general_purpose::STANDARD.decode(CURVE_ACCOUNT_BASE64).unwrap();
+        let curve = decode_bonding_curve(&data).unwrap();
+        assert_eq!(curve.virtual_token_reserves, 1_041_254_143_255_101);
+        assert_eq!(curve.virtual_sol_reserves, 30_916_148_273);
+        assert_eq!(curve.real_token_reserves, 761_354_143_255_101);
+        assert_eq!(curve.real_sol_reserves, 916_148_273);
+        assert_eq!(curve.token_total_supply, 1_000_000_000_000_000);
+        assert!(!curve.complete);
+
+        let event = extract_pump_fun_account_data(CURVE, &data, 100).unwrap();
+        assert_eq!(event.token.supply, 1_000_000_000_000_000);
+        assert_eq!(event.pump_data.real_sol_reserves, 916_148_273);
+        assert_eq!(event.transaction_signature, "4Hw1dSRn_100");
+    }
+
+    #[test]
+    fn complete_flag_must_be_a_bool() {
+        let reserves = [0, 85_000_000_000, 0, 85_000_000_000, 1_000_000_000_000_000];
+        assert!(decode_bonding_curve(&curve_data(reserves, 1)).unwrap().complete);
+        assert!(decode_bonding_curve(&curve_data(reserves, 2)).is_none());
+    }
+
+    #[test]
+    fn short_bonding_curve_data_is_rejected() {
+        let data = base64::engine::general_purpose::STANDARD.decode(CURVE_ACCOUNT_BASE64).unwrap();
+        for len in [8, 40, 48] {
+            assert!(decode_bonding_curve(&data[..len]).is_none(), "decoded a curve from {} bytes", len);
+            assert!(extract_pump_fun_account_data(CURVE, &data[..len], 100).is_none());
+        }
+    }
+
+    #[test]
+    fn short_or_non_ascii_pubkey_does_not_panic() {
+        let data = base64::engine::general_purpose::STANDARD.decode(CURVE_ACCOUNT_BASE64).unwrap();
+        assert_eq!(extract_pump_fun_account_data("abc", &data, 7).unwrap().transaction_signature, "abc_7");
+        assert!(extract_pump_fun_account_data("ééééé", &data, 7).is_some());
+    }
+
+    /// A `CreateEvent`: Borsh strings name, symbol and uri, then mint, bonding_curve and user
+    fn create_event_data() -> Vec<u8> {
+        let mut data = CREATE_EVENT_DISCRIMINATOR.to_vec();
+        for text in ["Pepe Coin", "PEPE", "https://ipfs.io/ipfs/QmPepe"] {
+            data.extend_from_slice(&(text.len() as u32).to_le_bytes());
+            data.extend_from_slice(text.as_bytes());
+        }
+        for key in [3u8, 4, 5] {
+            data.extend_from_slice(&[key; 32]);
+        }
+        data
+    }
+
+    const LIMITS: SanityLimits = SanityLimits { max_sol_reserves: 10_000_000_000_000, max_token_reserves: 2_000_000_000_000_000 };
+
+    #[test]
+    fn decoded_trade_passes_the_sanity_checks() {
+        let trade = decode_trade_event(&trade_event_data(), "5x7K8", 300).unwrap();
+        assert_eq!(check_trade_sanity(&trade, &LIMITS), Ok(()));
+    }
+
+    #[test]
+    fn garbage_trade_fails_the_sanity_checks() {
+        for (offset, value) in [(40, u64::MAX), (48, u64::MAX), (97, u64::MAX), (105, u64::MAX), (105, 0)] {
+            let mut data = trade_event_data();
+            data[offset..offset + 8].copy_from_slice(&value.to_le_bytes());
+            let trade = decode_trade_event(&data, "5x7K8", 300).unwrap();
+            assert!(check_trade_sanity(&trade, &LIMITS).is_err(), "offset {} = {}", offset, value);
+        }
+    }
+
+    #[test]
+    fn create_with_oversized_metadata_fails_the_sanity_checks() {
+        let mut create = decode_create_event(&create_event_data()).unwrap();
+        assert_eq!(check_create_sanity(&create), Ok(()));
+        create.symbol = "S".repeat(MAX_SYMBOL_LEN + 1);
+        assert!(check_create_sanity(&create).is_err());
+    }
+
+    #[test]
+    fn pubkeys_must_be_base58_of_32_bytes() {
+        assert!(is_valid_pubkey(CURVE));
+        assert!(is_valid_pubkey(PUMP_FUN_PROGRAM_ID));
+        assert!(!is_valid_pubkey("4Hw1dSRnaN5ryEaBUyTXGcNEmqSmLcuVhpgmTTdcL0Il"));
+        assert!(!is_valid_pubkey("not a pubkey"));
+        assert!(!is_valid_pubkey(""));
+        assert!(!is_valid_pubkey(&bs58::encode([7; 31]).into_string()));
+        assert!(!is_valid_pubkey(&bs58::encode([7; 33]).into_string()));
+    }
+
+    #[test]
+    fn create_event_fields_decode_in_order() {
+        let create = decode_create_event(&create_event_data()).unwrap();
+        assert_eq!(create, CreateEvent {
+            name: "Pepe Coin".to_string(),
+            symbol: "PEPE".to_string(),
+            uri: "https://ipfs.io/ipfs/QmPepe".to_string(),
+            mint: bs58::encode([3; 32]).into_string(),
+            bonding_curve: bs58::encode([4; 32]).into_string(),
+            creator: bs58::encode([5; 32]).into_string(),
+        });
+    }
+
+    #[test]
+    fn truncated_create_event_is_skipped() {
+        let data = create_event_data();
+        for len in [8, 11, 20, data.len() - 33, data.len() - 1] {
+            assert!(decode_create_event(&data[..len]).is_none(), "decoded a create from {} bytes", len);
+        }
+    }
+
+    #[test]
+    fn oversized_string_length_is_rejected() {
+        let mut data = create_event_data();
+        data[8..12].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(decode_create_event(&data).is_none());
+        let mut data = create_event_data();
+        data[12] = 0xff;
+        assert!(decode_create_event(&data).is_none(), "accepted a name that is not UTF-8");
+    }
+
+    #[test]
+    fn logs_with_a_launch_and_a_trade_decode_both() {
+        let logs = vec![program_data_line(&create_event_data()), program_data_line(&trade_event_data())];
+        let (creates, trades) = decode_logs(&logs_result(Value::Null, logs)).unwrap();
+        assert_eq!(creates.len(), 1);
+        assert_eq!(trades.len(), 1);
+        assert_eq!(creates[0].symbol, "PEPE");
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(((actual - expected) / expected).abs() < 1e-12, "{} is not {}", actual, expected);
+    }
+
+    #[test]
+    fn round_significant_keeps_tiny_prices() {
+        assert_eq!(round_significant(0.0, 4), 0.0);
+        assert_eq!(round_significant(2.7958993476234855e-8, 4), 2.796e-8);
+        assert_eq!(round_significant(1.23456789e-10, 3), 1.23e-10);
+        assert_eq!(round_significant(-9.87654e-12, 2), -9.9e-12);
+        assert_close(round_significant(4.56789e-300, 3), 4.57e-300);
+        // Subnormal values have no representable scale and are left as they are
+        assert_eq!(round_significant(1e-320, 3), 1e-320);
+    }
+
+    #[test]
+    fn round_significant_handles_large_values() {
+        assert_eq!(round_significant(123_456_789.0, 4), 123_500_000.0);
+        assert_eq!(round_significant(999_999.0, 3), 1_000_000.0);
+        assert_close(round_significant(1.23456e300, 3), 1.23e300);
+        assert_eq!(round_significant(f64::MAX, 3), f64::MAX);
+        assert!(round_significant(f64::INFINITY, 3).is_infinite());
+        assert!(round_significant(f64::NAN, 3).is_nan());
+    }
+
+    #[test]
+    fn serialized_events_carry_the_cluster() {
+        let format = OutputFormat { cluster: Cluster::Devnet, units: Units::Lamports, timestamps: TimestampFormat::Rfc3339, price_digits: None };
+        let data = base64::engine::general_purpose::STANDARD.decode(CURVE_ACCOUNT_BASE64).unwrap();
+        let event = extract_pump_fun_account_data(CURVE, &data, 100).unwrap();
+        let json: Value = serde_json::from_str(&event.to_json(format).unwrap()).unwrap();
+        assert_eq!(json["cluster"], "devnet");
+        for part in event.to_split_json(format).unwrap() {
+            assert_eq!(serde_json::from_str::<Value>(&part).unwrap()["cluster"], "devnet");
+        }
+
+        let logs = vec![program_data_line(&trade_event_data())];
+        let trade = &decode_logs(&logs_result(Value::Null, logs)).unwrap().1[0];
+        assert_eq!(serde_json::from_str::<Value>(&trade.to_json(format).unwrap()).unwrap()["cluster"], "devnet");
+    }
+
+    fn output_format(units: Units, timestamps: TimestampFormat) -> OutputFormat {
+        OutputFormat { cluster: Cluster::Mainnet, units, timestamps, price_digits: None }
+    }
+
+    fn curve_event_json(units: Units) -> Value {
+        let data = base64::engine::general_purpose::STANDARD.decode(CURVE_ACCOUNT_BASE64).unwrap();
+        let event = extract_pump_fun_account_data(CURVE, &data, 100).unwrap();
+        serde_json::from_str(&event.to_json(output_format(units, TimestampFormat::Rfc3339)).unwrap()).unwrap()
+    }
+
+    fn trade_json(units: Units) -> Value {
+        let trade = decode_trade_event(&trade_event_data(), "5x7K8", 300).unwrap();
+        serde_json::from_str(&trade.to_json(output_format(units, TimestampFormat::Rfc3339)).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn each_timestamp_format_renders_the_same_instant() {
+        let timestamp = DateTime::from_timestamp_millis(1_705_314_645_123).unwrap();
+        assert_eq!(TimestampFormat::Rfc3339.render(timestamp), "2024-01-15T10:30:45.123+00:00");
+        assert_eq!(TimestampFormat::EpochMs.render(timestamp), 1_705_314_645_123i64);
+        assert_eq!(TimestampFormat::EpochS.render(timestamp), 1_705_314_645i64);
+        for format in [TimestampFormat::Rfc3339, TimestampFormat::EpochMs, TimestampFormat::EpochS] {
+            assert_eq!(format.to_string().parse::<TimestampFormat>(), Ok(format));
+        }
+    }
+
+    #[test]
+    fn events_carry_timestamps_in_the_configured_format() {
+        let mut trade = decode_trade_event(&trade_event_data(), "5x7K8", 300).unwrap();
+        trade.timestamp = DateTime::from_timestamp_millis(1_705_314_645_123).unwrap();
+        let json: Value = serde_json::from_str(&trade.to_json(output_format(Units::Lamports, TimestampFormat::EpochMs)).unwrap()).unwrap();
+        assert_eq!(json["timestamp"], 1_705_314_645_123i64);
+        assert_eq!(json["block_time"], 1_700_000_000_000i64);
+    }
+
+    #[test]
+    fn lamport_units_keep_raw_amounts() {
+        let json = curve_event_json(Units::Lamports);
+        assert_eq!(json["token"]["supply"], 1_000_000_000_000_000u64);
+        assert_eq!(json["pump_data"]["virtual_sol_reserves"], 30_916_148_273u64);
+        assert_eq!(json["pump_data"]["virtual_token_reserves"], 1_041_254_143_255_101u64);
+        assert_eq!(json["pump_data"]["real_sol_reserves"], 916_148_273u64);
+        assert_eq!(json["pump_data"]["real_token_reserves"], 761_354_143_255_101u64);
+
+        let trade = trade_json(Units::Lamports);
+        assert_eq!(trade["sol_amount"], 500_000_000u64);
+        assert_eq!(trade["token_amount"], 17_241_379_310_344u64);
+        assert_eq!(trade["virtual_sol_reserves"], 31_000_000_000u64);
+        assert_eq!(trade["virtual_token_reserves"], 1_038_275_862_068_966u64);
+    }
+
+    #[test]
+    fn sol_units_scale_amounts_by_their_decimals() {
+        let json = curve_event_json(Units::Sol);
+        assert_close(json["token"]["supply"].as_f64().unwrap(), 1_000_000_000.0);
+        assert_close(json["pump_data"]["virtual_sol_reserves"].as_f64().unwrap(), 30.916148273);
+        assert_close(json["pump_data"]["virtual_token_reserves"].as_f64().unwrap(), 1_041_254_143.255101);
+        assert_close(json["pump_data"]["real_sol_reserves"].as_f64().unwrap(), 0.916148273);
+        assert_close(json["pump_data"]["real_token_reserves"].as_f64().unwrap(), 761_354_143.255101);
+
+        let trade = trade_json(Units::Sol);
+        assert_close(trade["sol_amount"].as_f64().unwrap(), 0.5);
+        assert_close(trade["token_amount"].as_f64().unwrap(), 17_241_379.310344);
+        assert_close(trade["virtual_sol_reserves"].as_f64().unwrap(), 31.0);
+        assert_close(trade["virtual_token_reserves"].as_f64().unwrap(), 1_038_275_862.068966);
+    }
+}
@@ -0,0 +1,307 @@
+//! gRPC fan-out: a server-streaming `SubscribeEvents` RPC, schema in `proto/events.proto`
+//!
+//! The service is written out by hand rather than generated, so the build needs no
+//! `protoc`. Each stream holds its own broadcast subscription, dropped with the stream
+//! when the client disconnects.
+
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use chrono::DateTime;
+use futures::Stream;
+use log::{error, info};
+use serde_json::Value;
+use tonic::codegen::{empty_body, http, Body, BoxFuture, Context, Poll, Service, StdError};
+use tonic::{Code, Status};
+use crate::config::Config;
+use crate::event_channels::{EventChannels, Missed, Subscription};
+use crate::event_parser::{self, TimestampFormat, Units};
+use crate::metrics;
+
+const SUBSCRIBE_EVENTS_PATH: &str = "/pumpfun.events.v1.Events/SubscribeEvents";
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct SubscribeRequest {
+    #[prost(string, repeated, tag = "1")]
+    pub event_types: Vec<String>,
+    #[prost(string, repeated, tag = "2")]
+    pub mints: Vec<String>,
+    #[prost(double, optional, tag = "3")]
+    pub min_market_cap_sol: Option<f64>,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Event {
+    #[prost(string, tag = "1")]
+    pub event_type: String,
+    #[prost(string, tag = "2")]
+    pub cluster: String,
+    #[prost(int64, tag = "3")]
+    pub timestamp_ms: i64,
+    #[prost(string, tag = "4")]
+    pub transaction_signature: String,
+    #[prost(uint64, tag = "5")]
+    pub slot: u64,
+    #[prost(message, optional, tag = "6")]
+    pub token: Option<Token>,
+    #[prost(message, optional, tag = "7")]
+    pub pump_data: Option<PumpData>,
+    #[prost(string, tag = "8")]
+    pub mint_address: String,
+    #[prost(string, tag = "9")]
+    pub trader: String,
+    #[prost(uint64, tag = "10")]
+    pub sol_amount: u64,
+    #[prost(uint64, tag = "11")]
+    pub token_amount: u64,
+    #[prost(int64, tag = "12")]
+    pub block_time_ms: i64,
+    #[prost(string, tag = "13")]
+    pub json: String,
+    #[prost(uint64, tag = "14")]
+    pub virtual_sol_reserves: u64,
+    #[prost(uint64, tag = "15")]
+    pub virtual_token_reserves: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct Token {
+    #[prost(string, tag = "1")]
+    pub mint_address: String,
+    #[prost(string, tag = "2")]
+    pub name: String,
+    #[prost(string, tag = "3")]
+    pub symbol: String,
+    #[prost(string, tag = "4")]
+    pub creator: String,
+    #[prost(uint64, tag = "5")]
+    pub supply: u64,
+    #[prost(uint32, tag = "6")]
+    pub decimals: u32,
+    #[prost(string, tag = "7")]
+    pub uri: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct PumpData {
+    #[prost(string, tag = "1")]
+    pub bonding_curve: String,
+    #[prost(uint64, tag = "2")]
+    pub virtual_sol_reserves: u64,
+    #[prost(uint64, tag = "3")]
+    pub virtual_token_reserves: u64,
+    #[prost(double, tag = "4")]
+    pub price_sol: f64,
+    #[prost(uint64, tag = "5")]
+    pub real_sol_reserves: u64,
+    #[prost(uint64, tag = "6")]
+    pub real_token_reserves: u64,
+    #[prost(bool, tag = "7")]
+    pub complete: bool,
+}
+
+/// Converts broadcast JSON back to raw units and epoch milliseconds
+#[derive(Clone, Copy)]
+struct Decoder {
+    units: Units,
+    timestamps: TimestampFormat,
+}
+
+impl Decoder {
+    fn millis(&self, value: &Value) -> i64 {
+        let parsed = match self.timestamps {
+            TimestampFormat::Rfc3339 => value.as_str().and_then(|s| DateTime::parse_from_rfc3339(s).ok()).map(|t| t.timestamp_millis()),
+            TimestampFormat::EpochMs => value.as_i64(),
+            TimestampFormat::EpochS => value.as_i64().map(|secs| secs * 1000),
+        };
+        parsed.unwrap_or_default()
+    }
+
+    /// An amount in base units, scaled back up when `UNITS=sol` sent it divided by `10^decimals`
+    fn base_units(&self, value: &Value, decimals: i32) -> u64 {
+        match self.units {
+            Units::Lamports => value.as_u64().unwrap_or_default(),
+            Units::Sol => value.as_f64().map_or(0, |amount| (amount * 10f64.powi(decimals)).round() as u64),
+        }
+    }
+
+    fn event(&self, event: &Value, json: String) -> Event {
+        let text = |value: &Value| value.as_str().unwrap_or_default().to_string();
+        let token = event.get("token").map(|token| {
+            let decimals = token["decimals"].as_u64().unwrap_or_default() as u32;
+            Token {
+                mint_address: text(&token["mint_address"]),
+                name: text(&token["name"]),
+                symbol: text(&token["symbol"]),
+                creator: text(&token["creator"]),
+                supply: self.base_units(&token["supply"], decimals as i32),
+                decimals,
+                uri: text(&token["uri"]),
+            }
+        });
+        let token_decimals = token.as_ref().map_or(event_parser::PUMP_TOKEN_DECIMALS, |token| token.decimals as i32);
+        let pump_data = event.get("pump_data").map(|pump_data| PumpData {
+            bonding_curve: text(&pump_data["bonding_curve"]),
+            virtual_sol_reserves: self.base_units(&pump_data["virtual_sol_reserves"], 9),
+            virtual_token_reserves: self.base_units(&pump_data["virtual_token_reserves"], token_decimals),
+            price_sol: pump_data["price_sol"].as_f64().unwrap_or_default(),
+            real_sol_reserves: self.base_units(&pump_data["real_sol_reserves"], 9),
+            real_token_reserves: self.base_units(&pump_data["real_token_reserves"], token_decimals),
+            complete: pump_data["complete"].as_bool().unwrap_or_default(),
+        });
+        Event {
+            event_type: text(&event["event_type"]),
+            cluster: text(&event["cluster"]),
+            timestamp_ms: self.millis(&event["timestamp"]),
+            transaction_signature: text(&event["transaction_signature"]),
+            slot: event["slot"].as_u64().unwrap_or_default(),
+            token,
+            pump_data,
+            mint_address: text(&event["mint_address"]),
+            trader: text(&event["trader"]),
+            sol_amount: self.base_units(&event["sol_amount"], 9),
+            token_amount: self.base_units(&event["token_amount"], event_parser::PUMP_TOKEN_DECIMALS),
+            block_time_ms: event.get("block_time").map_or(0, |time| self.millis(time)),
+            json,
+            virtual_sol_reserves: self.base_units(&event["virtual_sol_reserves"], 9),
+            virtual_token_reserves: self.base_units(&event["virtual_token_reserves"], event_parser::PUMP_TOKEN_DECIMALS),
+        }
+    }
+}
+
+impl SubscribeRequest {
+    fn accepts(&self, event: &Value) -> bool {
+        let event_type = event["event_type"].as_str().unwrap_or_default();
+        if !self.event_types.is_empty() && !self.event_types.iter().any(|t| t == event_type) {
+            return false;
+        }
+        if !self.mints.is_empty() {
+            let mint = event["token"]["mint_address"].as_str().or(event["mint_address"].as_str()).unwrap_or_default();
+            if !self.mints.iter().any(|m| m == mint) {
+                return false;
+            }
+        }
+        match self.min_market_cap_sol {
+            Some(min) => event_parser::event_market_cap_sol(event).is_some_and(|market_cap| market_cap >= min),
+            None => true,
+        }
+    }
+}
+
+/// Counts an open stream in `pumpfun_grpc_streams` until dropped
+struct OpenStream;
+
+impl OpenStream {
+    fn new() -> Self {
+        metrics::record_grpc_stream_opened();
+        OpenStream
+    }
+}
+
+impl Drop for OpenStream {
+    fn drop(&mut self) {
+        metrics::record_grpc_stream_closed();
+    }
+}
+
+type EventStream = Pin<Box<dyn Stream<Item = Result<Event, Status>> + Send>>;
+
+/// Streams the events that pass `request` from `rx`, ending the stream if the client lags out
+fn event_stream(rx: Subscription, request: SubscribeRequest, decoder: Decoder) -> EventStream {
+    let state = (rx, OpenStream::new(), false);
+    Box::pin(futures::stream::unfold(state, move |(mut rx, open, done)| {
+        let request = request.clone();
+        async move {
+            if done {
+                return None;
+            }
+            loop {
+                let message = match rx.recv().await {
+                    Ok(message) => message,
+                    Err(Missed::Lagged { lossy: true, .. }) => continue,
+                    Err(Missed::Lagged { skipped, .. }) => {
+                        let status = Status::data_loss(format!("fell behind by {} events", skipped));
+                        return Some((Err(status), (rx, open, true)));
+                    }
+                    Err(Missed::Closed) => return None,
+                };
+                // Control messages such as stream_stalled have no typed counterpart
+                let Ok(event) = serde_json::from_str::<Value>(&message) else { continue };
+                if event.get("event_type").is_none() || !request.accepts(&event) {
+                    continue;
+                }
+                return Some((Ok(decoder.event(&event, message)), (rx, open, false)));
+            }
+        }
+    }))
+}
+
+/// The `pumpfun.events.v1.Events` service
+#[derive(Clone)]
+struct EventsService {
+    sender: EventChannels,
+    decoder: Decoder,
+}
+
+impl tonic::server::NamedService for EventsService {
+    const NAME: &'static str = "pumpfun.events.v1.Events";
+}
+
+struct SubscribeEvents(EventsService);
+
+impl tonic::server::ServerStreamingService<SubscribeRequest> for SubscribeEvents {
+    type Response = Event;
+    type ResponseStream = EventStream;
+    type Future = BoxFuture<tonic::Response<EventStream>, Status>;
+
+    fn call(&mut self, request: tonic::Request<SubscribeRequest>) -> Self::Future {
+        let rx = self.0.sender.subscribe();
+        let decoder = self.0.decoder;
+        Box::pin(async move { Ok(tonic::Response::new(event_stream(rx, request.into_inner(), decoder))) })
+    }
+}
+
+impl<B> Service<http::Request<B>> for EventsService
+where
+    B: Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<tonic::body::BoxBody>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        if request.uri().path() != SUBSCRIBE_EVENTS_PATH {
+            return Box::pin(async move {
+                let mut response = http::Response::new(empty_body());
+                let headers = response.headers_mut();
+                headers.insert(Status::GRPC_STATUS, (Code::Unimplemented as i32).into());
+                headers.insert(http::header::CONTENT_TYPE, tonic::metadata::GRPC_CONTENT_TYPE);
+                Ok(response)
+            });
+        }
+        let service = self.clone();
+        Box::pin(async move {
+            let mut grpc = tonic::server::Grpc::new(tonic::codec::ProstCodec::default());
+            Ok(grpc.server_streaming(SubscribeEvents(service), request).await)
+        })
+    }
+}
+
+/// Serves `SubscribeEvents` on `port` until the task is aborted
+pub async fn start_grpc_server(port: u16, config: Arc<Config>, sender: EventChannels) {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+    let service = EventsService {
+        sender,
+        decoder: Decoder { units: config.units, timestamps: config.timestamp_format },
+    };
+    info!("gRPC Server running on {}", addr);
+    if let Err(e) = tonic::transport::Server::builder().add_service(service).serve(addr).await {
+        error!("gRPC server error on port {}: {}", port, e);
+    }
+}
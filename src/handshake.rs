@@ -0,0 +1,168 @@
+use serde::Serialize;
+use crate::client_commands;
+use crate::client_groups;
+use crate::client_options::{self, ClientOptions};
+use crate::config::Config;
+use crate::csv_format;
+use crate::projection;
+use crate::solana_client;
+use crate::transitions::StateMachine;
+
+/// Version of the event and control message schema, bumped on breaking changes
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// First message on every connection
+///
+/// With `HANDSHAKE_FORMAT=full` it also carries the server's capabilities, so a
+/// client can fully initialize from this one frame.
+#[derive(Serialize, Debug)]
+pub struct HandshakeMessage {
+    #[serde(rename = "type")]
+    pub message_type: &'static str,
+    pub connection_id: usize,
+    pub message: &'static str,
+    pub units: String,
+    pub timestamp_format: String,
+    pub slot_batches: bool,
+    pub stream_health: bool,
+    pub reserve_deltas: bool,
+    pub verbosity: String,
+    pub format: String,
+    pub schema: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ack_window: Option<u32>,
+    /// Only present with `slot_batches`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub batch_sort: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group: Option<String>,
+    pub transitions_only: bool,
+    pub sync: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema_version: Option<u32>,
+    /// Highest slot the service had seen when the client connected
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest_slot: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub capabilities: Option<Capabilities>,
+    /// Buffered events, oldest first, when `REPLAY_DELIVERY=combined`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replay: Option<Vec<serde_json::Value>>,
+}
+
+/// What this server supports, also returned by the `capabilities` command
+#[derive(Serialize, Debug)]
+pub struct Capabilities {
+    pub schema_version: u32,
+    pub commands: &'static [&'static str],
+    pub connection_parameters: &'static [&'static str],
+    /// Per-message compression is not supported by the WebSocket stack
+    pub compression: Option<&'static str>,
+    pub signed_events: bool,
+    pub replay_buffer_size: usize,
+    pub max_event_age_secs: Option<u64>,
+    pub replay_ttl_secs: Option<u64>,
+    /// Column order of `format=csv` event rows
+    pub csv_columns: &'static [&'static str],
+    /// Field paths accepted by the `project` command
+    pub projection_fields: &'static [&'static str],
+    /// Client groups that can be joined with `?group=`
+    pub groups: Vec<String>,
+    /// Creators one connection may follow with `track_creator`; absent when tracking is disabled
+    pub max_tracked_creators: Option<usize>,
+    /// States and transitions sent to `transitions_only` connections; absent when transitions are disabled
+    pub transitions: Option<StateMachine>,
+    /// Most mints one `sync` snapshot holds; absent when sync is disabled
+    pub max_sync_mints: Option<usize>,
+}
+
+impl Capabilities {
+    pub fn new(config: &Config) -> Self {
+        Capabilities {
+            schema_version: SCHEMA_VERSION,
+            commands: client_commands::COMMANDS,
+            connection_parameters: client_options::PARAMETERS,
+            compression: None,
+            signed_events: config.signing_key.is_some(),
+            replay_buffer_size: config.replay_buffer_size,
+            max_event_age_secs: config.max_event_age_secs,
+            replay_ttl_secs: config.replay_ttl_secs,
+            csv_columns: csv_format::COLUMNS,
+            projection_fields: projection::FIELDS,
+            groups: client_groups::names(),
+            max_tracked_creators: config.creator_tracking.map(|tracking| tracking.max_per_connection),
+            transitions: config.transitions.as_ref().map(StateMachine::new),
+            max_sync_mints: config.sync.map(|limits| limits.max_mints),
+        }
+    }
+}
+
+impl HandshakeMessage {
+    pub fn new(connection_id: usize, options: &ClientOptions, config: &Config) -> Self {
+        let full = config.full_handshake;
+        HandshakeMessage {
+            message_type: "connection_established",
+            connection_id,
+            message: "Connected to Pump.fun WebSocket Service",
+            units: config.units.to_string(),
+            timestamp_format: config.timestamp_format.to_string(),
+            slot_batches: options.slot_batches,
+            stream_health: options.stream_health,
+            reserve_deltas: options.reserve_deltas,
+            verbosity: options.verbosity.to_string(),
+            format: options.format.to_string(),
+            schema: options.schema.to_string(),
+            ack_window: options.ack_window,
+            batch_sort: options.slot_batches.then(|| options.batch_sort.to_string()),
+            group: options.group.clone(),
+            transitions_only: options.transitions_only,
+            sync: options.sync,
+            schema_version: full.then_some(SCHEMA_VERSION),
+            latest_slot: full.then(solana_client::latest_slot),
+            capabilities: full.then(|| Capabilities::new(config)),
+            replay: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+    use serde_json::Value;
+
+    fn keys(value: &Value) -> BTreeSet<&str> {
+        value.as_object().unwrap().keys().map(String::as_str).collect()
+    }
+
+    #[test]
+    fn basic_handshake_has_every_connection_field() {
+        let config = Config::for_tests();
+        let welcome = serde_json::to_value(HandshakeMessage::new(7, &ClientOptions::default(), &config)).unwrap();
+        assert_eq!(keys(&welcome), BTreeSet::from([
+            "type", "connection_id", "message", "units", "timestamp_format", "slot_batches", "stream_health",
+            "reserve_deltas", "verbosity", "format", "schema", "transitions_only", "sync",
+        ]));
+
+        let options = ClientOptions { slot_batches: true, ack_window: Some(10), group: Some("launches".to_string()), ..Default::default() };
+        let welcome = serde_json::to_value(HandshakeMessage::new(7, &options, &config)).unwrap();
+        for key in ["ack_window", "batch_sort", "group"] {
+            assert!(welcome.get(key).is_some(), "missing {}", key);
+        }
+    }
+
+    #[test]
+    fn full_handshake_adds_the_capabilities() {
+        let mut config = Config::for_tests();
+        config.full_handshake = true;
+        let welcome = serde_json::to_value(HandshakeMessage::new(7, &ClientOptions::default(), &config)).unwrap();
+        for key in ["schema_version", "latest_slot", "capabilities"] {
+            assert!(welcome.get(key).is_some(), "missing {}", key);
+        }
+        assert_eq!(keys(&welcome["capabilities"]), BTreeSet::from([
+            "schema_version", "commands", "connection_parameters", "compression", "signed_events", "replay_buffer_size",
+            "max_event_age_secs", "replay_ttl_secs", "csv_columns", "projection_fields", "groups", "max_tracked_creators",
+            "transitions", "max_sync_mints",
+        ]));
+    }
+}
@@ -0,0 +1,187 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use log::{info, error};
+use serde_json::{json, Value};
+use crate::config::Config;
+use crate::connection_registry;
+use crate::metrics;
+use crate::parse_failures;
+use crate::parse_watchdog;
+use crate::restart;
+use crate::signing;
+use crate::solana_client;
+use crate::ws_server;
+
+// Page size limits for `/admin/connections`
+const DEFAULT_CONNECTIONS_LIMIT: usize = 100;
+const MAX_CONNECTIONS_LIMIT: usize = 1000;
+
+/// Starts the HTTP server for health checks and admin operations
+///
+/// # Arguments
+/// * `config` - Service configuration (HTTP port, admin key, drain timeout)
+pub async fn start_http_server(config: Arc<Config>) {
+    let addr = SocketAddr::from(([0, 0, 0, 0], config.http_port));
+    let graceful_restart = config.graceful_restart;
+
+    let make_service = make_service_fn(move |_| {
+        let config = config.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| handle_request(req, config.clone())))
+        }
+    });
+
+    // Bind, or take over the previous process's listener after a restart
+    let inherited = graceful_restart
+        .then(|| restart::inherited_listener(restart::HTTP_LISTENER, addr.port()))
+        .flatten();
+    let listener = match inherited.map_or_else(|| std::net::TcpListener::bind(addr), Ok) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to bind HTTP port {}: {}", addr.port(), e);
+            return;
+        }
+    };
+    if graceful_restart {
+        restart::register_listener(restart::HTTP_LISTENER, &listener);
+    }
+    let server = match Server::from_tcp(listener) {
+        Ok(builder) => builder.serve(make_service).with_graceful_shutdown(restart::handed_off()),
+        Err(e) => {
+            error!("Failed to serve HTTP port {}: {}", addr.port(), e);
+            return;
+        }
+    };
+
+    info!("HTTP Server running on {}", addr);
+    if let Err(e) = server.await {
+        error!("HTTP server error: {}", e);
+    }
+}
+
+/// Routes a single HTTP request
+async fn handle_request(req: Request<Body>, config: Arc<Config>) -> Result<Response<Body>, Infallible> {
+    let response = match (req.method(), req.uri().path()) {
+        (&Method::GET, "/ready") => ready(),
+        (&Method::GET, "/metrics") => Response::builder()
+            .header(hyper::header::CONTENT_TYPE, "text/plain; version=0.0.4")
+            .body(Body::from(metrics::render_prometheus()))
+            .unwrap(),
+        (&Method::GET, "/stats") => json_response(StatusCode::OK, metrics::stats()),
+        (&Method::GET, "/version") => version(&config),
+        (&Method::POST, "/admin/drain") => admin_rejection(&req, &config).unwrap_or_else(|| drain(&config)),
+        (&Method::GET, "/admin/connections") => admin_rejection(&req, &config).unwrap_or_else(|| connections(&req)),
+        (&Method::GET, "/admin/parse-failures") => admin_rejection(&req, &config).unwrap_or_else(|| json_response(StatusCode::OK, json!({
+            "capacity": config.parse_failure_samples.unwrap_or(0),
+            "samples": parse_failures::list(),
+        }))),
+        (&Method::DELETE, "/admin/parse-failures") => admin_rejection(&req, &config).unwrap_or_else(|| json_response(StatusCode::OK, json!({
+            "cleared": parse_failures::clear(),
+        }))),
+        _ => json_response(StatusCode::NOT_FOUND, json!({"error": "not_found"})),
+    };
+    Ok(response)
+}
+
+/// Readiness probe: not ready once draining, while every RPC endpoint is down, or
+/// while the parser watchdog reports most notifications failing to parse, so load
+/// balancers stop routing new clients here
+fn ready() -> Response<Body> {
+    let draining = ws_server::is_draining();
+    let upstreams_down = solana_client::all_upstreams_down();
+    let parser_failing = parse_watchdog::is_tripped();
+    let ready = !draining && !upstreams_down && !parser_failing;
+    let status = if ready { StatusCode::OK } else { StatusCode::SERVICE_UNAVAILABLE };
+    json_response(status, json!({
+        "ready": ready,
+        "draining": draining,
+        "all_upstreams_down": upstreams_down,
+        "parser_failing": parser_failing,
+        "active_connections": ws_server::get_active_connections(),
+    }))
+}
+
+/// Build version and cluster, plus the public key clients use to verify event signatures
+fn version(config: &Config) -> Response<Body> {
+    json_response(StatusCode::OK, json!({
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "cluster": config.cluster.to_string(),
+        "signing": config.signing_key.as_ref().map(|key| json!({
+            "algorithm": "ed25519",
+            "public_key": signing::public_key(key),
+        })),
+    }))
+}
+
+/// Puts the WebSocket server into draining mode
+fn drain(config: &Config) -> Response<Body> {
+    let started = ws_server::start_draining(std::time::Duration::from_secs(config.drain_timeout_secs));
+    json_response(StatusCode::ACCEPTED, json!({
+        "draining": true,
+        "already_draining": !started,
+        "active_connections": ws_server::get_active_connections(),
+        "timeout_secs": config.drain_timeout_secs,
+    }))
+}
+
+/// Lists open WebSocket connections, paged with `offset` and `limit` query parameters
+fn connections(req: &Request<Body>) -> Response<Body> {
+    let mut offset = 0;
+    let mut limit = DEFAULT_CONNECTIONS_LIMIT;
+    for (name, value) in form_urlencoded::parse(req.uri().query().unwrap_or_default().as_bytes()) {
+        let target = match name.as_ref() {
+            "offset" => &mut offset,
+            "limit" => &mut limit,
+            _ => continue,
+        };
+        match value.parse() {
+            Ok(number) => *target = number,
+            Err(_) => return json_response(StatusCode::BAD_REQUEST, json!({"error": format!("invalid {} '{}'", name, value)})),
+        }
+    }
+    let limit = limit.min(MAX_CONNECTIONS_LIMIT);
+    let (total, connections) = connection_registry::list(offset, limit);
+    json_response(StatusCode::OK, json!({
+        "total": total,
+        "offset": offset,
+        "limit": limit,
+        "connections": connections,
+    }))
+}
+
+/// Checks the `Authorization: Bearer <ADMIN_API_KEY>` header, returning an error response if it fails
+///
+/// Admin endpoints are disabled entirely when no admin key is configured.
+fn admin_rejection(req: &Request<Body>, config: &Config) -> Option<Response<Body>> {
+    let Some(admin_key) = config.admin_api_key.as_deref() else {
+        return Some(json_response(StatusCode::FORBIDDEN, json!({"error": "admin_disabled"})));
+    };
+
+    let provided = req
+        .headers()
+        .get(hyper::header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "));
+
+    match provided {
+        Some(key) if constant_time_eq(key.as_bytes(), admin_key.as_bytes()) => None,
+        _ => Some(json_response(StatusCode::UNAUTHORIZED, json!({"error": "unauthorized"}))),
+    }
+}
+
+/// Compares two byte strings without short-circuiting on the first difference
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+fn json_response(status: StatusCode, body: Value) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
@@ -0,0 +1,84 @@
+//! Launch metadata from `CreateEvent` logs, keyed by bonding curve, for the token
+//! details that curve account updates don't carry: mint, name, symbol, creator and URI
+
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::Mutex;
+use crate::event_parser::{CreateEvent, TokenEvent};
+
+struct Launches {
+    by_curve: BTreeMap<String, CreateEvent>,
+    // Curves in the order their launches were seen, for evicting the oldest
+    order: VecDeque<String>,
+}
+
+/// Launches remembered before the oldest are forgotten
+pub const CAPACITY: usize = 100_000;
+
+static LAUNCHES: Mutex<Launches> = Mutex::new(Launches::new());
+
+impl Launches {
+    const fn new() -> Self {
+        Self { by_curve: BTreeMap::new(), order: VecDeque::new() }
+    }
+
+    fn insert(&mut self, create: CreateEvent, capacity: usize) {
+        let curve = create.bonding_curve.clone();
+        if self.by_curve.insert(curve.clone(), create).is_none() {
+            self.order.push_back(curve);
+        }
+        while self.order.len() > capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.by_curve.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// Remembers a launch, forgetting the oldest beyond `CAPACITY`
+pub fn remember(create: CreateEvent) {
+    LAUNCHES.lock().unwrap().insert(create, CAPACITY);
+}
+
+/// Replaces the placeholder token details of a curve update with its launch's metadata,
+/// if that launch has been seen
+pub fn apply(event: &mut TokenEvent) {
+    if let Some(create) = LAUNCHES.lock().unwrap().by_curve.get(&event.pump_data.bonding_curve) {
+        create.fill(&mut event.token);
+    }
+}
+
+/// Number of launches remembered
+pub fn len() -> usize {
+    LAUNCHES.lock().unwrap().by_curve.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn launch(curve: &str) -> CreateEvent {
+        CreateEvent {
+            name: "Test".to_string(),
+            symbol: "TEST".to_string(),
+            uri: String::new(),
+            mint: format!("{}-mint", curve),
+            bonding_curve: curve.to_string(),
+            creator: String::new(),
+        }
+    }
+
+    #[test]
+    fn oldest_launches_are_forgotten_past_capacity() {
+        let mut launches = Launches::new();
+        for curve in ["a", "b", "c"] {
+            launches.insert(launch(curve), 2);
+        }
+        assert_eq!(launches.by_curve.keys().collect::<Vec<_>>(), ["b", "c"]);
+        assert_eq!(launches.order, ["b", "c"]);
+
+        // Seeing a launch again neither duplicates nor refreshes it
+        launches.insert(launch("b"), 2);
+        launches.insert(launch("d"), 2);
+        assert_eq!(launches.by_curve.keys().collect::<Vec<_>>(), ["c", "d"]);
+    }
+}
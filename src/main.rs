@@ -2,47 +2,317 @@ mod config;
 mod solana_client;
 mod event_parser;
 mod ws_server;
+mod state_index;
+mod replay_buffer;
+mod telemetry;
+mod http_server;
+mod metrics;
+mod client_commands;
+mod client_options;
+mod handshake;
+mod slot_batcher;
+mod proxy;
+mod webhooks;
+mod uds_server;
+mod signing;
+mod delta_encoder;
+mod program_check;
+mod delivery_hours;
+mod seen_mints;
+mod csv_format;
+mod supervisor;
+mod creation_throttle;
+mod rpc_http;
+mod snapshot;
+mod reconnect_storm;
+mod connection_registry;
+mod event_channels;
+mod reconciliation;
+mod parse_failures;
+mod projection;
+mod output_schema;
+mod restart;
+mod client_groups;
+mod message_deadline;
+mod creator_tracking;
+mod parse_watchdog;
+mod volume_leaders;
+mod backfill;
+mod buffer_saturation;
+mod profile_timestamps;
+mod transitions;
+mod coarse_clock;
+mod state_sync;
+mod launch_metadata;
+#[cfg(feature = "grpc")]
+mod grpc_server;
 
-use tokio::sync::broadcast;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use tokio::signal;
-use log::{info, warn, error};
+use log::{info, error};
 
 #[tokio::main]
 async fn main() {
-    // Initialize logging with better configuration
-    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
+    let config = Arc::new(config::Config::from_env());
+
+    // Initialize logging, exporting pipeline spans if an OTLP endpoint is configured
+    if telemetry::init(config.otel_exporter_endpoint.as_deref()) {
+        info!("Exporting traces to {}", config.otel_exporter_endpoint.as_deref().unwrap_or_default());
+    }
     
     info!("Starting Pump.fun WebSocket Service...");
     
-    let config = config::Config::from_env();
-    info!("Configuration loaded - Server port: {}, Solana RPC: {}", config.server_port, config.solana_rpc_ws);
+    info!("Configuration loaded - Server port: {}, Solana RPC: {} ({})", config.server_port, config.solana_rpc_ws, config.cluster);
+    if let Some(proxy) = &config.rpc_proxy {
+        info!("Connecting to Solana RPC through proxy {}", proxy);
+    }
+    let trigger_fields: Vec<String> = config.trigger_fields.iter().map(|f| f.to_string()).collect();
+    info!("Emitting updates on changes to: [{}]", trigger_fields.join(", "));
+    if !config.emit_instruction_events {
+        info!("Token mint, name, symbol and creator are placeholders; set INSTRUCTION_EVENTS=emit to read them from launch logs");
+    }
+
+    // Fail fast on a wrong cluster or RPC URL rather than waiting forever for events
+    if config.verify_program_on_start {
+        let rpc_http = config.solana_rpc_http.as_ref().expect("checked by Config::from_env");
+        if let Err(e) = program_check::verify_program(rpc_http).await {
+            error!("Startup check failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // Load the client groups, reloading them on SIGHUP
+    let groups_handle = config.client_groups_file.clone().map(|path| {
+        match client_groups::load(&path) {
+            Ok(count) => info!("Loaded {} client groups from {}", count, path),
+            Err(e) => panic!("CLIENT_GROUPS_FILE: {}", e),
+        }
+        tokio::spawn(async move {
+            client_groups::reload_on_sighup(path).await;
+        })
+    });
+
+    // Create broadcast channels for event distribution. `main` owns the original
+    // senders for the life of the process and every task gets a clone, so the channels
+    // stay open for the listener even if a consumer task such as the WS server exits.
+    if let Some(capacity) = config.critical_channel_capacity {
+        info!("Sending launches on a separate critical channel of {} events", capacity);
+    }
+    if config.broadcast_shards > 1 {
+        info!("Sharding the broadcast channels {} ways", config.broadcast_shards);
+    }
+    if let Some(max) = config.broadcast_buffer_max {
+        info!("Broadcast buffer starts at 1000 events and can grow to {}", max);
+    }
+    let buffer_max = config.broadcast_buffer_max.unwrap_or(1000);
+    let sender = event_channels::EventChannels::new(1000, buffer_max, config.critical_channel_capacity, config.broadcast_shards); // Increased buffer size for better performance
+
+    // Carry raw account fields through the pipeline for sinks that emit Geyser account updates
+    if config.uds_schema.needs_raw_accounts() || config.webhook_schema.needs_raw_accounts() || config.ws_geyser_schema {
+        info!("Keeping raw account fields on account events for the geyser output schema");
+        event_parser::keep_raw_accounts();
+    }
+
+    event_parser::set_log_scan_limits(config.log_scan_max_lines, config.log_scan_max_bytes);
 
-    // Create broadcast channel for event distribution
-    let (sender, _) = broadcast::channel(1000); // Increased buffer size for better performance
+    // Stamp events from a coarse clock, if a resolution is configured
+    let clock_handle = (config.timestamp_resolution_ms > 0).then(|| {
+        let resolution = Duration::from_millis(config.timestamp_resolution_ms);
+        info!("Event timestamps have a resolution of {}ms", config.timestamp_resolution_ms);
+        tokio::spawn(async move {
+            coarse_clock::run(resolution).await;
+        })
+    });
+
+    // Recent events kept for clients that connect (or reconnect) later
+    let replay_ttl = config.replay_ttl_secs.map(|secs| chrono::Duration::seconds(secs as i64));
+    let replay = Arc::new(Mutex::new(replay_buffer::ReplayBuffer::new(config.replay_buffer_size, replay_ttl)));
 
-    // Spawn Solana event listener task
+    // Expire replayed events during quiet periods too, not only when the next event arrives
+    let expiry_handle = replay_ttl.map(|_| {
+        let expiry_replay = replay.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(Duration::from_secs(1));
+            loop {
+                ticker.tick().await;
+                expiry_replay.lock().unwrap().evict_expired();
+            }
+        })
+    });
+
+    // Per-mint state, updated by the listener and queried by clients
+    let seen_mints = (config.seen_mints_capacity > 0).then(|| {
+        let filter = seen_mints::SeenMints::new(config.seen_mints_capacity, config.seen_mints_false_positive_rate);
+        info!("Remembering up to {} seen mints in a {} KiB filter", config.seen_mints_capacity, filter.size_bytes() / 1024);
+        filter
+    });
+    let state = Arc::new(Mutex::new(state_index::StateIndex::new(
+        config.trigger_fields.clone(),
+        config.max_tracked_mints,
+        config.creator_limit,
+        seen_mints,
+        config.graduation_alert,
+        config.creator_tracking.is_some(),
+        config.volume_leaders.map(|board| board.window),
+        config.wash_trade_detection,
+        config.transitions.as_ref().map(|settings| settings.market_caps_sol.clone()),
+    )));
+
+    // Spawn Solana event listener task, restarted if it panics. A panic while holding
+    // the state or replay lock poisons it, so the poison is cleared as soon as the
+    // listener panics; both are left consistent by every update.
     let solana_sender = sender.clone();
-    let solana_url = config.solana_rpc_ws.clone();
-    let solana_handle = tokio::spawn(async move {
-        solana_client::solana_event_listener(solana_sender, solana_url).await;
+    let solana_config = config.clone();
+    let solana_replay = replay.clone();
+    let solana_state = state.clone();
+    let mut solana_handle = tokio::spawn(supervisor::supervise("solana_listener", move || {
+        solana_state.clear_poison();
+        solana_replay.clear_poison();
+        solana_client::solana_event_listener(solana_sender.clone(), solana_config.clone(), solana_replay.clone(), solana_state.clone())
+    }));
+
+    // Release rate-limited launches, if a limit is configured
+    let throttle_handle = config.max_new_tokens_per_sec.map(|per_sec| {
+        info!("Sending at most {} token_created events per second", per_sec);
+        let throttle_config = config.clone();
+        let throttle_sender = sender.clone();
+        let throttle_replay = replay.clone();
+        tokio::spawn(async move {
+            creation_throttle::run_release(per_sec, throttle_config, throttle_sender, throttle_replay).await;
+        })
     });
 
+    // Report processed-commitment events whose slot never reached confirmed
+    let reconcile_handle = config.reconcile_processed.then(|| {
+        info!("Subscribing at processed commitment and reconciling against confirmed blocks");
+        let rpc_http = config.solana_rpc_http.clone().expect("checked by Config::from_env");
+        let reconcile_sender = sender.clone();
+        tokio::spawn(async move {
+            reconciliation::run(rpc_http, reconcile_sender).await;
+        })
+    });
 
+    // Broadcast the volume leaderboard, if enabled
+    let leaders_handle = config.volume_leaders.map(|board| {
+        info!("Sending the top {} mints by volume every {}s", board.top, board.interval.as_secs());
+        let leaders_state = state.clone();
+        let leaders_sender = sender.clone();
+        tokio::spawn(async move {
+            volume_leaders::run(board, leaders_state, leaders_sender).await;
+        })
+    });
+
+    // Stall mints that stop updating, for transitions_only clients
+    let stall_handle = config.transitions.as_ref().and_then(|settings| settings.stall_after).map(|stall_after| {
+        let stall_config = config.clone();
+        let stall_state = state.clone();
+        let stall_sender = sender.clone();
+        let stall_replay = replay.clone();
+        tokio::spawn(async move {
+            transitions::run_stall_sweep(stall_after, stall_config, stall_state, stall_sender, stall_replay).await;
+        })
+    });
+
+    // Sample broadcast channel depth and receiver count for /metrics and /stats
+    let metrics_sender = sender.clone();
+    let metrics_handle = tokio::spawn(async move {
+        metrics::sample_broadcast_channel(metrics_sender, Duration::from_secs(1)).await;
+    });
+
+    // Grow the broadcast buffer, or warn or shed clients, when it stays saturated
+    let saturation_sender = sender.clone();
+    let saturation_settings = config.buffer_saturation;
+    let saturation_handle = tokio::spawn(async move {
+        buffer_saturation::run(saturation_sender, saturation_settings).await;
+    });
+
+    // Pause delivery outside the configured hours, if any
+    let schedule_handle = config.delivery_hours.clone().map(|schedule| {
+        info!("Delivering events only during {} UTC", schedule);
+        let schedule_sender = sender.clone();
+        tokio::spawn(async move {
+            delivery_hours::run_schedule(schedule, schedule_sender).await;
+        })
+    });
+
+    // Spawn the webhook sink; it exits straight away when no webhooks are configured
+    let webhook_config = config.clone();
+    let webhook_sender = sender.clone();
+    let webhook_handle = tokio::spawn(supervisor::supervise("webhooks", move || {
+        webhooks::run_webhooks(webhook_config.clone(), webhook_sender.subscribe())
+    }));
+
+    // Spawn the Unix socket event stream if a socket path is configured
+    let uds_path = config.uds_path.clone();
+    let uds_handle = uds_path.clone().map(|path| {
+        let uds_sender = sender.clone();
+        let uds_schema = config.uds_schema;
+        tokio::spawn(async move {
+            uds_server::start_uds_server(path, uds_schema, uds_sender).await;
+        })
+    });
+
+    // Spawn the gRPC event stream if a port is configured and the build supports it
+    #[cfg(feature = "grpc")]
+    let grpc_handle = config.grpc_port.map(|port| {
+        let grpc_config = config.clone();
+        let grpc_sender = sender.clone();
+        tokio::spawn(async move {
+            grpc_server::start_grpc_server(port, grpc_config, grpc_sender).await;
+        })
+    });
+    #[cfg(not(feature = "grpc"))]
+    let grpc_handle: Option<tokio::task::JoinHandle<()>> = config.grpc_port.and_then(|_| {
+        log::warn!("GRPC_PORT is set but this build lacks the `grpc` feature; the gRPC server will not start");
+        None
+    });
+
+    // Spawn HTTP server task for health checks and admin endpoints
+    let http_config = config.clone();
+    let http_handle = tokio::spawn(async move {
+        http_server::start_http_server(http_config).await;
+    });
+
+    // With GRACEFUL_RESTART, SIGUSR2 hands the listeners to a new process and ends this one once drained
+    let restart_drain = config.graceful_restart.then_some(Duration::from_secs(config.drain_timeout_secs));
+    let restarted = async move {
+        match restart_drain {
+            Some(drain_timeout) => restart::run(drain_timeout).await,
+            None => std::future::pending().await,
+        }
+    };
 
     // Spawn WebSocket server task
+    let ws_sender = sender.clone();
     let ws_handle = tokio::spawn(async move {
-        ws_server::start_ws_server(config.server_port, sender.subscribe()).await;
+        ws_server::start_ws_server(config, ws_sender, replay, state).await;
     });
 
     // Wait for shutdown signal
     info!("Service running. Press Ctrl+C to shutdown gracefully...");
     
-    match signal::ctrl_c().await {
-        Ok(()) => {
-            info!("Shutdown signal received. Gracefully shutting down...");
+    // The listener only returns when reconnecting can't help; the service then shuts
+    // down like it does on a signal and exits with a failure status
+    let mut listener_failed = false;
+    tokio::select! {
+        result = signal::ctrl_c() => match result {
+            Ok(()) => {
+                info!("Shutdown signal received. Gracefully shutting down...");
+            }
+            Err(err) => {
+                error!("Unable to listen for shutdown signal: {}", err);
+            }
+        },
+        _ = restarted => {
+            info!("Handed over to the new process. Shutting down...");
         }
-        Err(err) => {
-            error!("Unable to listen for shutdown signal: {}", err);
+        result = &mut solana_handle => {
+            if let Ok(Err(e)) = result {
+                error!("{}", e);
+            }
+            error!("Solana listener stopped. Shutting down...");
+            listener_failed = true;
         }
     }
 
@@ -52,12 +322,89 @@ async fn main() {
     // Cancel all tasks
     solana_handle.abort();
     ws_handle.abort();
+    http_handle.abort();
+    metrics_handle.abort();
+    saturation_handle.abort();
+    webhook_handle.abort();
+    if let Some(handle) = &uds_handle {
+        handle.abort();
+    }
+    if let Some(handle) = &schedule_handle {
+        handle.abort();
+    }
+    if let Some(handle) = &throttle_handle {
+        handle.abort();
+    }
+    if let Some(handle) = &leaders_handle {
+        handle.abort();
+    }
+    if let Some(handle) = &stall_handle {
+        handle.abort();
+    }
+    if let Some(handle) = &expiry_handle {
+        handle.abort();
+    }
+    if let Some(handle) = &clock_handle {
+        handle.abort();
+    }
+    if let Some(handle) = &reconcile_handle {
+        handle.abort();
+    }
+    if let Some(handle) = &groups_handle {
+        handle.abort();
+    }
+    if let Some(handle) = &grpc_handle {
+        handle.abort();
+    }
     
     // Wait for tasks to finish
+    if !listener_failed {
+        let _ = solana_handle.await;
+    }
     let _ = tokio::join!(
-        solana_handle,
-        ws_handle
+        ws_handle,
+        http_handle,
+        metrics_handle,
+        saturation_handle,
+        webhook_handle
     );
+    if let Some(handle) = uds_handle {
+        let _ = handle.await;
+    }
+    if let Some(handle) = schedule_handle {
+        let _ = handle.await;
+    }
+    if let Some(handle) = throttle_handle {
+        let _ = handle.await;
+    }
+    if let Some(handle) = leaders_handle {
+        let _ = handle.await;
+    }
+    if let Some(handle) = stall_handle {
+        let _ = handle.await;
+    }
+    if let Some(handle) = expiry_handle {
+        let _ = handle.await;
+    }
+    if let Some(handle) = clock_handle {
+        let _ = handle.await;
+    }
+    if let Some(handle) = reconcile_handle {
+        let _ = handle.await;
+    }
+    if let Some(handle) = groups_handle {
+        let _ = handle.await;
+    }
+    if let Some(handle) = grpc_handle {
+        let _ = handle.await;
+    }
+    if let Some(path) = &uds_path {
+        uds_server::cleanup(path);
+    }
     
+    telemetry::shutdown();
     info!("Service shutdown complete.");
+    if listener_failed {
+        std::process::exit(1);
+    }
 }
\ No newline at end of file
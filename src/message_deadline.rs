@@ -0,0 +1,151 @@
+//! Per-message read deadline for client connections, so a client trickling a frame
+//! a byte at a time cannot hold its read task open indefinitely
+
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::time::{Instant, Sleep};
+
+/// When the first byte of the message being received arrived, shared with the reader
+#[derive(Clone, Default)]
+pub struct MessageClock(Arc<Mutex<Option<Instant>>>);
+
+impl MessageClock {
+    /// Marks the current message complete; the deadline restarts with the next byte
+    pub fn reset(&self) {
+        *self.0.lock().unwrap() = None;
+    }
+
+    fn started(&self) -> Option<Instant> {
+        *self.0.lock().unwrap()
+    }
+
+    fn start(&self) {
+        self.0.lock().unwrap().get_or_insert_with(Instant::now);
+    }
+}
+
+/// Wraps a client stream so reads fail with `TimedOut` once a message has been
+/// arriving for longer than `timeout`; without a timeout it only passes reads through
+///
+/// The stream cannot see message boundaries, so the reader calls
+/// `MessageClock::reset` after each complete message.
+pub struct DeadlineStream<S> {
+    inner: S,
+    clock: MessageClock,
+    timeout: Option<Duration>,
+    sleep: Pin<Box<Sleep>>,
+}
+
+impl<S> DeadlineStream<S> {
+    pub fn new(inner: S, timeout: Option<Duration>, clock: MessageClock) -> Self {
+        DeadlineStream {
+            inner,
+            clock,
+            timeout,
+            sleep: Box::pin(tokio::time::sleep(Duration::ZERO)),
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for DeadlineStream<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let filled = buf.filled().len();
+        let read = Pin::new(&mut self.inner).poll_read(cx, buf);
+        let Some(timeout) = self.timeout else { return read };
+        if matches!(read, Poll::Ready(Ok(()))) && buf.filled().len() > filled {
+            self.clock.start();
+        }
+        let Some(started) = self.clock.started() else { return read };
+        let deadline = started + timeout;
+        if Instant::now() >= deadline {
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, "message not completed in time")));
+        }
+        if read.is_pending() {
+            // Wake at the deadline even if the client sends nothing more
+            if self.sleep.deadline() != deadline {
+                self.sleep.as_mut().reset(deadline);
+            }
+            if self.sleep.as_mut().poll(cx).is_ready() {
+                return Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, "message not completed in time")));
+            }
+        }
+        read
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for DeadlineStream<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use tokio::io::AsyncWriteExt;
+    use tokio_tungstenite::WebSocketStream;
+    use tungstenite::protocol::Role;
+
+    const TIMEOUT: Duration = Duration::from_millis(100);
+
+    /// A masked text frame, as a client sends it
+    fn client_frame(text: &str) -> Vec<u8> {
+        let mask = [1, 2, 3, 4];
+        let mut frame = vec![0x81, 0x80 | text.len() as u8];
+        frame.extend_from_slice(&mask);
+        frame.extend(text.bytes().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+        frame
+    }
+
+    async fn server(timeout: Option<Duration>) -> (WebSocketStream<DeadlineStream<tokio::io::DuplexStream>>, tokio::io::DuplexStream, MessageClock) {
+        let (client, server) = tokio::io::duplex(1024);
+        let clock = MessageClock::default();
+        let stream = DeadlineStream::new(server, timeout, clock.clone());
+        (WebSocketStream::from_raw_socket(stream, Role::Server, None).await, client, clock)
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn trickled_message_times_out() {
+        let (mut server, mut client, _clock) = server(Some(TIMEOUT)).await;
+        tokio::spawn(async move {
+            for byte in client_frame(r#"{"action":"pause"}"#) {
+                if client.write_all(&[byte]).await.is_err() {
+                    return;
+                }
+                tokio::time::sleep(TIMEOUT / 4).await;
+            }
+            std::future::pending::<()>().await;
+        });
+        let started = Instant::now();
+        match server.next().await {
+            Some(Err(tungstenite::Error::Io(e))) => assert_eq!(e.kind(), io::ErrorKind::TimedOut),
+            other => panic!("expected a timeout, got {:?}", other),
+        }
+        assert!(started.elapsed() < TIMEOUT * 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn deadline_restarts_with_each_message() {
+        let (mut server, mut client, clock) = server(Some(TIMEOUT)).await;
+        for _ in 0..3 {
+            client.write_all(&client_frame(r#"{"action":"resume"}"#)).await.unwrap();
+            assert!(matches!(server.next().await, Some(Ok(tungstenite::Message::Text(_)))));
+            clock.reset();
+            tokio::time::sleep(TIMEOUT * 2).await;
+        }
+    }
+}
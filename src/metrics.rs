@@ -0,0 +1,611 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use serde_json::{json, Value};
+use crate::creation_throttle;
+use crate::creator_tracking;
+use crate::event_channels::EventChannels;
+use crate::launch_metadata;
+use crate::parse_watchdog;
+use crate::reconciliation;
+use crate::solana_client;
+use crate::ws_server;
+
+// Launches dropped from a full creation rate-limit queue
+static CREATIONS_DROPPED: AtomicU64 = AtomicU64::new(0);
+
+// Events sent at processed commitment whose slot was not confirmed
+static EVENTS_REVERTED: AtomicU64 = AtomicU64::new(0);
+
+// Pipeline tasks restarted by the supervisor after a panic
+static TASK_RESTARTS: AtomicU64 = AtomicU64::new(0);
+
+// Events dropped because VALIDATE_PUBKEYS rejected their account address
+static INVALID_PUBKEYS: AtomicU64 = AtomicU64::new(0);
+
+// Events dropped because their decoded values failed the sanity checks
+static DECODE_ANOMALIES: AtomicU64 = AtomicU64::new(0);
+
+// Logs notifications whose log array was only partly scanned under LOG_SCAN_MAX_LINES or LOG_SCAN_MAX_BYTES
+static LOGS_TRUNCATED: AtomicU64 = AtomicU64::new(0);
+
+// Updates suppressed as replays of pre-disconnect state after a resubscribe
+static REPLAY_SUPPRESSED: AtomicU64 = AtomicU64::new(0);
+
+// RPC connections torn down by the stall watchdog
+static STREAM_STALLS: AtomicU64 = AtomicU64::new(0);
+
+// Reconnect storms detected on the RPC connection
+static RECONNECT_STORMS: AtomicU64 = AtomicU64::new(0);
+
+// Times every RPC endpoint was declared down
+static UPSTREAM_OUTAGES: AtomicU64 = AtomicU64::new(0);
+
+// Buy and sell events recovered from transaction history after a reconnect
+static BACKFILLED_EVENTS: AtomicU64 = AtomicU64::new(0);
+
+// Times the parser watchdog saw most notifications failing to parse
+static PARSE_WATCHDOG_TRIPS: AtomicU64 = AtomicU64::new(0);
+
+// RPC connect, subscribe or read failures, each followed by a backoff
+static RPC_CONNECTION_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+// Messages read from the RPC node and from clients, their total size, and the largest one seen.
+// tungstenite reassembles fragmented messages before handing them over, so sizes are per message.
+static RPC_MESSAGES: AtomicU64 = AtomicU64::new(0);
+static RPC_MESSAGE_BYTES: AtomicU64 = AtomicU64::new(0);
+static RPC_LARGEST_MESSAGE: AtomicU64 = AtomicU64::new(0);
+static CLIENT_MESSAGES: AtomicU64 = AtomicU64::new(0);
+static CLIENT_MESSAGE_BYTES: AtomicU64 = AtomicU64::new(0);
+static CLIENT_LARGEST_MESSAGE: AtomicU64 = AtomicU64::new(0);
+// Client connections closed for taking longer than CLIENT_MESSAGE_TIMEOUT_MS to send a message
+static CLIENT_MESSAGE_TIMEOUTS: AtomicU64 = AtomicU64::new(0);
+
+// New mints whose creator exceeded CREATOR_LAUNCH_LIMIT, flagged or suppressed
+static CREATOR_SPAM: AtomicU64 = AtomicU64::new(0);
+
+// Mints whose progress crossed NEAR_GRADUATION_ALERT_PROGRESS upward
+static NEAR_GRADUATION_ALERTS: AtomicU64 = AtomicU64::new(0);
+
+// Mints flagged by WASH_TRADE_DETECTION
+static WASH_TRADE_FLAGS: AtomicU64 = AtomicU64::new(0);
+
+// Events held in the replay buffer, and the timestamp in ms of the oldest (0 when empty)
+static REPLAY_DEPTH: AtomicU64 = AtomicU64::new(0);
+static REPLAY_OLDEST_MS: AtomicU64 = AtomicU64::new(0);
+
+// Mints currently held in the state index, and mints evicted to respect MAX_TRACKED_MINTS
+static TRACKED_MINTS: AtomicU64 = AtomicU64::new(0);
+static MINT_EVICTIONS: AtomicU64 = AtomicU64::new(0);
+
+// Untracked mints the seen-mints filter recognized, emitted as trades instead of new tokens
+static MINTS_READMITTED: AtomicU64 = AtomicU64::new(0);
+
+// Webhook deliveries that succeeded, were given up on, or were skipped because the sink lagged
+static WEBHOOK_DELIVERED: AtomicU64 = AtomicU64::new(0);
+static WEBHOOK_DEAD_LETTERS: AtomicU64 = AtomicU64::new(0);
+static WEBHOOK_DROPPED: AtomicU64 = AtomicU64::new(0);
+
+// Broadcast channel gauges, refreshed by `sample_broadcast_channel`
+static BROADCAST_QUEUE_DEPTH: AtomicU64 = AtomicU64::new(0);
+static CRITICAL_QUEUE_DEPTH: AtomicU64 = AtomicU64::new(0);
+static BROADCAST_RECEIVERS: AtomicU64 = AtomicU64::new(0);
+static BROADCAST_SHARD_RECEIVERS: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+static BROADCAST_BUFFER_CAPACITY: AtomicU64 = AtomicU64::new(0);
+
+// Times the broadcast buffer stayed saturated for BUFFER_SATURATION_SECS, and clients shed for it
+static BUFFER_SATURATIONS: AtomicU64 = AtomicU64::new(0);
+static CLIENTS_SHED: AtomicU64 = AtomicU64::new(0);
+
+// Open gRPC event streams
+static GRPC_STREAMS: AtomicU64 = AtomicU64::new(0);
+
+// Events broadcast while nothing was subscribed; they still reach the replay buffer
+static EVENTS_WITHOUT_SUBSCRIBERS: AtomicU64 = AtomicU64::new(0);
+
+// Upper bounds of the client lag histogram, in events skipped per lag
+const CLIENT_LAG_BUCKETS: [u64; 5] = [1, 10, 100, 1000, 10000];
+static CLIENT_LAG_BUCKET_COUNTS: [AtomicU64; 5] = [const { AtomicU64::new(0) }; 5];
+static CLIENT_LAG_SUM: AtomicU64 = AtomicU64::new(0);
+static CLIENT_LAG_COUNT: AtomicU64 = AtomicU64::new(0);
+
+// Send times of events broadcast within the last minute
+static RECENT_BROADCASTS: Mutex<VecDeque<Instant>> = Mutex::new(VecDeque::new());
+const EVENT_RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// Counts a stretch of time the broadcast buffer stayed saturated
+pub fn record_buffer_saturation() {
+    BUFFER_SATURATIONS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts clients disconnected for falling behind a saturated buffer
+pub fn record_clients_shed(clients: u64) {
+    CLIENTS_SHED.fetch_add(clients, Ordering::Relaxed);
+}
+
+/// Fraction of the broadcast buffer in use, as of the last sample
+fn buffer_saturation() -> f64 {
+    let capacity = BROADCAST_BUFFER_CAPACITY.load(Ordering::Relaxed);
+    match capacity {
+        0 => 0.0,
+        capacity => (BROADCAST_QUEUE_DEPTH.load(Ordering::Relaxed) as f64 / capacity as f64).min(1.0),
+    }
+}
+
+/// Counts a launch dropped from the full creation rate-limit queue
+pub fn record_creation_dropped() {
+    CREATIONS_DROPPED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts a supervised task restarted after a panic and returns the new total
+pub fn record_task_restart() -> u64 {
+    TASK_RESTARTS.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Counts an event dropped for a malformed pubkey and returns the new total
+pub fn record_invalid_pubkey() -> u64 {
+    INVALID_PUBKEYS.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Counts a decode anomaly and returns the new total
+pub fn record_decode_anomaly() -> u64 {
+    DECODE_ANOMALIES.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Counts a log array cut short by the scan limits and returns the new total
+pub fn record_logs_truncated() -> u64 {
+    LOGS_TRUNCATED.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Counts an update suppressed during the post-resubscribe grace window
+pub fn record_replay_suppressed() {
+    REPLAY_SUPPRESSED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts a stalled RPC subscription and returns the new total
+pub fn record_stream_stall() -> u64 {
+    STREAM_STALLS.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Counts an `event_reverted` notice
+pub fn record_event_reverted() {
+    EVENTS_REVERTED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts the start of a reconnect storm
+pub fn record_reconnect_storm() {
+    RECONNECT_STORMS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts the start of an all upstreams down outage
+pub fn record_upstream_outage() {
+    UPSTREAM_OUTAGES.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts a trade sent by a reconnect backfill
+pub fn record_backfilled_event() {
+    BACKFILLED_EVENTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts the parser watchdog tripping
+pub fn record_parse_watchdog_trip() {
+    PARSE_WATCHDOG_TRIPS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts a failed or dropped RPC connection and returns the new total
+pub fn record_rpc_connection_failure() -> u64 {
+    RPC_CONNECTION_FAILURES.fetch_add(1, Ordering::Relaxed) + 1
+}
+
+/// Records a message of `len` bytes read from the RPC connection
+pub fn record_rpc_message(len: usize) {
+    RPC_MESSAGES.fetch_add(1, Ordering::Relaxed);
+    RPC_MESSAGE_BYTES.fetch_add(len as u64, Ordering::Relaxed);
+    RPC_LARGEST_MESSAGE.fetch_max(len as u64, Ordering::Relaxed);
+}
+
+/// Records a message of `len` bytes read from a client connection
+pub fn record_client_message(len: usize) {
+    CLIENT_MESSAGES.fetch_add(1, Ordering::Relaxed);
+    CLIENT_MESSAGE_BYTES.fetch_add(len as u64, Ordering::Relaxed);
+    CLIENT_LARGEST_MESSAGE.fetch_max(len as u64, Ordering::Relaxed);
+}
+
+/// Counts a client connection closed because a message arrived too slowly
+pub fn record_client_message_timeout() {
+    CLIENT_MESSAGE_TIMEOUTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts a launch over the per-creator limit
+pub fn record_creator_spam() {
+    CREATOR_SPAM.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts a `near_graduation` alert
+pub fn record_near_graduation_alert() {
+    NEAR_GRADUATION_ALERTS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts a `suspicious_activity` flag for likely wash trading
+pub fn record_wash_trade_flag() {
+    WASH_TRADE_FLAGS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Updates the replay buffer size and the timestamp of its oldest event
+pub fn set_replay_buffer(depth: usize, oldest: Option<chrono::DateTime<chrono::Utc>>) {
+    REPLAY_DEPTH.store(depth as u64, Ordering::Relaxed);
+    REPLAY_OLDEST_MS.store(oldest.map_or(0, |timestamp| timestamp.timestamp_millis().max(1) as u64), Ordering::Relaxed);
+}
+
+/// Age of the oldest event in the replay buffer, if it holds any
+fn replay_oldest_age_secs() -> Option<f64> {
+    let oldest = REPLAY_OLDEST_MS.load(Ordering::Relaxed);
+    (oldest > 0).then(|| (chrono::Utc::now().timestamp_millis() - oldest as i64).max(0) as f64 / 1000.0)
+}
+
+/// Updates the number of mints held in the state index
+pub fn set_tracked_mints(count: usize) {
+    TRACKED_MINTS.store(count as u64, Ordering::Relaxed);
+}
+
+/// Counts a mint the seen-mints filter recognized after it was evicted
+pub fn record_mint_readmitted() {
+    MINTS_READMITTED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts mints evicted from the state index
+pub fn record_mint_evictions(count: u64) {
+    MINT_EVICTIONS.fetch_add(count, Ordering::Relaxed);
+}
+
+/// Counts a webhook delivery that got a 2xx response
+pub fn record_webhook_delivered() {
+    WEBHOOK_DELIVERED.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts a webhook delivery that failed every retry
+pub fn record_webhook_dead_letter() {
+    WEBHOOK_DEAD_LETTERS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Counts events the webhook sink missed because it fell behind the broadcast channel
+pub fn record_webhook_dropped(skipped: u64) {
+    WEBHOOK_DROPPED.fetch_add(skipped, Ordering::Relaxed);
+}
+
+#[cfg(feature = "grpc")]
+pub fn record_grpc_stream_opened() {
+    GRPC_STREAMS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[cfg(feature = "grpc")]
+pub fn record_grpc_stream_closed() {
+    GRPC_STREAMS.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// Records an event broadcast to clients for the last-minute event rate
+pub fn record_event_broadcast() {
+    let now = Instant::now();
+    let mut recent = RECENT_BROADCASTS.lock().unwrap();
+    recent.push_back(now);
+    prune_before(&mut recent, now);
+}
+
+/// Number of events broadcast in the last minute
+pub fn events_last_minute() -> usize {
+    let mut recent = RECENT_BROADCASTS.lock().unwrap();
+    prune_before(&mut recent, Instant::now());
+    recent.len()
+}
+
+fn prune_before(recent: &mut VecDeque<Instant>, now: Instant) {
+    while recent.front().is_some_and(|sent| now.duration_since(*sent) > EVENT_RATE_WINDOW) {
+        recent.pop_front();
+    }
+}
+
+/// Counts an event broadcast while no client or sink was subscribed
+pub fn record_event_without_subscribers() {
+    EVENTS_WITHOUT_SUBSCRIBERS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Records a client falling behind the broadcast channel by `skipped` events
+pub fn record_client_lag(skipped: u64) {
+    for (bound, count) in CLIENT_LAG_BUCKETS.iter().zip(&CLIENT_LAG_BUCKET_COUNTS) {
+        if skipped <= *bound {
+            count.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    CLIENT_LAG_SUM.fetch_add(skipped, Ordering::Relaxed);
+    CLIENT_LAG_COUNT.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Periodically samples the broadcast channels' queue depths and receiver count
+///
+/// Runs until the task is aborted.
+pub async fn sample_broadcast_channel(sender: EventChannels, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        let (depth, critical_depth) = sender.queue_depths();
+        BROADCAST_QUEUE_DEPTH.store(depth as u64, Ordering::Relaxed);
+        CRITICAL_QUEUE_DEPTH.store(critical_depth.unwrap_or(0) as u64, Ordering::Relaxed);
+        let shard_receivers: Vec<u64> = sender.shard_receiver_counts().into_iter().map(|count| count as u64).collect();
+        BROADCAST_RECEIVERS.store(shard_receivers.iter().sum(), Ordering::Relaxed);
+        *BROADCAST_SHARD_RECEIVERS.lock().unwrap() = shard_receivers;
+        BROADCAST_BUFFER_CAPACITY.store(sender.buffer_capacity() as u64, Ordering::Relaxed);
+    }
+}
+
+/// Current values as JSON for the `/stats` endpoint
+pub fn stats() -> Value {
+    let lag_buckets: serde_json::Map<String, Value> = CLIENT_LAG_BUCKETS.iter()
+        .zip(&CLIENT_LAG_BUCKET_COUNTS)
+        .map(|(bound, count)| (bound.to_string(), count.load(Ordering::Relaxed).into()))
+        .collect();
+    let mut stats = json!({
+        "active_connections": ws_server::get_active_connections(),
+        "paused_connections": ws_server::get_paused_connections(),
+        "grpc_streams": GRPC_STREAMS.load(Ordering::Relaxed),
+        "subscription_healthy": solana_client::is_subscription_healthy(),
+        "last_notification_age_secs": solana_client::last_notification_age().map(|age| age.as_secs_f64()),
+        "broadcast_queue_depth": BROADCAST_QUEUE_DEPTH.load(Ordering::Relaxed),
+        "critical_queue_depth": CRITICAL_QUEUE_DEPTH.load(Ordering::Relaxed),
+        "broadcast_receivers": BROADCAST_RECEIVERS.load(Ordering::Relaxed),
+        "broadcast_shard_receivers": BROADCAST_SHARD_RECEIVERS.lock().unwrap().clone(),
+        "events_without_subscribers": EVENTS_WITHOUT_SUBSCRIBERS.load(Ordering::Relaxed),
+        "client_lag": {
+            "count": CLIENT_LAG_COUNT.load(Ordering::Relaxed),
+            "skipped_total": CLIENT_LAG_SUM.load(Ordering::Relaxed),
+            "buckets": lag_buckets,
+        },
+        "decode_anomalies": DECODE_ANOMALIES.load(Ordering::Relaxed),
+        "invalid_pubkeys": INVALID_PUBKEYS.load(Ordering::Relaxed),
+        "task_restarts": TASK_RESTARTS.load(Ordering::Relaxed),
+        "resubscribe_replays_suppressed": REPLAY_SUPPRESSED.load(Ordering::Relaxed),
+        "stream_stalls": STREAM_STALLS.load(Ordering::Relaxed),
+        "reconnect_storms": RECONNECT_STORMS.load(Ordering::Relaxed),
+        "rpc_connection_failures": RPC_CONNECTION_FAILURES.load(Ordering::Relaxed),
+        "all_upstreams_down": solana_client::all_upstreams_down(),
+        "upstream_outages": UPSTREAM_OUTAGES.load(Ordering::Relaxed),
+        "backfilled_events": BACKFILLED_EVENTS.load(Ordering::Relaxed),
+        "parser_failing": parse_watchdog::is_tripped(),
+        "parse_watchdog_trips": PARSE_WATCHDOG_TRIPS.load(Ordering::Relaxed),
+        "upstreams": solana_client::upstream_stats(),
+        "messages_read": {
+            "rpc": {
+                "count": RPC_MESSAGES.load(Ordering::Relaxed),
+                "bytes": RPC_MESSAGE_BYTES.load(Ordering::Relaxed),
+                "largest_bytes": RPC_LARGEST_MESSAGE.load(Ordering::Relaxed),
+            },
+            "clients": {
+                "count": CLIENT_MESSAGES.load(Ordering::Relaxed),
+                "bytes": CLIENT_MESSAGE_BYTES.load(Ordering::Relaxed),
+                "largest_bytes": CLIENT_LARGEST_MESSAGE.load(Ordering::Relaxed),
+                "timeouts": CLIENT_MESSAGE_TIMEOUTS.load(Ordering::Relaxed),
+            },
+        },
+        "creator_spam_launches": CREATOR_SPAM.load(Ordering::Relaxed),
+        "near_graduation_alerts": NEAR_GRADUATION_ALERTS.load(Ordering::Relaxed),
+        "wash_trade_flags": WASH_TRADE_FLAGS.load(Ordering::Relaxed),
+        "tracked_creators": creator_tracking::tracked_total(),
+        "creation_queue_depth": creation_throttle::queue_depth(),
+        "creations_dropped": CREATIONS_DROPPED.load(Ordering::Relaxed),
+        "events_reverted": EVENTS_REVERTED.load(Ordering::Relaxed),
+        "reconciliation_pending_slots": reconciliation::pending_slots(),
+        "replay_buffer": {
+            "depth": REPLAY_DEPTH.load(Ordering::Relaxed),
+            "oldest_event_age_secs": replay_oldest_age_secs(),
+        },
+        "tracked_mints": TRACKED_MINTS.load(Ordering::Relaxed),
+        "mint_evictions": MINT_EVICTIONS.load(Ordering::Relaxed),
+        "mints_readmitted": MINTS_READMITTED.load(Ordering::Relaxed),
+        "webhooks": {
+            "delivered": WEBHOOK_DELIVERED.load(Ordering::Relaxed),
+            "dead_letters": WEBHOOK_DEAD_LETTERS.load(Ordering::Relaxed),
+            "dropped": WEBHOOK_DROPPED.load(Ordering::Relaxed),
+        },
+    });
+    // Added separately, as the stats above already reach the json! macro's recursion limit
+    stats["broadcast_buffer"] = json!({
+        "capacity": BROADCAST_BUFFER_CAPACITY.load(Ordering::Relaxed),
+        "saturation": buffer_saturation(),
+        "saturations": BUFFER_SATURATIONS.load(Ordering::Relaxed),
+        "clients_shed": CLIENTS_SHED.load(Ordering::Relaxed),
+    });
+    stats["launch_metadata_entries"] = launch_metadata::len().into();
+    stats["logs_truncated"] = LOGS_TRUNCATED.load(Ordering::Relaxed).into();
+    stats
+}
+
+/// Renders all metrics in the Prometheus text exposition format
+pub fn render_prometheus() -> String {
+    let mut out = String::new();
+    write_metric(&mut out, "pumpfun_task_restarts_total", "counter",
+        "Pipeline tasks restarted after a panic",
+        TASK_RESTARTS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_invalid_pubkeys_total", "counter",
+        "Events dropped because their account address is not a valid base58 public key",
+        INVALID_PUBKEYS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_decode_anomalies_total", "counter",
+        "Events dropped because decoded values failed sanity checks",
+        DECODE_ANOMALIES.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_logs_truncated_total", "counter",
+        "Logs notifications scanned only up to LOG_SCAN_MAX_LINES or LOG_SCAN_MAX_BYTES",
+        LOGS_TRUNCATED.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_resubscribe_replays_suppressed_total", "counter",
+        "Updates suppressed as replays of pre-disconnect state after a resubscribe",
+        REPLAY_SUPPRESSED.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_stream_stalls_total", "counter",
+        "RPC connections torn down because no notification arrived within STREAM_STALL_TIMEOUT_SECS",
+        STREAM_STALLS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_reconnect_storms_total", "counter",
+        "Times the RPC connection reconnected RECONNECT_STORM_THRESHOLD times within RECONNECT_STORM_WINDOW_SECS",
+        RECONNECT_STORMS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_all_upstreams_down", "gauge",
+        "1 while every RPC endpoint has failed UPSTREAM_DOWN_AFTER_FAILURES connection attempts in a row",
+        solana_client::all_upstreams_down() as u64);
+    write_metric(&mut out, "pumpfun_upstream_outages_total", "counter",
+        "Times every RPC endpoint was declared down",
+        UPSTREAM_OUTAGES.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_backfilled_events_total", "counter",
+        "Buy and sell events recovered from transaction history after an RPC reconnect",
+        BACKFILLED_EVENTS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_parser_failing", "gauge",
+        "1 while at least PARSE_WATCHDOG_FAILURE_RATIO of recent RPC notifications fail to parse",
+        parse_watchdog::is_tripped() as u64);
+    write_metric(&mut out, "pumpfun_parse_watchdog_trips_total", "counter",
+        "Times the parser watchdog found most RPC notifications failing to parse",
+        PARSE_WATCHDOG_TRIPS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_rpc_connection_failures_total", "counter",
+        "RPC connections that failed to connect or subscribe, or dropped, each followed by an exponential backoff",
+        RPC_CONNECTION_FAILURES.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_rpc_messages_read_total", "counter",
+        "WebSocket messages read from the RPC node, after reassembly of fragments",
+        RPC_MESSAGES.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_rpc_message_bytes_total", "counter",
+        "Bytes in WebSocket messages read from the RPC node",
+        RPC_MESSAGE_BYTES.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_rpc_largest_message_bytes", "gauge",
+        "Largest WebSocket message read from the RPC node",
+        RPC_LARGEST_MESSAGE.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_client_messages_read_total", "counter",
+        "WebSocket messages read from clients, after reassembly of fragments",
+        CLIENT_MESSAGES.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_client_message_bytes_total", "counter",
+        "Bytes in WebSocket messages read from clients",
+        CLIENT_MESSAGE_BYTES.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_client_largest_message_bytes", "gauge",
+        "Largest WebSocket message read from a client",
+        CLIENT_LARGEST_MESSAGE.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_client_message_timeouts_total", "counter",
+        "Client connections closed for not completing a message within CLIENT_MESSAGE_TIMEOUT_MS",
+        CLIENT_MESSAGE_TIMEOUTS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_creator_spam_launches_total", "counter",
+        "New mints whose creator exceeded CREATOR_LAUNCH_LIMIT within CREATOR_LAUNCH_WINDOW_SECS",
+        CREATOR_SPAM.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_near_graduation_alerts_total", "counter",
+        "near_graduation alerts sent for mints crossing NEAR_GRADUATION_ALERT_PROGRESS",
+        NEAR_GRADUATION_ALERTS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_wash_trade_flags_total", "counter",
+        "suspicious_activity flags sent for mints whose recent trades look like wash trading",
+        WASH_TRADE_FLAGS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_tracked_creators", "gauge",
+        "Creators followed with track_creator, counted once per connection",
+        creator_tracking::tracked_total());
+    write_metric(&mut out, "pumpfun_creation_queue_depth", "gauge",
+        "Launches queued by MAX_NEW_TOKENS_PER_SEC and not yet sent",
+        creation_throttle::queue_depth());
+    write_metric(&mut out, "pumpfun_creations_dropped_total", "counter",
+        "Launches dropped because the MAX_NEW_TOKENS_PER_SEC queue was full",
+        CREATIONS_DROPPED.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_events_reverted_total", "counter",
+        "Events sent at processed commitment whose slot never reached confirmed (RECONCILE_PROCESSED)",
+        EVENTS_REVERTED.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_reconciliation_pending_slots", "gauge",
+        "Slots with sent events not yet checked against the confirmed chain",
+        reconciliation::pending_slots());
+    write_metric(&mut out, "pumpfun_launch_metadata_entries", "gauge",
+        "Launches whose CreateEvent metadata is held for filling in curve updates",
+        launch_metadata::len());
+    write_metric(&mut out, "pumpfun_replay_buffer_depth", "gauge",
+        "Events held in the replay buffer",
+        REPLAY_DEPTH.load(Ordering::Relaxed));
+    if let Some(age) = replay_oldest_age_secs() {
+        write_metric(&mut out, "pumpfun_replay_oldest_event_age_seconds", "gauge",
+            "Age of the oldest event in the replay buffer",
+            age);
+    }
+    write_metric(&mut out, "pumpfun_tracked_mints", "gauge",
+        "Mints held in the in-memory state index",
+        TRACKED_MINTS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_mint_evictions_total", "counter",
+        "Mints evicted from the state index to stay within MAX_TRACKED_MINTS",
+        MINT_EVICTIONS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_mints_readmitted_total", "counter",
+        "Untracked mints the seen-mints filter recognized and emitted as trades rather than new tokens",
+        MINTS_READMITTED.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_subscription_healthy", "gauge",
+        "1 while the RPC subscription is confirmed and notifications keep arriving, otherwise 0",
+        solana_client::is_subscription_healthy() as u64);
+    if let Some(age) = solana_client::last_notification_age() {
+        write_metric(&mut out, "pumpfun_last_notification_age_seconds", "gauge",
+            "Seconds since the last notification from the RPC subscription",
+            age.as_secs_f64());
+    }
+    write_metric(&mut out, "pumpfun_active_connections", "gauge",
+        "Currently connected WebSocket clients",
+        ws_server::get_active_connections() as u64);
+    write_metric(&mut out, "pumpfun_paused_connections", "gauge",
+        "Connected clients that have paused delivery with the pause command",
+        ws_server::get_paused_connections() as u64);
+    write_metric(&mut out, "pumpfun_grpc_streams", "gauge",
+        "Open SubscribeEvents streams on the gRPC server",
+        GRPC_STREAMS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_broadcast_queue_depth", "gauge",
+        "Events queued in the broadcast channel that not every client has received yet",
+        BROADCAST_QUEUE_DEPTH.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_critical_queue_depth", "gauge",
+        "Events queued in the critical channel that not every client has received yet (priority mode only)",
+        CRITICAL_QUEUE_DEPTH.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_broadcast_receivers", "gauge",
+        "Receivers subscribed to the broadcast channel",
+        BROADCAST_RECEIVERS.load(Ordering::Relaxed));
+    write_shard_receivers(&mut out);
+    write_metric(&mut out, "pumpfun_broadcast_buffer_capacity", "gauge",
+        "Events the broadcast channel currently holds for a slow client; grows with BROADCAST_BUFFER=adaptive",
+        BROADCAST_BUFFER_CAPACITY.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_broadcast_buffer_saturation", "gauge",
+        "Fraction of the broadcast buffer queued for at least one client, from 0 to 1",
+        buffer_saturation());
+    write_metric(&mut out, "pumpfun_broadcast_buffer_saturations_total", "counter",
+        "Times the broadcast buffer stayed at least 90% full for BUFFER_SATURATION_SECS",
+        BUFFER_SATURATIONS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_slow_clients_shed_total", "counter",
+        "Clients disconnected by BUFFER_SATURATION_ACTION=shed for falling behind a saturated buffer",
+        CLIENTS_SHED.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_events_without_subscribers_total", "counter",
+        "Events broadcast while no client or sink was subscribed",
+        EVENTS_WITHOUT_SUBSCRIBERS.load(Ordering::Relaxed));
+    write_client_lag_histogram(&mut out);
+    write_metric(&mut out, "pumpfun_webhook_delivered_total", "counter",
+        "Webhook deliveries that received a 2xx response",
+        WEBHOOK_DELIVERED.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_webhook_dead_letters_total", "counter",
+        "Webhook deliveries given up on after every retry failed",
+        WEBHOOK_DEAD_LETTERS.load(Ordering::Relaxed));
+    write_metric(&mut out, "pumpfun_webhook_dropped_total", "counter",
+        "Events not delivered to webhooks because the sink fell behind",
+        WEBHOOK_DROPPED.load(Ordering::Relaxed));
+    out
+}
+
+fn write_shard_receivers(out: &mut String) {
+    let name = "pumpfun_broadcast_shard_receivers";
+    let _ = writeln!(out, "# HELP {} Receivers subscribed to each broadcast shard (BROADCAST_SHARDS)", name);
+    let _ = writeln!(out, "# TYPE {} gauge", name);
+    for (shard, count) in BROADCAST_SHARD_RECEIVERS.lock().unwrap().iter().enumerate() {
+        let _ = writeln!(out, "{}{{shard=\"{}\"}} {}", name, shard, count);
+    }
+}
+
+fn write_client_lag_histogram(out: &mut String) {
+    let name = "pumpfun_client_lag_events";
+    let _ = writeln!(out, "# HELP {} Events skipped each time a client fell behind the broadcast channel", name);
+    let _ = writeln!(out, "# TYPE {} histogram", name);
+    for (bound, count) in CLIENT_LAG_BUCKETS.iter().zip(&CLIENT_LAG_BUCKET_COUNTS) {
+        let _ = writeln!(out, "{}_bucket{{le=\"{}\"}} {}", name, bound, count.load(Ordering::Relaxed));
+    }
+    let total = CLIENT_LAG_COUNT.load(Ordering::Relaxed);
+    let _ = writeln!(out, "{}_bucket{{le=\"+Inf\"}} {}", name, total);
+    let _ = writeln!(out, "{}_sum {}", name, CLIENT_LAG_SUM.load(Ordering::Relaxed));
+    let _ = writeln!(out, "{}_count {}", name, total);
+}
+
+fn write_metric(out: &mut String, name: &str, kind: &str, help: &str, value: impl std::fmt::Display) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} {}", name, kind);
+    let _ = writeln!(out, "{} {}", name, value);
+}
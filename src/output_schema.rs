@@ -0,0 +1,100 @@
+//! Shapes events take on the Unix socket and webhook sinks, and on WebSocket clients that ask for one
+
+use std::fmt;
+use std::str::FromStr;
+use serde_json::{json, Map, Value};
+
+/// Message shape written by a webhook or Unix socket sink, or chosen by a WebSocket client
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OutputSchema {
+    /// Events exactly as broadcast, with nested `token` and `pump_data` objects
+    #[default]
+    Envelope,
+    /// One level of fields, nested names joined with `_`, e.g. `pump_data_price_sol`
+    Flat,
+    /// Account events shaped like a Geyser plugin account update, from their `raw_account`
+    Geyser,
+}
+
+impl OutputSchema {
+    /// Converts a broadcast message to this schema; messages that are not JSON objects pass through
+    pub fn render(self, message: String) -> String {
+        match self {
+            OutputSchema::Envelope => message,
+            OutputSchema::Flat => match serde_json::from_str::<Value>(&message) {
+                Ok(Value::Object(object)) => {
+                    let mut flat = Map::new();
+                    flatten_into(&mut flat, "", object);
+                    Value::Object(flat).to_string()
+                }
+                _ => message,
+            },
+            OutputSchema::Geyser => match serde_json::from_str::<Value>(&message) {
+                Ok(event) if event["raw_account"].is_object() => geyser_account_update(&event).to_string(),
+                _ => message,
+            },
+        }
+    }
+
+    /// Whether this schema needs account events to carry their `raw_account`
+    pub fn needs_raw_accounts(self) -> bool {
+        self == OutputSchema::Geyser
+    }
+}
+
+/// `{"slot", "is_startup", "account": {...}}`, as Geyser account update streams send it
+///
+/// Startup updates are the accounts loaded by `STARTUP_SNAPSHOT=emit`. Account
+/// notifications name no transaction, so `txn_signature` is always null.
+fn geyser_account_update(event: &Value) -> Value {
+    let raw = &event["raw_account"];
+    json!({
+        "slot": event["slot"],
+        "is_startup": event["event_type"] == "snapshot",
+        "account": {
+            "pubkey": raw["pubkey"],
+            "lamports": raw["lamports"],
+            "owner": raw["owner"],
+            "executable": raw["executable"],
+            "rent_epoch": raw["rent_epoch"],
+            "data": raw["data"],
+            "write_version": raw["write_version"],
+            "txn_signature": null,
+        },
+    })
+}
+
+fn flatten_into(flat: &mut Map<String, Value>, prefix: &str, object: Map<String, Value>) {
+    for (key, value) in object {
+        let key = if prefix.is_empty() { key } else { format!("{}_{}", prefix, key) };
+        match value {
+            Value::Object(nested) => flatten_into(flat, &key, nested),
+            value => {
+                flat.insert(key, value);
+            }
+        }
+    }
+}
+
+impl FromStr for OutputSchema {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "envelope" => Ok(OutputSchema::Envelope),
+            "flat" => Ok(OutputSchema::Flat),
+            "geyser" => Ok(OutputSchema::Geyser),
+            other => Err(format!("unknown output schema '{}', expected 'envelope', 'flat' or 'geyser'", other)),
+        }
+    }
+}
+
+impl fmt::Display for OutputSchema {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            OutputSchema::Envelope => "envelope",
+            OutputSchema::Flat => "flat",
+            OutputSchema::Geyser => "geyser",
+        })
+    }
+}
@@ -0,0 +1,56 @@
+//! Ring buffer of recent RPC messages the parser rejected, for `/admin/parse-failures`
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+
+// Longer messages are truncated so a full buffer stays small
+const MAX_SAMPLE_BYTES: usize = 16 * 1024;
+
+struct Sample {
+    received_at: DateTime<Utc>,
+    reason: String,
+    raw: String,
+    raw_bytes: usize,
+}
+
+// Most recent failures, oldest first
+static SAMPLES: Mutex<VecDeque<Sample>> = Mutex::new(VecDeque::new());
+
+/// Keeps `raw` and why it failed to parse, dropping the oldest sample beyond `capacity`
+pub fn record(raw: &str, reason: String, capacity: usize) {
+    let mut end = raw.len().min(MAX_SAMPLE_BYTES);
+    while !raw.is_char_boundary(end) {
+        end -= 1;
+    }
+    let mut samples = SAMPLES.lock().unwrap();
+    while samples.len() >= capacity {
+        samples.pop_front();
+    }
+    samples.push_back(Sample {
+        received_at: Utc::now(),
+        reason,
+        raw: raw[..end].to_string(),
+        raw_bytes: raw.len(),
+    });
+}
+
+/// Samples as JSON, newest first
+pub fn list() -> Vec<Value> {
+    SAMPLES.lock().unwrap().iter().rev().map(|sample| json!({
+        "received_at": sample.received_at.to_rfc3339(),
+        "reason": sample.reason,
+        "raw": sample.raw,
+        "raw_bytes": sample.raw_bytes,
+        "truncated": sample.raw.len() < sample.raw_bytes,
+    })).collect()
+}
+
+/// Empties the buffer and returns how many samples it held
+pub fn clear() -> usize {
+    let mut samples = SAMPLES.lock().unwrap();
+    let cleared = samples.len();
+    samples.clear();
+    cleared
+}
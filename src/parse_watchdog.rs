@@ -0,0 +1,100 @@
+//! Watches the share of RPC notifications the parser rejects, so a provider format
+//! change that breaks parsing shows up in readiness instead of as a quiet stream
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use log::{error, info};
+use crate::metrics;
+
+/// When the parser is considered broken and what to do about it
+#[derive(Debug, Clone, Copy)]
+pub struct WatchdogLimits {
+    /// Share of notifications failing to parse, from 0 to 1, at which the watchdog trips
+    pub failure_ratio: f64,
+    pub window: Duration,
+    /// Notifications needed in the window before the ratio is trusted
+    pub min_messages: u64,
+    /// Reconnect and resubscribe to the RPC node when the watchdog trips
+    pub restart: bool,
+}
+
+/// Notification counts for one second of the window
+struct Bucket {
+    second: u64,
+    parsed: u64,
+    failed: u64,
+}
+
+struct Window {
+    started: Instant,
+    buckets: VecDeque<Bucket>,
+    parsed: u64,
+    failed: u64,
+}
+
+static WINDOW: Mutex<Option<Window>> = Mutex::new(None);
+static TRIPPED: AtomicBool = AtomicBool::new(false);
+static RESTART_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Counts a notification that parsed or failed to, tripping or clearing the watchdog
+pub fn record(parsed: bool, limits: &WatchdogLimits) {
+    let mut window = WINDOW.lock().unwrap();
+    let window = window.get_or_insert_with(|| Window {
+        started: Instant::now(),
+        buckets: VecDeque::new(),
+        parsed: 0,
+        failed: 0,
+    });
+    let second = window.started.elapsed().as_secs();
+    while window.buckets.front().is_some_and(|bucket| bucket.second + limits.window.as_secs() <= second) {
+        let expired = window.buckets.pop_front().expect("checked above");
+        window.parsed -= expired.parsed;
+        window.failed -= expired.failed;
+    }
+    if window.buckets.back().is_none_or(|bucket| bucket.second != second) {
+        window.buckets.push_back(Bucket { second, parsed: 0, failed: 0 });
+    }
+    let bucket = window.buckets.back_mut().expect("pushed above");
+    if parsed {
+        bucket.parsed += 1;
+        window.parsed += 1;
+    } else {
+        bucket.failed += 1;
+        window.failed += 1;
+    }
+
+    let total = window.parsed + window.failed;
+    if total < limits.min_messages {
+        return;
+    }
+    let ratio = window.failed as f64 / total as f64;
+    let failing = ratio >= limits.failure_ratio;
+    if failing == TRIPPED.swap(failing, Ordering::SeqCst) {
+        return;
+    }
+    if !failing {
+        info!("Parse failure rate back to {:.1}% over the last {}s, parser watchdog cleared", ratio * 100.0, limits.window.as_secs());
+        return;
+    }
+    metrics::record_parse_watchdog_trip();
+    error!("PARSER FAILING: {} of the last {} RPC notifications ({:.1}%) could not be parsed; the provider's format may have changed. Readiness is false until parsing recovers",
+        window.failed, total, ratio * 100.0);
+    if limits.restart {
+        error!("Parser watchdog is restarting the RPC subscription");
+        RESTART_REQUESTED.store(true, Ordering::SeqCst);
+        // The new subscription is judged on its own notifications
+        *window = Window { started: Instant::now(), buckets: VecDeque::new(), parsed: 0, failed: 0 };
+    }
+}
+
+/// Whether the watchdog has tripped and not yet seen parsing recover
+pub fn is_tripped() -> bool {
+    TRIPPED.load(Ordering::SeqCst)
+}
+
+/// Returns true once per trip that asked for the RPC subscription to be restarted
+pub fn take_restart_request() -> bool {
+    RESTART_REQUESTED.swap(false, Ordering::SeqCst)
+}
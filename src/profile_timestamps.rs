@@ -0,0 +1,48 @@
+//! Optional monotonic nanosecond timestamps on events, for measuring how long the
+//! parse -> enrich -> broadcast path takes independently of wall-clock skew
+
+use std::cell::Cell;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+// Zero point of every profiling timestamp, fixed on first use
+static EPOCH: OnceLock<Instant> = OnceLock::new();
+
+thread_local! {
+    // Receipt time of the RPC message this thread is processing, if any
+    static RECEIVED_NS: Cell<Option<u64>> = const { Cell::new(None) };
+}
+
+/// Nanoseconds on the process's monotonic clock
+pub fn now_ns() -> u64 {
+    EPOCH.get_or_init(Instant::now).elapsed().as_nanos() as u64
+}
+
+/// Marks the RPC message the current thread is about to process, until dropped
+pub struct Receipt;
+
+impl Drop for Receipt {
+    fn drop(&mut self) {
+        RECEIVED_NS.with(|received| received.set(None));
+    }
+}
+
+/// Records the receipt time of an RPC message, stamped on every event broadcast
+/// while the returned guard lives
+pub fn receive() -> Receipt {
+    RECEIVED_NS.with(|received| received.set(Some(now_ns())));
+    Receipt
+}
+
+/// Adds `received_ns` and `emitted_ns` to a serialized event
+///
+/// Events not broadcast while handling an RPC message, such as launches released
+/// by the rate limit or backfilled trades, carry only `emitted_ns`.
+pub fn stamp(json: String) -> String {
+    let Ok(mut event) = serde_json::from_str::<serde_json::Value>(&json) else { return json };
+    if let Some(received) = RECEIVED_NS.with(Cell::get) {
+        event["received_ns"] = received.into();
+    }
+    event["emitted_ns"] = now_ns().into();
+    event.to_string()
+}
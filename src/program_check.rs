@@ -0,0 +1,31 @@
+//! Optional startup check that the pump.fun program exists on the configured cluster
+
+use std::time::Duration;
+use hyper::Uri;
+use log::info;
+use serde_json::json;
+use crate::event_parser::PUMP_FUN_PROGRAM_ID;
+use crate::rpc_http;
+
+// The check runs before anything else starts, so an unreachable RPC must not hang startup
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Looks up the program account over HTTP RPC and fails unless it exists and is executable
+///
+/// Catches a wrong cluster or RPC URL at startup instead of silently receiving no events.
+pub async fn verify_program(rpc_http: &Uri) -> Result<(), String> {
+    let params = json!([PUMP_FUN_PROGRAM_ID, {"encoding": "base64"}]);
+    let result = rpc_http::call(rpc_http, "getAccountInfo", params, REQUEST_TIMEOUT).await?;
+
+    let account = &result["value"];
+    if account.is_null() {
+        return Err(format!("program {} does not exist on this cluster; check the RPC URLs", PUMP_FUN_PROGRAM_ID));
+    }
+    let owner = account["owner"].as_str().unwrap_or("unknown");
+    let executable = account["executable"].as_bool().unwrap_or(false);
+    if !executable {
+        return Err(format!("account {} exists but is not an executable program (owner {})", PUMP_FUN_PROGRAM_ID, owner));
+    }
+    info!("Verified program {}: owner {}, executable {}", PUMP_FUN_PROGRAM_ID, owner, executable);
+    Ok(())
+}
@@ -0,0 +1,96 @@
+//! Per-client field projection set with the `project` command
+
+use serde_json::{Map, Value};
+
+/// Every field path an event can carry, across all event types
+pub const FIELDS: &[&str] = &[
+    "event_type",
+    "event_id",
+    "timestamp",
+    "transaction_signature",
+    "slot",
+    "cluster",
+    "seq",
+    "signature",
+    "trace_id",
+    "span_id",
+    "mint_address",
+    "trader",
+    "sol_amount",
+    "token_amount",
+    "virtual_sol_reserves",
+    "virtual_token_reserves",
+    "block_time",
+    "ms_since_creation",
+    "creator_launches",
+    "window_secs",
+    "token",
+    "token.mint_address",
+    "token.name",
+    "token.symbol",
+    "token.creator",
+    "token.supply",
+    "token.decimals",
+    "token.uri",
+    "pump_data",
+    "pump_data.bonding_curve",
+    "pump_data.virtual_sol_reserves",
+    "pump_data.virtual_token_reserves",
+    "pump_data.real_sol_reserves",
+    "pump_data.real_token_reserves",
+    "pump_data.complete",
+    "pump_data.price_sol",
+];
+
+/// Requested fields, each with the JSON pointers to try in order
+#[derive(Debug, Clone)]
+pub struct Projection {
+    fields: Vec<(String, Vec<String>)>,
+}
+
+impl Projection {
+    /// Validates the requested paths against `FIELDS`
+    ///
+    /// A bare name that is not a top-level field, such as `price_sol`, selects the
+    /// nested field of that name. `mint_address` also falls back to `token.mint_address`,
+    /// since only some event types carry it at the top level.
+    pub fn new(fields: &[String]) -> Result<Self, String> {
+        let mut resolved = Vec::with_capacity(fields.len());
+        for field in fields {
+            let mut candidates: Vec<&str> = FIELDS.iter().copied().filter(|known| known == field).collect();
+            if !field.contains('.') {
+                let nested = FIELDS.iter().copied().filter(|known| known.split_once('.').is_some_and(|(_, name)| name == field));
+                candidates.extend(nested);
+            }
+            if candidates.is_empty() {
+                return Err(format!("unknown field '{}'", field));
+            }
+            let pointers = candidates.iter().map(|path| format!("/{}", path.replace('.', "/"))).collect();
+            resolved.push((field.clone(), pointers));
+        }
+        Ok(Projection { fields: resolved })
+    }
+
+    /// Reduces an event to a flat object of the requested fields, numbering it with `seq`
+    ///
+    /// Fields the event does not have are left out. Messages without an `event_type`,
+    /// such as `stream_stalled`, are returned unchanged.
+    pub fn apply(&self, message: String, seq: &mut u64) -> String {
+        let Ok(event) = serde_json::from_str::<Value>(&message) else { return message };
+        if event.get("event_type").is_none() {
+            return message;
+        }
+        *seq += 1;
+        let mut projected = Map::new();
+        for (field, pointers) in &self.fields {
+            let value = match field.as_str() {
+                "seq" => event.get("seq").cloned().or(Some((*seq).into())),
+                _ => pointers.iter().find_map(|pointer| event.pointer(pointer)).cloned(),
+            };
+            if let Some(value) = value {
+                projected.insert(field.clone(), value);
+            }
+        }
+        Value::Object(projected).to_string()
+    }
+}
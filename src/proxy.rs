@@ -0,0 +1,139 @@
+//! Outbound proxy support for the Solana RPC connection
+//!
+//! HTTP proxies are used through a `CONNECT` tunnel and SOCKS proxies through
+//! SOCKS5. Either way the WebSocket (and TLS for `wss://`) runs end-to-end
+//! over the tunnel, so the proxy never sees the RPC traffic in clear text.
+
+use std::fmt;
+use std::io;
+use base64::Engine;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+// Upper bound on the proxy's CONNECT response headers
+const MAX_CONNECT_RESPONSE_BYTES: usize = 8192;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProxyKind {
+    Http,
+    Socks5,
+}
+
+/// A proxy for the outbound RPC connection, parsed from `HTTPS_PROXY` or `SOCKS_PROXY`
+#[derive(Debug, Clone)]
+pub struct Proxy {
+    kind: ProxyKind,
+    host: String,
+    port: u16,
+    credentials: Option<(String, String)>,
+}
+
+impl Proxy {
+    /// Parses `http://[user:pass@]host:port` (HTTP) or `socks5://[user:pass@]host:port` (SOCKS)
+    pub fn parse(kind: ProxyKind, url: &str) -> Result<Self, String> {
+        let (scheme, rest) = url.split_once("://").unwrap_or(("", url));
+        let default_port = match (kind, scheme) {
+            (ProxyKind::Http, "http" | "") => 80,
+            (ProxyKind::Socks5, "socks5" | "socks5h" | "") => 1080,
+            _ => return Err(format!("unsupported proxy scheme '{}'", scheme)),
+        };
+
+        let authority = rest.trim_end_matches('/');
+        let (credentials, host_port) = match authority.rsplit_once('@') {
+            Some((userinfo, host_port)) => {
+                let (user, pass) = userinfo.split_once(':').unwrap_or((userinfo, ""));
+                (Some((decode(user)?, decode(pass)?)), host_port)
+            }
+            None => (None, authority),
+        };
+
+        let (host, port) = match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, port.parse().map_err(|_| format!("invalid proxy port '{}'", port))?),
+            None => (host_port, default_port),
+        };
+        if host.is_empty() || host.contains('/') {
+            return Err(format!("invalid proxy host '{}'", host));
+        }
+
+        Ok(Proxy {
+            kind,
+            host: host.to_string(),
+            port,
+            credentials,
+        })
+    }
+
+    /// Opens a TCP tunnel to `target_host:target_port` through the proxy
+    pub async fn connect(&self, target_host: &str, target_port: u16) -> io::Result<TcpStream> {
+        match self.kind {
+            ProxyKind::Http => self.connect_http(target_host, target_port).await,
+            ProxyKind::Socks5 => self.connect_socks5(target_host, target_port).await,
+        }
+    }
+
+    async fn connect_http(&self, target_host: &str, target_port: u16) -> io::Result<TcpStream> {
+        let mut stream = TcpStream::connect((self.host.as_str(), self.port)).await?;
+
+        let target = format!("{}:{}", target_host, target_port);
+        let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", target, target);
+        if let Some((user, pass)) = &self.credentials {
+            let token = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, pass));
+            request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).await?;
+
+        // Read the response headers byte-wise so nothing past them is consumed from the tunnel
+        let mut reader = BufReader::with_capacity(1, &mut stream);
+        let mut status_line = String::new();
+        reader.read_line(&mut status_line).await?;
+        let mut read = status_line.len();
+        loop {
+            let mut line = String::new();
+            let n = reader.read_line(&mut line).await?;
+            read += n;
+            if n == 0 || read > MAX_CONNECT_RESPONSE_BYTES {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "malformed proxy CONNECT response"));
+            }
+            if line == "\r\n" || line == "\n" {
+                break;
+            }
+        }
+
+        match status_line.split_whitespace().nth(1) {
+            Some(status) if status.starts_with('2') => Ok(stream),
+            _ => Err(io::Error::other(format!("proxy refused CONNECT: {}", status_line.trim()))),
+        }
+    }
+
+    async fn connect_socks5(&self, target_host: &str, target_port: u16) -> io::Result<TcpStream> {
+        let proxy = (self.host.as_str(), self.port);
+        let target = (target_host, target_port);
+        let stream = match &self.credentials {
+            Some((user, pass)) => tokio_socks::tcp::Socks5Stream::connect_with_password(proxy, target, user, pass).await,
+            None => tokio_socks::tcp::Socks5Stream::connect(proxy, target).await,
+        };
+        stream
+            .map(|stream| stream.into_inner())
+            .map_err(|e| io::Error::other(format!("SOCKS5 proxy error: {}", e)))
+    }
+}
+
+/// Shows the proxy without its credentials, for logging
+impl fmt::Display for Proxy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let scheme = match self.kind {
+            ProxyKind::Http => "http",
+            ProxyKind::Socks5 => "socks5",
+        };
+        write!(f, "{}://{}:{}", scheme, self.host, self.port)
+    }
+}
+
+/// Percent-decodes a user or password from the proxy URL
+fn decode(component: &str) -> Result<String, String> {
+    percent_encoding::percent_decode_str(component)
+        .decode_utf8()
+        .map(|decoded| decoded.into_owned())
+        .map_err(|_| "proxy credentials must be valid UTF-8".to_string())
+}
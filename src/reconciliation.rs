@@ -0,0 +1,82 @@
+//! Optional check that events sent at `processed` commitment made it into a confirmed block
+
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::sync::Mutex;
+use std::time::Duration;
+use hyper::Uri;
+use log::{debug, info, warn};
+use serde_json::{json, Value};
+use crate::event_channels::EventChannels;
+use crate::metrics;
+use crate::rpc_http;
+
+// How often confirmed slots are checked, and how long each RPC call may take
+const CHECK_INTERVAL: Duration = Duration::from_secs(2);
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Slots waiting for confirmation; the oldest are forgotten beyond this, e.g. while the HTTP RPC is down
+const MAX_PENDING_SLOTS: usize = 10_000;
+
+// Mints with events sent at each slot not yet checked against the confirmed chain
+static PENDING: Mutex<BTreeMap<u64, BTreeSet<String>>> = Mutex::new(BTreeMap::new());
+
+/// Remembers that an event for `mint` was sent from `slot`
+pub fn track(slot: u64, mint: &str) {
+    let mut pending = PENDING.lock().unwrap();
+    pending.entry(slot).or_default().insert(mint.to_string());
+    while pending.len() > MAX_PENDING_SLOTS {
+        pending.pop_first();
+    }
+}
+
+/// Number of slots waiting for confirmation
+pub fn pending_slots() -> usize {
+    PENDING.lock().unwrap().len()
+}
+
+/// Checks tracked slots against the confirmed chain, sending `event_reverted` for each mint at a slot that was skipped
+///
+/// Runs until the task is aborted.
+pub async fn run(rpc_http: Uri, sender: EventChannels) {
+    let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = check(&rpc_http, &sender).await {
+            warn!("Reconciliation check failed, retrying: {}", e);
+        }
+    }
+}
+
+async fn check(rpc_http: &Uri, sender: &EventChannels) -> Result<(), String> {
+    let Some(first) = PENDING.lock().unwrap().keys().next().copied() else {
+        return Ok(());
+    };
+    let confirmed = rpc_http::call(rpc_http, "getSlot", json!([{"commitment": "confirmed"}]), REQUEST_TIMEOUT).await?
+        .as_u64()
+        .ok_or("getSlot returned no slot")?;
+    if confirmed < first {
+        return Ok(());
+    }
+    let blocks = rpc_http::call(rpc_http, "getBlocks", json!([first, confirmed, {"commitment": "confirmed"}]), REQUEST_TIMEOUT).await?;
+    let blocks: HashSet<u64> = blocks.as_array()
+        .ok_or("getBlocks returned no block list")?
+        .iter()
+        .filter_map(Value::as_u64)
+        .collect();
+
+    // Everything up to the confirmed slot is settled either way
+    let settled = {
+        let mut pending = PENDING.lock().unwrap();
+        let unsettled = pending.split_off(&(confirmed + 1));
+        std::mem::replace(&mut *pending, unsettled)
+    };
+    debug!("Reconciled {} slots up to confirmed slot {}", settled.len(), confirmed);
+    for (slot, mints) in settled.into_iter().filter(|(slot, _)| !blocks.contains(slot)) {
+        info!("Slot {} was not confirmed; reverting events for {} mint(s)", slot, mints.len());
+        for mint in mints {
+            metrics::record_event_reverted();
+            let _ = sender.send(json!({"type": "event_reverted", "mint": mint, "slot": slot}).to_string());
+        }
+    }
+    Ok(())
+}
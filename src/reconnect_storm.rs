@@ -0,0 +1,76 @@
+//! Detects an RPC connection that keeps dropping and slows reconnects down while it does
+
+use std::collections::VecDeque;
+use std::time::Duration;
+use log::warn;
+use serde_json::json;
+use tokio::time::Instant;
+use crate::event_channels::EventChannels;
+use crate::metrics;
+
+/// Thresholds for treating reconnects as a storm
+#[derive(Debug, Clone, Copy)]
+pub struct StormLimits {
+    /// Reconnects within `window` that make a storm
+    pub reconnects: usize,
+    pub window: Duration,
+    /// Delay before each reconnect while the storm lasts
+    pub cooldown: Duration,
+    /// A connection that stays up this long ends the storm
+    pub stable_after: Duration,
+}
+
+/// Sliding window of recent reconnects
+pub struct StormDetector {
+    limits: StormLimits,
+    reconnects: VecDeque<Instant>,
+    in_storm: bool,
+}
+
+impl StormDetector {
+    pub fn new(limits: StormLimits) -> Self {
+        StormDetector {
+            limits,
+            reconnects: VecDeque::with_capacity(limits.reconnects),
+            in_storm: false,
+        }
+    }
+
+    /// Returns true from the start of a storm until a stable connection ends it
+    pub fn in_storm(&self) -> bool {
+        self.in_storm
+    }
+
+    /// Records how long the connection that just ended was up, ending the storm if it was stable
+    pub fn connection_ended(&mut self, uptime: Duration) {
+        if uptime >= self.limits.stable_after {
+            self.reconnects.clear();
+            self.in_storm = false;
+        }
+    }
+
+    /// Records a reconnect and returns the cool-down to wait instead of the usual delay during a storm
+    ///
+    /// Clients are told once, with an `upstream_unstable` message, when a storm starts.
+    pub fn record_reconnect(&mut self, sender: &EventChannels) -> Option<Duration> {
+        let now = Instant::now();
+        while self.reconnects.front().is_some_and(|at| now.duration_since(*at) > self.limits.window) {
+            self.reconnects.pop_front();
+        }
+        self.reconnects.push_back(now);
+
+        if !self.in_storm && self.reconnects.len() >= self.limits.reconnects {
+            self.in_storm = true;
+            metrics::record_reconnect_storm();
+            warn!("{} reconnects within {:?}, waiting {:?} between reconnects until the connection is stable",
+                self.reconnects.len(), self.limits.window, self.limits.cooldown);
+            let unstable = json!({
+                "type": "upstream_unstable",
+                "reconnects": self.reconnects.len(),
+                "window_secs": self.limits.window.as_secs(),
+            });
+            let _ = sender.send(unstable.to_string());
+        }
+        self.in_storm.then_some(self.limits.cooldown)
+    }
+}
@@ -0,0 +1,67 @@
+use std::collections::VecDeque;
+use chrono::{DateTime, Duration, Utc};
+use crate::metrics;
+
+/// Bounded buffer of the most recently broadcast events
+///
+/// Newly connected clients are sent the buffered events after the welcome
+/// message so they can catch up on activity they missed while disconnected.
+/// Events are evicted once the buffer is full or, with a TTL, once they are
+/// older than the TTL, whichever comes first.
+pub struct ReplayBuffer {
+    capacity: usize,
+    ttl: Option<Duration>,
+    events: VecDeque<(DateTime<Utc>, String)>,
+}
+
+impl ReplayBuffer {
+    pub fn new(capacity: usize, ttl: Option<Duration>) -> Self {
+        ReplayBuffer {
+            capacity,
+            ttl,
+            events: VecDeque::with_capacity(capacity),
+        }
+    }
+
+    /// Stores an event, evicting expired events and then the oldest one once the buffer is full
+    pub fn push(&mut self, timestamp: DateTime<Utc>, event: String) {
+        if self.capacity == 0 {
+            return;
+        }
+        self.evict_expired();
+        if self.events.len() == self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back((timestamp, event));
+        self.report();
+    }
+
+    /// Drops events older than the TTL, if one is set
+    pub fn evict_expired(&mut self) {
+        let Some(ttl) = self.ttl else { return };
+        let cutoff = Utc::now() - ttl;
+        let before = self.events.len();
+        // Events are stored in broadcast order, which is close enough to timestamp order
+        while self.events.front().is_some_and(|(timestamp, _)| *timestamp < cutoff) {
+            self.events.pop_front();
+        }
+        if self.events.len() != before {
+            self.report();
+        }
+    }
+
+    /// Returns the buffered events in broadcast order, skipping any older than `max_age`
+    pub fn snapshot(&mut self, max_age: Option<Duration>) -> Vec<String> {
+        self.evict_expired();
+        let cutoff = max_age.map(|age| Utc::now() - age);
+        self.events
+            .iter()
+            .filter(|(timestamp, _)| cutoff.is_none_or(|cutoff| *timestamp >= cutoff))
+            .map(|(_, event)| event.clone())
+            .collect()
+    }
+
+    fn report(&self) {
+        metrics::set_replay_buffer(self.events.len(), self.events.front().map(|(timestamp, _)| *timestamp));
+    }
+}
@@ -0,0 +1,182 @@
+//! Hitless binary upgrades: on `SIGUSR2` the process starts its (possibly upgraded)
+//! binary with the listening sockets inherited, stops accepting and drains
+//!
+//! Descriptor inheritance and `SIGUSR2` are Unix-only; elsewhere `GRACEFUL_RESTART`
+//! is ignored with a warning and listeners are always bound fresh.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::sync::Notify;
+use crate::ws_server;
+
+/// Environment variable carrying the WebSocket listener's descriptor to the new process
+pub const WS_LISTENER: &str = "APEING_WS_LISTENER_FD";
+/// Environment variable carrying the HTTP listener's descriptor to the new process
+pub const HTTP_LISTENER: &str = "APEING_HTTP_LISTENER_FD";
+
+static HANDED_OFF: AtomicBool = AtomicBool::new(false);
+static HANDOFF: Notify = Notify::const_new();
+
+/// Takes over a listener passed by the previous process, if it is bound to `port`
+///
+/// Anything else in the variable, such as a descriptor that is not a socket, is
+/// ignored so the caller binds a fresh listener.
+pub fn inherited_listener(var: &str, port: u16) -> Option<std::net::TcpListener> {
+    #[cfg(unix)]
+    return unix::inherited_listener(var, port);
+
+    #[cfg(not(unix))]
+    {
+        let _ = (var, port);
+        None
+    }
+}
+
+/// Records a listener to hand to the next process on restart
+#[cfg(unix)]
+pub fn register_listener(var: &'static str, listener: &impl std::os::unix::io::AsRawFd) {
+    unix::register_listener(var, listener.as_raw_fd());
+}
+
+#[cfg(not(unix))]
+pub fn register_listener<T>(_var: &'static str, _listener: &T) {}
+
+/// Resolves once listeners have been handed to a new process; accept loops stop then
+pub async fn handed_off() {
+    loop {
+        let notified = HANDOFF.notified();
+        if HANDED_OFF.load(Ordering::SeqCst) {
+            return;
+        }
+        notified.await;
+    }
+}
+
+/// Waits for `SIGUSR2`, hands the listeners to a new process and drains
+///
+/// Returns once every client has disconnected or `drain_timeout` elapses, at which
+/// point this process should exit. Never returns where restarts are unsupported.
+pub async fn run(drain_timeout: Duration) {
+    #[cfg(unix)]
+    unix::wait_for_successor().await;
+
+    #[cfg(not(unix))]
+    {
+        log::warn!("GRACEFUL_RESTART is set but re-exec with inherited sockets is not supported on this platform");
+        std::future::pending::<()>().await;
+    }
+
+    HANDED_OFF.store(true, Ordering::SeqCst);
+    HANDOFF.notify_waiters();
+
+    ws_server::start_draining(drain_timeout);
+    let deadline = tokio::time::Instant::now() + drain_timeout;
+    while ws_server::get_active_connections() > 0 && tokio::time::Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::env;
+    use std::io;
+    use std::os::unix::io::{FromRawFd, RawFd};
+    use std::os::unix::process::CommandExt;
+    use std::process::{Child, Command};
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use log::{error, info, warn};
+    use tokio::signal::unix::{signal, SignalKind};
+
+    // How long the new process must stay up before this one stops accepting
+    const STARTUP_GRACE: Duration = Duration::from_secs(3);
+
+    // Listening sockets to pass on, by environment variable
+    static LISTENERS: Mutex<Vec<(&'static str, RawFd)>> = Mutex::new(Vec::new());
+
+    pub fn inherited_listener(var: &str, port: u16) -> Option<std::net::TcpListener> {
+        let fd: RawFd = env::var(var).ok()?.parse().ok()?;
+        // Only take ownership of descriptors that are open, so dropping never closes a stranger's
+        if fd < 3 || unsafe { libc::fcntl(fd, libc::F_GETFD) } == -1 {
+            return None;
+        }
+        let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+        match listener.local_addr() {
+            Ok(addr) if addr.port() == port && listener.set_nonblocking(true).is_ok() => {
+                info!("Inherited listener on {} from the previous process", addr);
+                Some(listener)
+            }
+            _ => {
+                warn!("Ignoring {}={}: not a listener on port {}", var, fd, port);
+                None
+            }
+        }
+    }
+
+    pub fn register_listener(var: &'static str, fd: RawFd) {
+        LISTENERS.lock().unwrap().push((var, fd));
+    }
+
+    /// Starts the current binary again with the registered listeners inherited
+    ///
+    /// The new process gets the same arguments and environment, plus one variable per
+    /// listener naming its descriptor.
+    fn spawn_successor() -> io::Result<Child> {
+        let listeners = LISTENERS.lock().unwrap().clone();
+        let mut command = Command::new(env::current_exe()?);
+        command.args(env::args_os().skip(1));
+        for (var, fd) in &listeners {
+            command.env(var, fd.to_string());
+        }
+        let fds: Vec<RawFd> = listeners.iter().map(|(_, fd)| *fd).collect();
+        // Runs in the child between fork and exec: keep the listeners open across exec
+        unsafe {
+            command.pre_exec(move || {
+                for &fd in &fds {
+                    let flags = libc::fcntl(fd, libc::F_GETFD);
+                    if flags == -1 || libc::fcntl(fd, libc::F_SETFD, flags & !libc::FD_CLOEXEC) == -1 {
+                        return Err(io::Error::last_os_error());
+                    }
+                }
+                Ok(())
+            });
+        }
+        command.spawn()
+    }
+
+    /// Returns once a new process has been started on `SIGUSR2` and stayed up
+    ///
+    /// Both processes accept for `STARTUP_GRACE` first; if the new one exits in that
+    /// time, this process keeps serving and waits for the next signal.
+    pub async fn wait_for_successor() {
+        let mut signals = match signal(SignalKind::user_defined2()) {
+            Ok(signals) => signals,
+            Err(e) => {
+                error!("Unable to listen for SIGUSR2, graceful restart disabled: {}", e);
+                return std::future::pending().await;
+            }
+        };
+        info!("Graceful restart enabled: send SIGUSR2 to start a new process on the same ports");
+
+        loop {
+            signals.recv().await;
+            let mut child = match spawn_successor() {
+                Ok(child) => child,
+                Err(e) => {
+                    error!("Graceful restart failed, still serving: {}", e);
+                    continue;
+                }
+            };
+            info!("Started new process {}", child.id());
+            tokio::time::sleep(STARTUP_GRACE).await;
+            match child.try_wait() {
+                Ok(None) => {
+                    info!("New process {} is up, handing over listeners", child.id());
+                    return;
+                }
+                Ok(Some(status)) => error!("New process exited during startup ({}), still serving", status),
+                Err(e) => error!("Unable to check on new process, still serving: {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,48 @@
+//! JSON-RPC calls over the Solana HTTP endpoint, for one-off requests outside the subscription
+
+use std::time::Duration;
+use hyper::{Body, Client, Method, Request, Uri};
+use serde_json::{json, Value};
+
+/// Sends one JSON-RPC request and returns its `result`
+///
+/// Fails on a timeout, a transport or HTTP error, invalid JSON or an RPC `error`.
+pub async fn call(rpc_http: &Uri, method: &str, params: Value, timeout: Duration) -> Result<Value, String> {
+    let https = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let client: Client<_> = Client::builder().build(https);
+
+    let body = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params,
+    });
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri(rpc_http.clone())
+        .header(hyper::header::CONTENT_TYPE, "application/json")
+        .body(Body::from(body.to_string()))
+        .expect("JSON-RPC request is well-formed");
+
+    let response = tokio::time::timeout(timeout, async {
+        let response = client.request(request).await
+            .map_err(|e| format!("could not reach {}: {}", rpc_http, e))?;
+        if !response.status().is_success() {
+            return Err(format!("{} answered {} with HTTP {}", rpc_http, method, response.status()));
+        }
+        hyper::body::to_bytes(response.into_body()).await
+            .map_err(|e| format!("failed to read the {} response: {}", method, e))
+    }).await
+        .map_err(|_| format!("{} did not answer {} within {:?}", rpc_http, method, timeout))??;
+    let mut reply: Value = serde_json::from_slice(&response)
+        .map_err(|e| format!("{} returned invalid JSON: {}", method, e))?;
+
+    if let Some(error) = reply.get("error") {
+        return Err(format!("{} failed: {}", method, error));
+    }
+    Ok(reply["result"].take())
+}
@@ -0,0 +1,62 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use log::warn;
+
+/// Bloom filter of every mint address seen since startup
+///
+/// Remembers mints after the state index has evicted them, in a fixed amount of
+/// memory. A mint it has never seen is always reported as new; a small fraction
+/// of new mints (the false-positive rate) are reported as already seen.
+pub struct SeenMints {
+    bits: Vec<u64>,
+    hashes: u32,
+    capacity: usize,
+    inserted: usize,
+}
+
+impl SeenMints {
+    /// Sizes the filter for `capacity` mints at the given false-positive rate
+    pub fn new(capacity: usize, false_positive_rate: f64) -> Self {
+        let ln2 = std::f64::consts::LN_2;
+        let bit_count = (-(capacity as f64) * false_positive_rate.ln() / (ln2 * ln2)).ceil().max(64.0) as usize;
+        let hashes = ((bit_count as f64 / capacity as f64) * ln2).round().clamp(1.0, 32.0) as u32;
+        SeenMints {
+            bits: vec![0; bit_count.div_ceil(64)],
+            hashes,
+            capacity,
+            inserted: 0,
+        }
+    }
+
+    /// Approximate memory used by the filter in bytes
+    pub fn size_bytes(&self) -> usize {
+        self.bits.len() * 8
+    }
+
+    /// Adds a mint, returning true if it was probably seen before
+    pub fn insert(&mut self, mint: &str) -> bool {
+        let (h1, h2) = (hash(0, mint), hash(1, mint));
+        let bit_count = self.bits.len() as u64 * 64;
+        let mut seen = true;
+        for i in 0..self.hashes as u64 {
+            let bit = h1.wrapping_add(i.wrapping_mul(h2)) % bit_count;
+            let (word, mask) = ((bit / 64) as usize, 1u64 << (bit % 64));
+            seen &= self.bits[word] & mask != 0;
+            self.bits[word] |= mask;
+        }
+        if !seen {
+            self.inserted += 1;
+            if self.inserted == self.capacity + 1 {
+                warn!("Seen-mints filter is past its capacity of {}; false positives will rise", self.capacity);
+            }
+        }
+        seen
+    }
+}
+
+fn hash(seed: u64, mint: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    seed.hash(&mut hasher);
+    mint.hash(&mut hasher);
+    hasher.finish()
+}
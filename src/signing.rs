@@ -0,0 +1,108 @@
+//! Optional Ed25519 signatures on emitted events
+//!
+//! The signature covers the event's RFC 8785 (JCS) canonical form, taken
+//! without its `signature` field. Clients verify by removing `signature`,
+//! canonicalizing the rest with any JCS implementation and checking it against
+//! the public key published at `/version`.
+
+use base64::Engine;
+use ed25519_dalek::{Signer, SigningKey};
+use serde_json::Value;
+
+/// Parses a 32-byte Ed25519 secret key given as hex or base64
+pub fn parse_signing_key(encoded: &str) -> Result<SigningKey, String> {
+    let encoded = encoded.trim();
+    let bytes = if encoded.len() == 64 && encoded.bytes().all(|b| b.is_ascii_hexdigit()) {
+        (0..64).step_by(2).map(|i| u8::from_str_radix(&encoded[i..i + 2], 16).unwrap()).collect()
+    } else {
+        base64::engine::general_purpose::STANDARD.decode(encoded).map_err(|_| "key must be hex or base64".to_string())?
+    };
+    let secret: [u8; 32] = bytes.try_into().map_err(|_| "key must be exactly 32 bytes".to_string())?;
+    Ok(SigningKey::from_bytes(&secret))
+}
+
+/// Base64 of the verifying key, as published at `/version`
+pub fn public_key(key: &SigningKey) -> String {
+    base64::engine::general_purpose::STANDARD.encode(key.verifying_key().as_bytes())
+}
+
+/// Adds a base64 `signature` field computed over the event's canonical form
+///
+/// Events that are not JSON objects are returned unchanged.
+pub fn sign_event(json: String, key: &SigningKey) -> String {
+    let Ok(Value::Object(mut event)) = serde_json::from_str::<Value>(&json) else {
+        return json;
+    };
+    event.remove("signature");
+
+    let mut canonical = String::new();
+    write_canonical(&mut canonical, &Value::Object(event.clone()));
+    let signature = key.sign(canonical.as_bytes());
+    event.insert("signature".to_string(), base64::engine::general_purpose::STANDARD.encode(signature.to_bytes()).into());
+    Value::Object(event).to_string()
+}
+
+/// Writes `value` in RFC 8785 (JCS) canonical form
+///
+/// Keys are sorted by UTF-16 code units and numbers use ECMAScript formatting,
+/// so any conforming JCS implementation produces the same bytes.
+fn write_canonical(out: &mut String, value: &Value) {
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by(|(a, _), (b, _)| a.encode_utf16().cmp(b.encode_utf16()));
+            out.push('{');
+            for (i, (key, value)) in entries.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&Value::from(key.as_str()).to_string());
+                out.push(':');
+                write_canonical(out, value);
+            }
+            out.push('}');
+        }
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(out, item);
+            }
+            out.push(']');
+        }
+        Value::Number(number) => match number.as_f64() {
+            Some(float) if number.is_f64() => out.push_str(&ecmascript_number(float)),
+            _ => out.push_str(&number.to_string()),
+        },
+        // serde_json escapes strings exactly as JCS requires
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// Formats a finite double the way ECMAScript's `Number.prototype.toString` does
+fn ecmascript_number(value: f64) -> String {
+    if value == 0.0 {
+        return "0".to_string();
+    }
+    // `{:e}` yields the shortest round-tripping digits, e.g. `-2.7958993476234855e-8`
+    let scientific = format!("{:e}", value.abs());
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let digits: String = mantissa.chars().filter(|c| *c != '.').collect();
+    let k = digits.len() as i32;
+    let n = exponent.parse::<i32>().unwrap() + 1;
+
+    let formatted = if k <= n && n <= 21 {
+        format!("{}{}", digits, "0".repeat((n - k) as usize))
+    } else if 0 < n && n <= 21 {
+        format!("{}.{}", &digits[..n as usize], &digits[n as usize..])
+    } else if -6 < n && n <= 0 {
+        format!("0.{}{}", "0".repeat((-n) as usize), digits)
+    } else {
+        let sign = if n - 1 < 0 { '-' } else { '+' };
+        let fraction = if k > 1 { format!(".{}", &digits[1..]) } else { String::new() };
+        format!("{}{}e{}{}", &digits[..1], fraction, sign, (n - 1).abs())
+    };
+    if value < 0.0 { format!("-{}", formatted) } else { formatted }
+}
@@ -0,0 +1,76 @@
+use serde_json::{json, Value};
+use tokio::time::{Duration, Instant};
+use crate::client_options::BatchSort;
+use crate::event_parser;
+
+/// Buffers one client's events until their slot is complete
+///
+/// A slot is considered complete when an event for a later slot arrives or
+/// the timeout elapses since its first event. `programNotification` carries no
+/// write version, so events within a batch keep the order the RPC node sent them
+/// unless the client asked for a `BatchSort`.
+pub struct SlotBatcher {
+    timeout: Duration,
+    sort: BatchSort,
+    slot: u64,
+    events: Vec<Value>,
+    deadline: Option<Instant>,
+}
+
+impl SlotBatcher {
+    pub fn new(timeout: Duration, sort: BatchSort) -> Self {
+        SlotBatcher {
+            timeout,
+            sort,
+            slot: 0,
+            events: Vec::new(),
+            deadline: None,
+        }
+    }
+
+    /// Adds an event, returning the previous slot's batch if this event starts a later slot
+    ///
+    /// Events for an older slot than the one being buffered are added to the
+    /// current batch rather than reopening a slot that was already emitted.
+    pub fn push(&mut self, slot: u64, event: Value) -> Option<Value> {
+        let completed = if !self.events.is_empty() && slot > self.slot {
+            self.flush()
+        } else {
+            None
+        };
+        if self.events.is_empty() {
+            self.slot = slot;
+            self.deadline = Some(Instant::now() + self.timeout);
+        }
+        self.events.push(event);
+        completed
+    }
+
+    /// Emits the buffered slot as a batch, if any
+    pub fn flush(&mut self) -> Option<Value> {
+        self.deadline = None;
+        if self.events.is_empty() {
+            return None;
+        }
+        // Stable sorts, so ties keep their delivery order
+        match self.sort {
+            BatchSort::Insertion => {}
+            BatchSort::MarketCapDesc => self.events.sort_by(|a, b| {
+                let (a, b) = (event_parser::event_market_cap_sol(a), event_parser::event_market_cap_sol(b));
+                b.unwrap_or(f64::NEG_INFINITY).total_cmp(&a.unwrap_or(f64::NEG_INFINITY))
+            }),
+            BatchSort::SlotAsc => self.events.sort_by_key(|event| event["slot"].as_u64().unwrap_or(u64::MAX)),
+        }
+        Some(json!({
+            "type": "slot_batch",
+            "slot": self.slot,
+            "slot_complete": true,
+            "events": std::mem::take(&mut self.events),
+        }))
+    }
+
+    /// When the buffered slot should be emitted even if no later slot has been seen
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+}
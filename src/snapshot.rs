@@ -0,0 +1,80 @@
+//! Optional one-time load of every existing bonding curve before the live subscription starts
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use hyper::Uri;
+use log::{info, warn};
+use serde_json::json;
+use crate::config::Config;
+use crate::event_channels::EventChannels;
+use crate::event_parser::{self, ProgramEvent, BONDING_CURVE_DISCRIMINATOR, PUMP_FUN_PROGRAM_ID};
+use crate::metrics;
+use crate::replay_buffer::ReplayBuffer;
+use crate::rpc_http;
+use crate::solana_client;
+use crate::state_index::StateIndex;
+
+// getProgramAccounts returns every curve at once, which can take a while on mainnet
+const SNAPSHOT_TIMEOUT: Duration = Duration::from_secs(120);
+
+// Set once a snapshot has been attempted, so a restarted listener doesn't repeat it
+static SNAPSHOT_ATTEMPTED: AtomicBool = AtomicBool::new(false);
+
+/// Returns true the first time it is called, when the snapshot should be taken
+pub fn take_once() -> bool {
+    !SNAPSHOT_ATTEMPTED.swap(true, Ordering::SeqCst)
+}
+
+/// Fetches every bonding curve account, adds each to the state index and broadcasts it as a `snapshot` event
+///
+/// Only bonding curves are requested, using a filter on their account
+/// discriminator. Returns the number of curves loaded.
+pub async fn load(
+    rpc_http: &Uri,
+    config: &Config,
+    sender: &EventChannels,
+    replay: &Mutex<ReplayBuffer>,
+    state: &Mutex<StateIndex>,
+) -> Result<usize, String> {
+    let params = json!([
+        PUMP_FUN_PROGRAM_ID,
+        {
+            "encoding": "base64",
+            "withContext": true,
+            "filters": [{"memcmp": {"offset": 0, "bytes": bs58::encode(BONDING_CURVE_DISCRIMINATOR).into_string()}}]
+        }
+    ]);
+    let result = rpc_http::call(rpc_http, "getProgramAccounts", params, SNAPSHOT_TIMEOUT).await?;
+    let slot = result["context"]["slot"].as_u64()
+        .ok_or("getProgramAccounts result has no context slot")?;
+    let accounts = result["value"].as_array()
+        .ok_or("getProgramAccounts result has no account list")?;
+    info!("Loaded {} bonding curve accounts at slot {}", accounts.len(), slot);
+
+    let mut loaded = 0;
+    for entry in accounts {
+        let Some(pubkey) = entry["pubkey"].as_str() else { continue };
+        let Some(ProgramEvent::Token(mut event)) = event_parser::parse_program_account(pubkey, &entry["account"], slot) else {
+            continue;
+        };
+        if config.validate_pubkeys && !event_parser::is_valid_pubkey(&event.token.mint_address) {
+            metrics::record_invalid_pubkey();
+            warn!("Skipping snapshot account with malformed pubkey '{:.64}'", pubkey);
+            continue;
+        }
+        if let Err(reason) = event_parser::check_sanity(&event, &config.sanity_limits) {
+            warn!("Skipping snapshot account {} ({})", pubkey, reason);
+            continue;
+        }
+        if !state.lock().unwrap().insert_snapshot(&event) {
+            continue;
+        }
+        event.event_type = "snapshot".to_string();
+        if let Ok(json) = event.to_json(config.output_format()) {
+            solana_client::broadcast(json, event.timestamp, config, sender, replay);
+        }
+        loaded += 1;
+    }
+    Ok(loaded)
+}
@@ -1,51 +1,303 @@
-use tokio_tungstenite::connect_async;
+use tokio::net::TcpStream;
+use tokio_tungstenite::{client_async_tls, connect_async, MaybeTlsStream, WebSocketStream};
 use futures::{SinkExt, StreamExt};
-use tokio::sync::broadcast::Sender;
 use serde_json::json;
-use log::{info, error};
-use crate::event_parser;
+use log::{info, warn, error, debug};
+use tracing::info_span;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use chrono::{DateTime, Utc};
+use crate::backfill;
+use crate::config::Config;
+use crate::creation_throttle;
+use crate::delivery_hours;
+use crate::event_channels::EventChannels;
+use crate::event_parser::{self, CreateEvent, ProgramEvent};
+use crate::launch_metadata;
+use crate::metrics;
+use crate::parse_failures;
+use crate::parse_watchdog;
+use crate::profile_timestamps;
+use crate::reconciliation;
+use crate::reconnect_storm::StormDetector;
+use crate::replay_buffer::ReplayBuffer;
+use crate::signing;
+use crate::snapshot;
+use crate::state_index::StateIndex;
+use crate::telemetry;
+
+/// Solana cluster the service is connected to, stamped on every event
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cluster {
+    Mainnet,
+    Devnet,
+    Testnet,
+    /// A private or unrecognized RPC node
+    Custom,
+}
+
+impl Cluster {
+    /// Guesses the cluster from well-known RPC hostnames, falling back to `Custom`
+    pub fn detect(rpc_url: &str) -> Self {
+        let host = rpc_url.parse::<tungstenite::http::Uri>().ok()
+            .and_then(|uri| uri.host().map(str::to_ascii_lowercase))
+            .unwrap_or_default();
+        if host.contains("devnet") {
+            Cluster::Devnet
+        } else if host.contains("testnet") {
+            Cluster::Testnet
+        } else if host.contains("mainnet") {
+            Cluster::Mainnet
+        } else {
+            Cluster::Custom
+        }
+    }
+}
+
+impl std::str::FromStr for Cluster {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mainnet" => Ok(Cluster::Mainnet),
+            "devnet" => Ok(Cluster::Devnet),
+            "testnet" => Ok(Cluster::Testnet),
+            "custom" => Ok(Cluster::Custom),
+            other => Err(format!("unknown cluster '{}', expected mainnet, devnet, testnet or custom", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for Cluster {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Cluster::Mainnet => "mainnet",
+            Cluster::Devnet => "devnet",
+            Cluster::Testnet => "testnet",
+            Cluster::Custom => "custom",
+        })
+    }
+}
+
+// Consecutive handshakes refused like a plain HTTP endpoint before the listener gives up
+const NON_WEBSOCKET_FAILURE_LIMIT: u32 = 3;
+
+// Whether the RPC subscription is currently live
+static UPSTREAM_CONNECTED: AtomicBool = AtomicBool::new(false);
+
+// Set after UPSTREAM_DOWN_AFTER_FAILURES consecutive failed connects, until a subscription succeeds
+static ALL_UPSTREAMS_DOWN: AtomicBool = AtomicBool::new(false);
+
+// Per-endpoint health for `/stats`: the endpoint's host, its last successful
+// subscription as Unix milliseconds (0 before the first), and failed attempts
+static UPSTREAM_ENDPOINT: OnceLock<String> = OnceLock::new();
+static UPSTREAM_LAST_SUCCESS_MS: AtomicU64 = AtomicU64::new(0);
+static UPSTREAM_FAILURES: AtomicU64 = AtomicU64::new(0);
+
+// Highest slot seen in a program notification
+static LATEST_SLOT: AtomicU64 = AtomicU64::new(0);
+
+// Whether the RPC node has acknowledged the program subscription on the current connection
+static SUBSCRIPTION_CONFIRMED: AtomicBool = AtomicBool::new(false);
+
+// Unix time in milliseconds of the last subscription notification, 0 before the first
+static LAST_NOTIFICATION_MS: AtomicU64 = AtomicU64::new(0);
+
+// Longest gap between notifications for the subscription to still count as healthy
+static HEALTHY_NOTIFICATION_GAP_SECS: AtomicU64 = AtomicU64::new(DEFAULT_HEALTHY_NOTIFICATION_GAP_SECS);
+const DEFAULT_HEALTHY_NOTIFICATION_GAP_SECS: u64 = 60;
+
+/// Returns true while subscribed to the RPC node
+pub fn is_upstream_connected() -> bool {
+    UPSTREAM_CONNECTED.load(Ordering::Relaxed)
+}
+
+/// Returns true while every configured RPC endpoint is failing to connect
+pub fn all_upstreams_down() -> bool {
+    ALL_UPSTREAMS_DOWN.load(Ordering::Relaxed)
+}
+
+/// Health of each configured RPC endpoint, for `/stats`
+///
+/// Endpoints are identified by host and port only, since RPC URLs often carry an API key.
+pub fn upstream_stats() -> serde_json::Value {
+    let last_success_ms = UPSTREAM_LAST_SUCCESS_MS.load(Ordering::Relaxed);
+    let last_success = (last_success_ms > 0)
+        .then(|| DateTime::<Utc>::from_timestamp_millis(last_success_ms as i64))
+        .flatten()
+        .map(|time| time.to_rfc3339());
+    json!([{
+        "endpoint": UPSTREAM_ENDPOINT.get(),
+        "connected": is_upstream_connected(),
+        "last_success": last_success,
+        "failures": UPSTREAM_FAILURES.load(Ordering::Relaxed),
+    }])
+}
+
+/// Time since the last subscription notification, or None before the first
+pub fn last_notification_age() -> Option<std::time::Duration> {
+    let last = LAST_NOTIFICATION_MS.load(Ordering::Relaxed);
+    let now = Utc::now().timestamp_millis().max(0) as u64;
+    (last > 0).then(|| std::time::Duration::from_millis(now.saturating_sub(last)))
+}
+
+/// Returns true when the subscription is confirmed and notifications are still arriving
+///
+/// Unlike `is_upstream_connected`, this catches a connection that is up but silent.
+pub fn is_subscription_healthy() -> bool {
+    let max_gap = std::time::Duration::from_secs(HEALTHY_NOTIFICATION_GAP_SECS.load(Ordering::Relaxed));
+    SUBSCRIPTION_CONFIRMED.load(Ordering::Relaxed) && last_notification_age().is_some_and(|age| age <= max_gap)
+}
+
+/// Returns the highest slot seen so far, or 0 before the first notification
+pub fn latest_slot() -> u64 {
+    LATEST_SLOT.load(Ordering::Relaxed)
+}
 
 /// Establishes and maintains a WebSocket connection to Solana RPC
 /// Subscribes to pump.fun contract events and broadcasts them to connected clients
-pub async fn solana_event_listener(sender: Sender<String>, rpc_url: String) {
+///
+/// `state` is owned by the pipeline rather than a single connection, so mints
+/// seen before a reconnect keep their state and are not re-announced as new.
+/// Reconnects for as long as that can help; otherwise returns why it gave up.
+pub async fn solana_event_listener(
+    sender: EventChannels,
+    config: Arc<Config>,
+    replay: Arc<Mutex<ReplayBuffer>>,
+    state: Arc<Mutex<StateIndex>>,
+) -> Result<(), String> {
+    let rpc_url = &config.solana_rpc_ws;
+    let mut subscribed_before = false;
+    let stall_timeout = (config.stream_stall_timeout_secs > 0)
+        .then(|| std::time::Duration::from_secs(config.stream_stall_timeout_secs));
+    if let Some(window) = stall_timeout {
+        HEALTHY_NOTIFICATION_GAP_SECS.store(window.as_secs(), Ordering::Relaxed);
+    }
+
+    // Connection failures back off exponentially; stalls reconnect after a short fixed
+    // delay because the socket itself worked, the provider just stopped sending
+    let initial_backoff = std::time::Duration::from_millis(config.reconnect_initial_backoff_ms);
+    let max_backoff = std::time::Duration::from_secs(config.reconnect_max_backoff_secs);
+    let mut failure_backoff = initial_backoff;
+    let mut storm = config.reconnect_storm.map(StormDetector::new);
+    let mut non_websocket_failures = 0;
+    let mut consecutive_failures = 0;
+    let _ = UPSTREAM_ENDPOINT.set(endpoint_label(rpc_url));
+
+    // Start from every existing curve, once per process, before streaming changes
+    if config.startup_snapshot && snapshot::take_once() {
+        let rpc_http = config.solana_rpc_http.as_ref().expect("checked by Config::from_env");
+        match snapshot::load(rpc_http, &config, &sender, &replay, &state).await {
+            Ok(loaded) => info!("Startup snapshot: sent {} bonding curves", loaded),
+            Err(e) => warn!("Startup snapshot failed, continuing with live updates only: {}", e),
+        }
+    }
+
     loop {
         // Attempt to establish WebSocket connection to Solana RPC
-        match connect_async(&rpc_url).await {
-            Ok((ws_stream, _)) => {
+        match connect(rpc_url, &config).await {
+            Ok(ws_stream) => {
                 info!("Connected to Solana RPC");
+                non_websocket_failures = 0;
                 let (mut write, mut read) = ws_stream.split();
 
                 // Create subscription message for pump.fun program account changes
                 // This subscribes to all account changes for the pump.fun contract
-                let subscription = json!({
+                let mut program_options = json!({"encoding": "jsonParsed"}); // Request parsed JSON data
+                let mut logs_options = json!({});
+                // Reconciliation trades certainty for latency; skipped slots are reported afterwards
+                if config.reconcile_processed {
+                    program_options["commitment"] = json!("processed");
+                    logs_options["commitment"] = json!("processed");
+                }
+                let mut subscriptions = vec![json!({
                     "jsonrpc": "2.0",
                     "id": 1,
                     "method": "programSubscribe",
-                    "params": [
-                        "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P", // pump.fun program ID
-                        {"encoding": "jsonParsed"} // Request parsed JSON data
-                    ]
-                });
-
-                // Send subscription request to Solana RPC
-                if let Err(e) = write.send(tungstenite::Message::Text(subscription.to_string())).await {
+                    "params": [event_parser::PUMP_FUN_PROGRAM_ID, program_options]
+                })];
+                // Transaction logs carry the decoded buy/sell events with exact amounts
+                if config.emit_instruction_events {
+                    subscriptions.push(json!({
+                        "jsonrpc": "2.0",
+                        "id": 2,
+                        "method": "logsSubscribe",
+                        "params": [{"mentions": [event_parser::PUMP_FUN_PROGRAM_ID]}, logs_options]
+                    }));
+                }
+
+                // Send subscription requests to Solana RPC
+                let mut subscribe_error = None;
+                for subscription in subscriptions {
+                    if let Err(e) = write.send(tungstenite::Message::Text(subscription.to_string())).await {
+                        subscribe_error = Some(e);
+                        break;
+                    }
+                }
+                if let Some(e) = subscribe_error {
                     error!("Subscription error: {:?}", e);
-                    continue; // Retry connection on subscription failure
+                    record_upstream_failure(&mut consecutive_failures, &config, &sender);
+                    // Retry connection on subscription failure
+                    if !wait_out_storm(&mut storm, &sender, true).await {
+                        back_off_after_failure(&mut failure_backoff, max_backoff).await;
+                    }
+                    continue;
                 }
 
                 info!("Subscribed to Pump.fun contract.");
+                UPSTREAM_CONNECTED.store(true, Ordering::Relaxed);
+                record_upstream_success(&mut consecutive_failures, &sender);
+                failure_backoff = initial_backoff;
+
+                // Suppress the burst of already-seen states the RPC may replay after a resubscribe
+                if subscribed_before && config.resubscribe_dedup_ms > 0 {
+                    state.lock().unwrap().begin_resubscribe_grace(std::time::Duration::from_millis(config.resubscribe_dedup_ms));
+                }
+                // Recover trades missed while disconnected before resuming the live stream
+                if let Some(limits) = config.backfill.as_ref().filter(|_| subscribed_before && latest_slot() > 0) {
+                    let rpc_http = config.solana_rpc_http.as_ref().expect("checked by Config::from_env");
+                    match backfill::run(rpc_http, latest_slot(), limits, &config, &sender, &replay, &state).await {
+                        Ok(sent) => info!("Backfill sent {} missed trades", sent),
+                        Err(e) => warn!("Backfill failed, resuming live events with a gap: {}", e),
+                    }
+                }
+                subscribed_before = true;
+                let connected_at = tokio::time::Instant::now();
 
-                // Process incoming messages from Solana RPC
-                while let Some(msg) = read.next().await {
+                // Process incoming messages from Solana RPC. Only notifications count as
+                // activity for the stall watchdog; pings keep a silent stream looking alive.
+                let mut last_notification = tokio::time::Instant::now();
+                let mut stalled = false;
+                loop {
+                    let next = match stall_timeout {
+                        Some(window) => match tokio::time::timeout_at(last_notification + window, read.next()).await {
+                            Ok(next) => next,
+                            Err(_) => {
+                                // Clients already know about a storm; don't add a notice per reconnect
+                                let notify = !storm.as_ref().is_some_and(StormDetector::in_storm);
+                                report_stall(window, &sender, notify);
+                                stalled = true;
+                                break; // Tear down the silent connection and resubscribe
+                            }
+                        },
+                        None => read.next().await,
+                    };
+                    let Some(msg) = next else { break };
+                    if let Ok(message) = &msg {
+                        metrics::record_rpc_message(message.len());
+                    }
                     match msg {
                         Ok(tungstenite::Message::Text(txt)) => {
-                            // Try to parse the raw message into structured format
-                            if let Some(parsed_event) = event_parser::parse_event(&txt) {
-                                // Send the structured event to clients
-                                let _ = sender.send(parsed_event);
-                            } else {
-                                // If parsing fails, send the raw message for debugging
-                                let _ = sender.send(txt);
+                            let _receipt = config.profile_timestamps.then(profile_timestamps::receive);
+                            if is_notification(&txt) {
+                                last_notification = tokio::time::Instant::now();
+                                LAST_NOTIFICATION_MS.store(Utc::now().timestamp_millis().max(0) as u64, Ordering::Relaxed);
+                            } else if is_subscription_confirmation(&txt) {
+                                SUBSCRIPTION_CONFIRMED.store(true, Ordering::Relaxed);
+                            }
+                            handle_notification(txt, &config, &state, &sender, &replay);
+                            if parse_watchdog::take_restart_request() {
+                                break; // Resubscribe in case the provider changed what it sends on this subscription
                             }
                         }
                         Ok(_) => {
@@ -58,13 +310,590 @@ pub async fn solana_event_listener(sender: Sender<String>, rpc_url: String) {
                     }
                 }
 
-                error!("Disconnected. Reconnecting...");
+                UPSTREAM_CONNECTED.store(false, Ordering::Relaxed);
+                SUBSCRIPTION_CONFIRMED.store(false, Ordering::Relaxed);
+                if let Some(storm) = storm.as_mut() {
+                    storm.connection_ended(connected_at.elapsed());
+                }
+                if wait_out_storm(&mut storm, &sender, !stalled).await {
+                    continue;
+                }
+                if stalled {
+                    tokio::time::sleep(std::time::Duration::from_millis(config.stall_reconnect_delay_ms)).await;
+                } else {
+                    error!("Disconnected. Reconnecting...");
+                    back_off_after_failure(&mut failure_backoff, max_backoff).await;
+                }
             }
             Err(e) => {
                 error!("Failed to connect: {:?}", e);
-                // Wait 5 seconds before attempting to reconnect
-                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                record_upstream_failure(&mut consecutive_failures, &config, &sender);
+                if let Some(reply) = non_websocket_reply(&e) {
+                    non_websocket_failures += 1;
+                    error!("{} answered the WebSocket handshake with {}; SOLANA_RPC_WS looks like an HTTP RPC URL rather than the provider's WebSocket endpoint",
+                        rpc_url, reply);
+                    if non_websocket_failures >= NON_WEBSOCKET_FAILURE_LIMIT {
+                        return Err(format!("Giving up after {} handshakes refused the same way; set SOLANA_RPC_WS to the WebSocket endpoint (usually ws:// or wss://, sometimes on a different port or path)",
+                            non_websocket_failures));
+                    }
+                } else {
+                    non_websocket_failures = 0;
+                }
+                if !wait_out_storm(&mut storm, &sender, true).await {
+                    back_off_after_failure(&mut failure_backoff, max_backoff).await;
+                }
             }
         }
     }
-}
\ No newline at end of file
+}
+
+/// Counts a failed connect or subscribe, announcing an outage once every endpoint has failed enough times in a row
+fn record_upstream_failure(consecutive_failures: &mut u32, config: &Config, sender: &EventChannels) {
+    UPSTREAM_FAILURES.fetch_add(1, Ordering::Relaxed);
+    *consecutive_failures += 1;
+    let Some(limit) = config.upstream_down_after_failures else {
+        return;
+    };
+    if *consecutive_failures >= limit && !ALL_UPSTREAMS_DOWN.swap(true, Ordering::Relaxed) {
+        error!("All upstreams down: {} consecutive connection failures; readiness is false and retries continue with backoff",
+            consecutive_failures);
+        metrics::record_upstream_outage();
+        let _ = sender.send(json!({"type": "all_upstreams_down", "failures": *consecutive_failures}).to_string());
+    }
+}
+
+/// Records a successful subscription, ending an outage if one was announced
+fn record_upstream_success(consecutive_failures: &mut u32, sender: &EventChannels) {
+    *consecutive_failures = 0;
+    UPSTREAM_LAST_SUCCESS_MS.store(Utc::now().timestamp_millis().max(0) as u64, Ordering::Relaxed);
+    if ALL_UPSTREAMS_DOWN.swap(false, Ordering::Relaxed) {
+        info!("Upstream recovered; leaving the all upstreams down state");
+        let _ = sender.send(json!({"type": "upstream_recovered"}).to_string());
+    }
+}
+
+/// Host and port of an RPC URL, without the path or query that may hold an API key
+fn endpoint_label(rpc_url: &str) -> String {
+    let Ok(uri) = rpc_url.parse::<tungstenite::http::Uri>() else {
+        return "invalid".to_string();
+    };
+    match (uri.host(), uri.port_u16()) {
+        (Some(host), Some(port)) => format!("{}:{}", host, port),
+        (Some(host), None) => host.to_string(),
+        (None, _) => "invalid".to_string(),
+    }
+}
+
+/// Records a reconnect and, during a reconnect storm, waits out the cool-down
+///
+/// A `failure` is still counted as a connection failure when the storm delay
+/// replaces the backoff. Returns false when there is no storm and the usual delay applies.
+async fn wait_out_storm(storm: &mut Option<StormDetector>, sender: &EventChannels, failure: bool) -> bool {
+    let Some(cooldown) = storm.as_mut().and_then(|storm| storm.record_reconnect(sender)) else {
+        return false;
+    };
+    if failure {
+        metrics::record_rpc_connection_failure();
+    }
+    warn!("Reconnect storm: reconnecting in {:?}", cooldown);
+    tokio::time::sleep(cooldown).await;
+    true
+}
+
+/// Counts a connection failure and waits out the current backoff, doubling it for next time
+async fn back_off_after_failure(backoff: &mut std::time::Duration, max_backoff: std::time::Duration) {
+    let failures = metrics::record_rpc_connection_failure();
+    warn!("Reconnecting in {:?} (connection failure #{})", backoff, failures);
+    tokio::time::sleep(*backoff).await;
+    *backoff = (*backoff * 2).min(max_backoff);
+}
+
+/// Describes the reply if the handshake failed the way a plain HTTP endpoint refuses an upgrade
+///
+/// Auth, rate-limit and server errors are left to the normal reconnect path.
+fn non_websocket_reply(error: &tungstenite::Error) -> Option<String> {
+    use tungstenite::http::StatusCode;
+    match error {
+        tungstenite::Error::Http(response) => matches!(response.status(),
+            StatusCode::OK | StatusCode::BAD_REQUEST | StatusCode::NOT_FOUND
+            | StatusCode::METHOD_NOT_ALLOWED | StatusCode::UPGRADE_REQUIRED)
+            .then(|| format!("HTTP {}", response.status())),
+        // WebSocket needs HTTP/1.1, so an HTTP/1.0 answer cannot come from a WebSocket endpoint
+        tungstenite::Error::Protocol(tungstenite::error::ProtocolError::WrongHttpVersion) => Some("an HTTP/1.0 response".to_string()),
+        _ => None,
+    }
+}
+
+/// Returns true for subscription notifications, as opposed to RPC responses
+fn is_notification(txt: &str) -> bool {
+    #[derive(serde::Deserialize)]
+    struct Method<'a> {
+        #[serde(borrow)]
+        method: Option<&'a str>,
+    }
+    serde_json::from_str::<Method>(txt).is_ok_and(|m| m.method.is_some_and(|method| method.ends_with("Notification")))
+}
+
+/// Returns true for the RPC node's successful reply to the `programSubscribe` request
+fn is_subscription_confirmation(txt: &str) -> bool {
+    #[derive(serde::Deserialize)]
+    struct Reply {
+        id: Option<u64>,
+        result: Option<serde::de::IgnoredAny>,
+    }
+    serde_json::from_str::<Reply>(txt).is_ok_and(|reply| reply.id == Some(1) && reply.result.is_some())
+}
+
+/// Logs a stalled subscription and, if `notify`, tells clients before the connection is torn down
+fn report_stall(window: std::time::Duration, sender: &EventChannels, notify: bool) {
+    let stalls = metrics::record_stream_stall();
+    warn!("No notifications for {:?}, reconnecting (stall #{})", window, stalls);
+    if !notify {
+        return;
+    }
+    let stalled = json!({
+        "type": "stream_stalled",
+        "silent_secs": window.as_secs(),
+        "slot": latest_slot(),
+    });
+    let _ = sender.send(stalled.to_string());
+}
+
+/// Opens the RPC WebSocket, tunnelling through the configured proxy if there is one
+async fn connect(rpc_url: &str, config: &Config) -> Result<WebSocketStream<MaybeTlsStream<TcpStream>>, tungstenite::Error> {
+    let Some(proxy) = &config.rpc_proxy else {
+        return connect_async(rpc_url).await.map(|(ws_stream, _)| ws_stream);
+    };
+
+    let uri: tungstenite::http::Uri = rpc_url.parse()?;
+    let host = uri.host().ok_or(tungstenite::Error::Url(tungstenite::error::UrlError::NoHostName))?;
+    let port = uri.port_u16().unwrap_or(if uri.scheme_str() == Some("wss") { 443 } else { 80 });
+    let tunnel = proxy.connect(host, port).await?;
+    client_async_tls(rpc_url, tunnel).await.map(|(ws_stream, _)| ws_stream)
+}
+
+/// Keeps a launch's metadata for its curve updates
+pub fn record_launch(create: CreateEvent, state: &Mutex<StateIndex>) {
+    // A launch whose first curve update came first is tracked under the curve's address
+    if state.lock().unwrap().adopt_launch(&create) {
+        debug!("Moved {} from its bonding curve {} to its mint address", create.symbol, create.bonding_curve);
+    }
+    launch_metadata::remember(create);
+}
+
+/// Counts an event dropped for an address clients could not look up on chain
+fn record_invalid_pubkey(address: &str) {
+    let invalid = metrics::record_invalid_pubkey();
+    if invalid % 100 == 1 {
+        warn!("Dropping event with malformed pubkey '{:.64}'", address);
+    }
+}
+
+/// Returns whether every address is a valid pubkey, recording the first one that isn't
+fn has_valid_pubkeys<const N: usize>(addresses: [&String; N]) -> bool {
+    match addresses.into_iter().find(|address| !event_parser::is_valid_pubkey(address)) {
+        Some(address) => {
+            record_invalid_pubkey(address);
+            false
+        }
+        None => true,
+    }
+}
+
+/// Counts an event dropped by the sanity checks
+fn record_anomaly(mint: &str, reason: &str, txt: &str) {
+    let anomalies = metrics::record_decode_anomaly();
+    // Log the first anomaly and then every 100th to keep the log readable
+    if anomalies % 100 == 1 {
+        warn!("Dropping anomalous event for {} ({}), sample: {:.512}", mint, reason, txt);
+    }
+}
+
+/// Runs a single RPC notification through the parse -> enrich -> broadcast pipeline
+fn handle_notification(txt: String, config: &Config, state: &Mutex<StateIndex>, sender: &EventChannels, replay: &Mutex<ReplayBuffer>) {
+    let span = info_span!("process_notification", bytes = txt.len());
+    let _entered = span.enter();
+
+    // Try to parse the raw message into structured format
+    let Some(mut program_event) = info_span!("parse").in_scope(|| event_parser::parse_event(&txt)) else {
+        // If parsing fails, send the raw message for debugging
+        if config.parse_failure_samples.is_some() || config.parse_watchdog.is_some() {
+            if let Some(reason) = event_parser::parse_failure_reason(&txt) {
+                if let Some(limits) = &config.parse_watchdog {
+                    parse_watchdog::record(false, limits);
+                }
+                if let Some(capacity) = config.parse_failure_samples {
+                    parse_failures::record(&txt, reason, capacity);
+                }
+            }
+        }
+        let _ = sender.send(txt);
+        return;
+    };
+    if let Some(limits) = &config.parse_watchdog {
+        parse_watchdog::record(true, limits);
+    }
+
+    // Curve updates don't store the mint or its metadata; take them from the launch's CreateEvent
+    if let ProgramEvent::Token(event) = &mut program_event {
+        launch_metadata::apply(event);
+    }
+
+    // Drop accounts whose address clients could not look up on chain
+    if config.validate_pubkeys {
+        let address = match &program_event {
+            ProgramEvent::Token(event) => Some(&event.token.mint_address),
+            ProgramEvent::GlobalUpdate(update) => Some(&update.account),
+            ProgramEvent::OtherAccount { .. } | ProgramEvent::Instructions { .. } => None,
+        };
+        if let Some(address) = address.filter(|address| !event_parser::is_valid_pubkey(address)) {
+            record_invalid_pubkey(address);
+            return;
+        }
+    }
+
+    let mut parsed_event = match program_event {
+        ProgramEvent::Token(event) => {
+            LATEST_SLOT.fetch_max(event.slot, Ordering::Relaxed);
+            event
+        }
+        ProgramEvent::GlobalUpdate(update) => {
+            LATEST_SLOT.fetch_max(update.slot, Ordering::Relaxed);
+            if config.emit_global_updates {
+                if let Ok(json) = update.to_json(config.output_format()) {
+                    broadcast(json, update.timestamp, config, sender, replay);
+                }
+            }
+            return;
+        }
+        ProgramEvent::OtherAccount { pubkey } => {
+            debug!("Ignoring update to non-curve program account {}", pubkey);
+            return;
+        }
+        ProgramEvent::Instructions { creates, trades } => {
+            for create in creates {
+                if let Err(reason) = event_parser::check_create_sanity(&create) {
+                    record_anomaly(&create.mint, &reason, &txt);
+                    continue;
+                }
+                if config.validate_pubkeys && !has_valid_pubkeys([&create.mint, &create.bonding_curve, &create.creator]) {
+                    continue;
+                }
+                record_launch(create, state);
+            }
+            for event in trades {
+                if let Err(reason) = event_parser::check_trade_sanity(&event, &config.sanity_limits) {
+                    record_anomaly(&event.mint_address, &reason, &txt);
+                    continue;
+                }
+                if config.validate_pubkeys && !has_valid_pubkeys([&event.mint_address, &event.trader]) {
+                    continue;
+                }
+                if config.backfill.is_some() && backfill::already_sent(&event.transaction_signature) {
+                    continue;
+                }
+                LATEST_SLOT.fetch_max(event.slot, Ordering::Relaxed);
+                if config.volume_leaders.is_some() {
+                    state.lock().unwrap().record_trade(&event.mint_address, event.sol_amount);
+                }
+                if config.wash_trade_detection.is_some() {
+                    let is_buy = event.event_type == "buy";
+                    let confidence = state.lock().unwrap().observe_trade(&event.mint_address, &event.trader, is_buy, event.sol_amount);
+                    if let Some(confidence) = confidence {
+                        metrics::record_wash_trade_flag();
+                        let json = json!({
+                            "type": "suspicious_activity",
+                            "mint": event.mint_address,
+                            "pattern": "wash_trade",
+                            "cluster": config.cluster.to_string(),
+                            "confidence": confidence,
+                            "slot": event.slot,
+                        });
+                        broadcast(json.to_string(), event.timestamp, config, sender, replay);
+                    }
+                }
+                if config.reconcile_processed {
+                    reconciliation::track(event.slot, &event.mint_address);
+                }
+                if let Ok(json) = event.to_json(config.output_format()) {
+                    broadcast(json, event.timestamp, config, sender, replay);
+                }
+            }
+            return;
+        }
+    };
+
+    // Drop values no real bonding curve can have rather than passing garbage to clients
+    if let Err(reason) = event_parser::check_sanity(&parsed_event, &config.sanity_limits) {
+        record_anomaly(&parsed_event.token.mint_address, &reason, &txt);
+        return;
+    }
+
+    // Only forward new mints and changes to watched fields
+    let observation = info_span!("enrich").in_scope(|| state.lock().unwrap().observe(&mut parsed_event));
+    if config.transitions.is_some() {
+        // A spam creator's launch is flagged or dropped rather than announced, so it is not a creation
+        let created = observation.emit && parsed_event.event_type == "token_created" && observation.creator_launches.is_none();
+        let transitions = state.lock().unwrap().transitions(&parsed_event, created);
+        let market_cap = event_parser::market_cap_sol(parsed_event.pump_data.price_sol, parsed_event.token.supply, parsed_event.token.decimals);
+        for transition in transitions {
+            let message = transition.message(config.cluster, &parsed_event.token.mint_address, market_cap, Some(parsed_event.slot));
+            broadcast(message.to_string(), parsed_event.timestamp, config, sender, replay);
+        }
+    }
+    let first_trade_after = observation.first_trade_after.filter(|_| config.emit_first_trades);
+    if !observation.emit && first_trade_after.is_none() && observation.near_graduation.is_none() {
+        return;
+    }
+    if let Some((trace_id, span_id)) = telemetry::current_trace_context() {
+        parsed_event.trace_id = Some(trace_id);
+        parsed_event.span_id = Some(span_id);
+    }
+
+    // Send the structured event to clients
+    let _broadcast = info_span!("broadcast", event_type = %parsed_event.event_type).entered();
+    if config.reconcile_processed {
+        reconciliation::track(parsed_event.slot, &parsed_event.token.mint_address);
+    }
+    let timestamp = parsed_event.timestamp;
+    if let Some(since_creation) = first_trade_after {
+        if let Ok(json) = parsed_event.to_first_trade_json(config.output_format(), since_creation) {
+            broadcast(json, timestamp, config, sender, replay);
+        }
+    }
+    if let (Some(progress), Some(alert)) = (observation.near_graduation, config.graduation_alert) {
+        metrics::record_near_graduation_alert();
+        let json = json!({
+            "type": "near_graduation",
+            "mint": parsed_event.token.mint_address,
+            "progress": progress,
+            "threshold": alert.threshold,
+            "cluster": config.cluster.to_string(),
+            "slot": parsed_event.slot,
+        });
+        broadcast(json.to_string(), timestamp, config, sender, replay);
+    }
+    if !observation.emit {
+        return;
+    }
+    // Replace a spam creator's launch with a flag, or drop it
+    if let (Some(launches), Some(limit)) = (observation.creator_launches, config.creator_limit) {
+        metrics::record_creator_spam();
+        if !config.suppress_creator_spam {
+            if let Ok(json) = parsed_event.to_creator_spam_json(config.output_format(), launches, limit.window) {
+                broadcast(json, timestamp, config, sender, replay);
+            }
+        }
+        return;
+    }
+    let events = if config.split_token_created && parsed_event.event_type == "token_created" {
+        parsed_event.to_split_json(config.output_format()).map(Vec::from)
+    } else {
+        parsed_event.to_json(config.output_format()).map(|json| vec![json])
+    };
+    let Ok(events) = events else {
+        return;
+    };
+    // Launches over the rate limit wait their turn; everything else goes out straight away
+    if config.max_new_tokens_per_sec.is_some() && parsed_event.event_type == "token_created" {
+        creation_throttle::enqueue(events, timestamp, config.new_token_queue_size);
+        return;
+    }
+    for json in events {
+        broadcast(json, timestamp, config, sender, replay);
+    }
+}
+
+/// Records a serialized event for replay and sends it to connected clients
+pub fn broadcast(json: String, timestamp: DateTime<Utc>, config: &Config, sender: &EventChannels, replay: &Mutex<ReplayBuffer>) {
+    // State is already updated; outside delivery hours the event just isn't sent or replayed
+    if delivery_hours::is_paused() {
+        return;
+    }
+    let json = if config.profile_timestamps { profile_timestamps::stamp(json) } else { json };
+    let json = match &config.signing_key {
+        Some(key) => signing::sign_event(json, key),
+        None => json,
+    };
+    replay.lock().unwrap().push(timestamp, json.clone());
+    metrics::record_event_broadcast();
+    // Fails only when nobody is subscribed right now; the event is still in the replay buffer
+    if sender.send(json).is_err() {
+        metrics::record_event_without_subscribers();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state_index::MintSort;
+
+    fn events_without_subscribers() -> u64 {
+        metrics::stats()["events_without_subscribers"].as_u64().unwrap()
+    }
+
+    #[test]
+    fn broadcasting_with_no_subscribers_counts_the_events() {
+        let config = Config::for_tests();
+        let sender = EventChannels::new(16, 16, None, 1);
+        let replay = Mutex::new(ReplayBuffer::new(8, None));
+        let before = events_without_subscribers();
+        for slot in 0..10_000 {
+            let event = json!({"event_type": "trade", "slot": slot});
+            broadcast(event.to_string(), Utc::now(), &config, &sender, &replay);
+        }
+        assert!(events_without_subscribers() - before >= 10_000);
+    }
+
+    #[test]
+    fn replay_buffer_and_state_fill_with_no_clients_connected() {
+        let config = Config::for_tests();
+        let sender = EventChannels::new(16, 16, None, 1);
+        let replay = Mutex::new(ReplayBuffer::new(8, None));
+        let state = Mutex::new(StateIndex::new(config.trigger_fields.clone(), 0, None, None, None, false, None, None, None));
+        for (slot, reserves) in [(100, 1_000), (101, 2_000), (102, 3_000)] {
+            handle_notification(curve_notification(reserves, slot), &config, &state, &sender, &replay);
+        }
+
+        let events = replay.lock().unwrap().snapshot(None);
+        let slots: Vec<u64> = events.iter()
+            .map(|event| serde_json::from_str::<serde_json::Value>(event).unwrap()["slot"].as_u64().unwrap())
+            .collect();
+        assert_eq!(slots, [100, 101, 102]);
+        let (tracked, _) = state.lock().unwrap().list(MintSort::Recency, 0, 10, config.output_format());
+        assert_eq!(tracked, 1);
+    }
+
+    #[test]
+    fn update_with_a_malformed_pubkey_is_dropped() {
+        let mut config = Config::for_tests();
+        config.validate_pubkeys = true;
+        let sender = EventChannels::new(16, 16, None, 1);
+        let replay = Mutex::new(ReplayBuffer::new(8, None));
+        let state = Mutex::new(StateIndex::new(config.trigger_fields.clone(), 0, None, None, None, false, None, None, None));
+        let invalid_before = metrics::stats()["invalid_pubkeys"].as_u64().unwrap();
+
+        let malformed = curve_notification(1_000, 100).replace(CURVE, "4Hw1dSRnaN5ryEaBUyTXGcNEmqSmLcuVhpgmTTdcL0Il");
+        handle_notification(malformed, &config, &state, &sender, &replay);
+        assert!(replay.lock().unwrap().snapshot(None).is_empty());
+        assert!(metrics::stats()["invalid_pubkeys"].as_u64().unwrap() > invalid_before);
+
+        handle_notification(curve_notification(1_000, 100), &config, &state, &sender, &replay);
+        assert_eq!(replay.lock().unwrap().snapshot(None).len(), 1);
+    }
+
+    const CURVE: &str = "4Hw1dSRnaN5ryEaBUyTXGcNEmqSmLcuVhpgmTTdcLRGM";
+
+    /// A programNotification for a bonding curve holding `real_sol_reserves`
+    fn curve_notification(real_sol_reserves: u64, slot: u64) -> String {
+        let mut data = vec![23, 183, 248, 55, 96, 216, 172, 96];
+        for amount in [1_073_000_000_000_000, 30_000_000_000 + real_sol_reserves, 793_100_000_000_000, real_sol_reserves, 1_000_000_000_000_000u64] {
+            data.extend_from_slice(&amount.to_le_bytes());
+        }
+        data.push(0);
+        json!({
+            "jsonrpc": "2.0",
+            "method": "programNotification",
+            "params": {"result": {"context": {"slot": slot}, "value": {"pubkey": CURVE, "account": {
+                "data": [base64::Engine::encode(&base64::engine::general_purpose::STANDARD, data), "base64"],
+                "owner": event_parser::PUMP_FUN_PROGRAM_ID,
+                "executable": false,
+                "lamports": 1_000_000,
+                "rentEpoch": 0,
+            }}}, "subscription": 42},
+        }).to_string()
+    }
+
+    #[test]
+    fn emitted_event_keeps_the_notification_slot_pubkey_and_ingestion_time() {
+        let config = Config::for_tests();
+        let sender = EventChannels::new(16, 16, None, 1);
+        let replay = Mutex::new(ReplayBuffer::new(8, None));
+        let state = Mutex::new(StateIndex::new(config.trigger_fields.clone(), 0, None, None, None, false, None, None, None));
+
+        let before = Utc::now();
+        handle_notification(curve_notification(1_000, 245_678_901), &config, &state, &sender, &replay);
+        let after = Utc::now();
+
+        let events = replay.lock().unwrap().snapshot(None);
+        let event: serde_json::Value = serde_json::from_str(&events[0]).unwrap();
+        assert_eq!(event["slot"], 245_678_901);
+        assert_eq!(event["pump_data"]["bonding_curve"], CURVE);
+        let timestamp = DateTime::parse_from_rfc3339(event["timestamp"].as_str().unwrap()).unwrap();
+        assert!(before <= timestamp && timestamp <= after, "{} is not the ingestion time", timestamp);
+    }
+
+    /// Serves one RPC connection: waits for the subscription, then sends `notifications`
+    async fn serve_rpc(listener: &tokio::net::TcpListener, notifications: Vec<String>) -> WebSocketStream<TcpStream> {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        ws.next().await.unwrap().unwrap();
+        for notification in notifications {
+            ws.send(tungstenite::Message::Text(notification)).await.unwrap();
+        }
+        ws
+    }
+
+    #[tokio::test]
+    async fn state_survives_an_rpc_reconnect() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let mut config = Config::for_tests();
+        config.solana_rpc_ws = format!("ws://{}", listener.local_addr().unwrap());
+        config.reconnect_initial_backoff_ms = 10;
+        config.resubscribe_dedup_ms = 60_000;
+        let sender = EventChannels::new(16, 16, None, 1);
+        let mut events = sender.subscribe();
+        let state = Arc::new(Mutex::new(StateIndex::new(config.trigger_fields.clone(), 0, None, None, None, false, None, None, None)));
+        let listener_task = tokio::spawn(solana_event_listener(
+            sender.clone(), Arc::new(config), Arc::new(Mutex::new(ReplayBuffer::new(16, None))), state));
+
+        // The first connection announces the mint, then drops; the second replays the
+        // same state, as providers do after a resubscribe, before the curve moves on
+        let first = serve_rpc(&listener, vec![curve_notification(1_000, 100)]).await;
+        drop(first);
+        let _second = serve_rpc(&listener, vec![curve_notification(1_000, 100), curve_notification(2_000, 101)]).await;
+
+        let mut received = Vec::new();
+        while received.len() < 2 {
+            let message = tokio::time::timeout(std::time::Duration::from_secs(10), events.recv()).await
+                .expect("timed out waiting for events").unwrap();
+            let event: serde_json::Value = serde_json::from_str(&message).unwrap();
+            if event.get("event_type").is_some() {
+                received.push(event);
+            }
+        }
+        listener_task.abort();
+
+        assert_eq!(received[0]["event_type"], "token_created");
+        assert_eq!(received[1]["event_type"], "trade");
+        assert_eq!(received[1]["pump_data"]["real_sol_reserves"], 2_000);
+    }
+
+    #[tokio::test]
+    async fn ingestion_continues_after_the_ws_server_exits() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        // Holding the server's port makes its bind fail, so the server task returns
+        let taken = std::net::TcpListener::bind("0.0.0.0:0").unwrap();
+        let mut config = Config::for_tests();
+        config.solana_rpc_ws = format!("ws://{}", listener.local_addr().unwrap());
+        config.server_port = taken.local_addr().unwrap().port();
+        let config = Arc::new(config);
+        let sender = EventChannels::new(16, 16, None, 1);
+        let mut events = sender.subscribe();
+        let replay = Arc::new(Mutex::new(ReplayBuffer::new(16, None)));
+        let state = Arc::new(Mutex::new(StateIndex::new(config.trigger_fields.clone(), 0, None, None, None, false, None, None, None)));
+
+        let server = tokio::spawn(crate::ws_server::start_ws_server(config.clone(), sender.clone(), replay.clone(), state.clone()));
+        tokio::time::timeout(std::time::Duration::from_secs(10), server).await
+            .expect("the WS server kept running").unwrap();
+
+        let listener_task = tokio::spawn(solana_event_listener(sender.clone(), config, replay.clone(), state));
+        let _rpc = serve_rpc(&listener, vec![curve_notification(1_000, 100)]).await;
+        let message = tokio::time::timeout(std::time::Duration::from_secs(10), events.recv()).await
+            .expect("timed out waiting for the event").unwrap();
+        listener_task.abort();
+
+        assert!(message.contains("token_created"));
+        assert_eq!(replay.lock().unwrap().snapshot(None), vec![message]);
+    }
+}
@@ -0,0 +1,876 @@
+use std::collections::hash_map::DefaultHasher;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use crate::event_parser::{self, CreateEvent, PumpData, OutputFormat, TokenDetails, TokenEvent};
+use crate::metrics;
+use crate::seen_mints::SeenMints;
+use crate::transitions::{Phase, Transition};
+
+// Number of recently observed states remembered per mint for replay suppression
+const RECENT_STATE_HISTORY: usize = 16;
+
+// Mints this far along their curve are evicted only after every less advanced mint
+const NEAR_GRADUATION_PROGRESS: f64 = 80.0;
+
+// Share of the mint limit evicted at once, so eviction is not a full scan on every new mint
+const EVICTION_BATCH_DIVISOR: usize = 100;
+
+// Buckets a mint's rolling volume window is divided into
+const VOLUME_BUCKETS: u32 = 30;
+
+// Number of tracked creators at which creators with no launch inside the window are first swept
+const CREATOR_SWEEP_THRESHOLD: usize = 10_000;
+
+// Most recent trades per mint the wash trade heuristic looks at, bounding its cost per trade
+const WASH_TRADE_HISTORY: usize = 64;
+
+// Number of mints with recent trades at which mints with none inside the window are first swept
+const WASH_TRADE_SWEEP_THRESHOLD: usize = 10_000;
+
+/// Most launches a single creator may make within `window` before further ones are spam
+#[derive(Debug, Clone, Copy)]
+pub struct CreatorLimit {
+    pub max_launches: usize,
+    pub window: Duration,
+}
+
+/// Progress at which a mint's `near_graduation` alert fires, re-armed only once
+/// progress falls `hysteresis` percentage points below `threshold`
+#[derive(Debug, Clone, Copy)]
+pub struct GraduationAlert {
+    pub threshold: f64,
+    pub hysteresis: f64,
+}
+
+/// Thresholds for flagging a mint's recent trades as likely wash trading
+///
+/// A mint is flagged once it has `min_trades` trades within `window`, all from at
+/// most `max_wallets` wallets, and at least `min_confidence` of consecutive trades
+/// reverse direction at a size within `size_tolerance` of the trade before.
+#[derive(Debug, Clone, Copy)]
+pub struct WashTradeDetection {
+    pub window: Duration,
+    pub min_trades: usize,
+    pub max_wallets: usize,
+    pub size_tolerance: f64,
+    pub min_confidence: f64,
+}
+
+/// Decoded fields whose change can trigger a new emission for a mint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TriggerField {
+    BondingCurve,
+    VirtualSolReserves,
+    VirtualTokenReserves,
+    RealSolReserves,
+    RealTokenReserves,
+    Supply,
+    /// The curve's `complete` flag, set when it graduates
+    Complete,
+}
+
+impl TriggerField {
+    pub const ALL: [TriggerField; 7] = [
+        TriggerField::BondingCurve,
+        TriggerField::VirtualSolReserves,
+        TriggerField::VirtualTokenReserves,
+        TriggerField::RealSolReserves,
+        TriggerField::RealTokenReserves,
+        TriggerField::Supply,
+        TriggerField::Complete,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            TriggerField::BondingCurve => "bonding_curve",
+            TriggerField::VirtualSolReserves => "virtual_sol_reserves",
+            TriggerField::VirtualTokenReserves => "virtual_token_reserves",
+            TriggerField::RealSolReserves => "real_sol_reserves",
+            TriggerField::RealTokenReserves => "real_token_reserves",
+            TriggerField::Supply => "supply",
+            TriggerField::Complete => "complete",
+        }
+    }
+
+    /// Returns true if this field differs between the last-emitted and the new state
+    fn changed(&self, previous: &MintState, current: &TokenEvent) -> bool {
+        match self {
+            TriggerField::BondingCurve => previous.pump_data.bonding_curve != current.pump_data.bonding_curve,
+            TriggerField::VirtualSolReserves => previous.pump_data.virtual_sol_reserves != current.pump_data.virtual_sol_reserves,
+            TriggerField::VirtualTokenReserves => previous.pump_data.virtual_token_reserves != current.pump_data.virtual_token_reserves,
+            TriggerField::RealSolReserves => previous.pump_data.real_sol_reserves != current.pump_data.real_sol_reserves,
+            TriggerField::RealTokenReserves => previous.pump_data.real_token_reserves != current.pump_data.real_token_reserves,
+            TriggerField::Supply => previous.token.supply != current.token.supply,
+            TriggerField::Complete => previous.pump_data.complete != current.pump_data.complete,
+        }
+    }
+}
+
+impl FromStr for TriggerField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TriggerField::ALL
+            .iter()
+            .find(|field| field.name() == s)
+            .copied()
+            .ok_or_else(|| {
+                let valid: Vec<&str> = TriggerField::ALL.iter().map(|f| f.name()).collect();
+                format!("unknown field '{}', expected one of: {}", s, valid.join(", "))
+            })
+    }
+}
+
+impl fmt::Display for TriggerField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// Order in which `StateIndex::list` returns mints
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MintSort {
+    /// Furthest along the bonding curve first
+    Progress,
+    /// Highest market cap first
+    MarketCap,
+    /// Most recently updated first
+    Recency,
+}
+
+impl FromStr for MintSort {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "progress" => Ok(MintSort::Progress),
+            "market_cap" => Ok(MintSort::MarketCap),
+            "recency" => Ok(MintSort::Recency),
+            other => Err(format!("unknown sort '{}', expected progress, market_cap or recency", other)),
+        }
+    }
+}
+
+/// Latest known state of a mint as reported to clients
+#[derive(Serialize, Debug)]
+pub struct MintSummary {
+    pub mint_address: String,
+    pub name: String,
+    pub symbol: String,
+    pub price_sol: f64,
+    pub market_cap_sol: f64,
+    pub progress: f64,
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+    /// Rendered in the configured `TIMESTAMP_FORMAT`
+    pub last_updated: serde_json::Value,
+}
+
+/// Rollup of a creator's mints currently in the index
+pub struct CreatorSummary {
+    pub tokens_launched: usize,
+    /// SOL moved through the creator's curves, in lamports, while their mints were tracked
+    pub volume_lamports: u64,
+}
+
+/// A mint's trading over the volume leaders window
+#[derive(Serialize, Debug)]
+pub struct VolumeLeader {
+    pub mint: String,
+    pub volume_sol: f64,
+    pub trades: u64,
+}
+
+/// SOL traded and number of buys and sells within one slice of the volume window
+struct VolumeBucket {
+    index: u64,
+    lamports: u64,
+    trades: u64,
+}
+
+/// A buy or sell kept for the wash trade heuristic
+struct RecentTrade {
+    at: Instant,
+    trader: String,
+    is_buy: bool,
+    lamports: u64,
+}
+
+/// A mint's trades within the wash trade window, oldest first
+#[derive(Default)]
+struct TradeWindow {
+    trades: VecDeque<RecentTrade>,
+    /// Whether the current run of suspicious trading was already reported
+    flagged: bool,
+}
+
+impl TradeWindow {
+    /// Share of consecutive trades that reverse direction at a similar size, if the
+    /// window holds enough trades from few enough wallets to judge
+    fn wash_trade_confidence(&self, detection: &WashTradeDetection) -> Option<f64> {
+        if self.trades.len() < detection.min_trades {
+            return None;
+        }
+        let wallets: HashSet<&str> = self.trades.iter().map(|trade| trade.trader.as_str()).collect();
+        if wallets.len() > detection.max_wallets {
+            return None;
+        }
+        let pairs = self.trades.len() - 1;
+        let mirrored = self.trades.iter().zip(self.trades.iter().skip(1)).filter(|(previous, trade)| {
+            let larger = previous.lamports.max(trade.lamports).max(1) as f64;
+            previous.is_buy != trade.is_buy
+                && previous.lamports.abs_diff(trade.lamports) as f64 / larger <= detection.size_tolerance
+        }).count();
+        Some(mirrored as f64 / pairs as f64)
+    }
+}
+
+/// Outcome of observing an account update
+pub struct Observation {
+    /// Whether the event should be sent to clients
+    pub emit: bool,
+    /// Time since the mint was first seen, set on the first reserve change after creation
+    pub first_trade_after: Option<chrono::Duration>,
+    /// Set on a new mint whose creator exceeded the launch limit: launches within the window
+    pub creator_launches: Option<usize>,
+    /// Progress of a mint that just crossed the near-graduation threshold upward
+    pub near_graduation: Option<f64>,
+}
+
+/// Last-emitted state for a single mint
+struct MintState {
+    token: TokenDetails,
+    pump_data: PumpData,
+    last_updated: DateTime<Utc>,
+    /// When the mint was first seen and emitted as `token_created`
+    created_at: DateTime<Utc>,
+    /// Last time any update for the mint arrived, emitted or not
+    last_seen: Instant,
+    /// Whether the reserves have changed since creation
+    traded: bool,
+    /// Fingerprints of the most recently observed states, emitted or not
+    recent_states: VecDeque<u64>,
+    /// Whether the next upward crossing of the near-graduation threshold raises an alert
+    graduation_alert_armed: bool,
+    /// SOL reserves in the last observed update, emitted or not
+    observed_sol_reserves: u64,
+    /// Sum of SOL reserve changes, in lamports, since the mint was first tracked
+    volume_lamports: u64,
+    phase: Phase,
+    /// Market cap in SOL in the last observed update, emitted or not
+    observed_market_cap_sol: f64,
+    /// Whether the mint was announced before its creator was known, so its launch
+    /// still has to be counted once the `CreateEvent` arrives
+    launch_uncounted: bool,
+}
+
+impl MintState {
+    fn market_cap_sol(&self) -> f64 {
+        event_parser::market_cap_sol(self.pump_data.price_sol, self.token.supply, self.token.decimals)
+    }
+
+    fn progress(&self) -> f64 {
+        event_parser::bonding_curve_progress(self.pump_data.virtual_token_reserves)
+    }
+
+    fn summary(&self, format: OutputFormat) -> MintSummary {
+        MintSummary {
+            mint_address: self.token.mint_address.clone(),
+            name: self.token.name.clone(),
+            symbol: self.token.symbol.clone(),
+            price_sol: format.round_price(self.pump_data.price_sol),
+            market_cap_sol: format.round_price(self.market_cap_sol()),
+            progress: self.progress(),
+            virtual_sol_reserves: self.pump_data.virtual_sol_reserves,
+            virtual_token_reserves: self.pump_data.virtual_token_reserves,
+            last_updated: format.timestamps.render(self.last_updated),
+        }
+    }
+
+    fn remember(&mut self, fingerprint: u64) {
+        if self.recent_states.len() == RECENT_STATE_HISTORY {
+            self.recent_states.pop_front();
+        }
+        self.recent_states.push_back(fingerprint);
+    }
+}
+
+/// Market cap of the state in an update, whether or not it is emitted
+fn observed_market_cap_sol(event: &TokenEvent) -> f64 {
+    event_parser::market_cap_sol(event.pump_data.price_sol, event.token.supply, event.token.decimals)
+}
+
+/// Hashes the decoded account state so identical states can be recognized cheaply
+fn fingerprint(event: &TokenEvent) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    event.token.supply.hash(&mut hasher);
+    event.pump_data.bonding_curve.hash(&mut hasher);
+    event.pump_data.virtual_sol_reserves.hash(&mut hasher);
+    event.pump_data.virtual_token_reserves.hash(&mut hasher);
+    event.pump_data.real_sol_reserves.hash(&mut hasher);
+    event.pump_data.real_token_reserves.hash(&mut hasher);
+    event.pump_data.complete.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// In-memory index of the last-emitted state per mint
+///
+/// Used to decide whether an account notification is a new token, a change
+/// in one of the configured trigger fields, or a redundant update.
+pub struct StateIndex {
+    trigger_fields: Vec<TriggerField>,
+    mints: HashMap<String, MintState>,
+    /// Upper bound on `mints`; 0 means unbounded
+    max_tracked_mints: usize,
+    creator_limit: Option<CreatorLimit>,
+    /// Recent launch times per creator, oldest first
+    creator_launches: HashMap<String, VecDeque<Instant>>,
+    /// Size of `creator_launches` at which stale creators are next swept
+    next_creator_sweep: usize,
+    /// Mints seen at any point, so evicted mints are not announced as new again
+    seen_mints: Option<SeenMints>,
+    /// End of the post-resubscribe window in which replayed states are suppressed
+    resubscribe_grace_until: Option<Instant>,
+    graduation_alert: Option<GraduationAlert>,
+    /// Tracked mints by creator, kept only when creator tracking is enabled
+    creator_mints: Option<HashMap<String, HashSet<String>>>,
+    /// Width of one rolling volume bucket, when volume leaders are enabled
+    volume_bucket: Option<Duration>,
+    /// Start of bucket 0
+    volume_epoch: Instant,
+    /// Buy and sell volume per mint within the window, oldest bucket first
+    recent_volume: HashMap<String, VecDeque<VolumeBucket>>,
+    wash_trade_detection: Option<WashTradeDetection>,
+    /// Recent trades per mint, kept only when wash trade detection is enabled
+    recent_trades: HashMap<String, TradeWindow>,
+    /// Size of `recent_trades` at which mints with no recent trades are next swept
+    next_trade_sweep: usize,
+    /// Market caps whose crossing is a transition, when transitions are enabled
+    transition_market_caps: Option<Vec<f64>>,
+}
+
+impl StateIndex {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        trigger_fields: Vec<TriggerField>,
+        max_tracked_mints: usize,
+        creator_limit: Option<CreatorLimit>,
+        seen_mints: Option<SeenMints>,
+        graduation_alert: Option<GraduationAlert>,
+        index_creators: bool,
+        volume_window: Option<Duration>,
+        wash_trade_detection: Option<WashTradeDetection>,
+        transition_market_caps: Option<Vec<f64>>,
+    ) -> Self {
+        StateIndex {
+            trigger_fields,
+            mints: HashMap::new(),
+            max_tracked_mints,
+            creator_limit,
+            creator_launches: HashMap::new(),
+            next_creator_sweep: CREATOR_SWEEP_THRESHOLD,
+            seen_mints,
+            resubscribe_grace_until: None,
+            graduation_alert,
+            creator_mints: index_creators.then(HashMap::new),
+            volume_bucket: volume_window.map(|window| (window / VOLUME_BUCKETS).max(Duration::from_secs(1))),
+            volume_epoch: Instant::now(),
+            recent_volume: HashMap::new(),
+            wash_trade_detection,
+            recent_trades: HashMap::new(),
+            next_trade_sweep: WASH_TRADE_SWEEP_THRESHOLD,
+            transition_market_caps,
+        }
+    }
+
+    /// Starts a window in which updates matching any recently observed state are suppressed
+    ///
+    /// After a reconnect the RPC may replay account states we already processed
+    /// before the disconnect. Those can differ from the last-emitted state and
+    /// would otherwise be re-emitted as spurious trades.
+    pub fn begin_resubscribe_grace(&mut self, window: Duration) {
+        self.resubscribe_grace_until = Some(Instant::now() + window);
+    }
+
+    /// Records a mint from the startup snapshot without counting it as a launch
+    ///
+    /// Its trading history is unknown, so it is assumed to have traded and no
+    /// `first_trade` follows. Returns false if the mint is already tracked.
+    pub fn insert_snapshot(&mut self, event: &TokenEvent) -> bool {
+        if self.mints.contains_key(&event.token.mint_address) {
+            return false;
+        }
+        if let Some(seen) = self.seen_mints.as_mut() {
+            seen.insert(&event.token.mint_address);
+        }
+        let now = Utc::now();
+        self.mints.insert(event.token.mint_address.clone(), MintState {
+            token: event.token.clone(),
+            pump_data: event.pump_data.clone(),
+            last_updated: now,
+            created_at: now,
+            last_seen: Instant::now(),
+            traded: true,
+            recent_states: VecDeque::from([fingerprint(event)]),
+            graduation_alert_armed: self.below_graduation_threshold(event),
+            observed_sol_reserves: event.pump_data.virtual_sol_reserves,
+            volume_lamports: 0,
+            phase: Phase::Active,
+            observed_market_cap_sol: observed_market_cap_sol(event),
+            launch_uncounted: false,
+        });
+        self.index_creator(&event.token);
+        self.evict_if_full();
+        metrics::set_tracked_mints(self.mints.len());
+        true
+    }
+
+    /// Records the event and returns whether it should be emitted
+    ///
+    /// The first event for a mint is emitted as `token_created`. Later events
+    /// are emitted as `trade` only when a watched field has changed. With a
+    /// seen-mints filter, a mint that was evicted comes back as a `trade`.
+    pub fn observe(&mut self, event: &mut TokenEvent) -> Observation {
+        let fingerprint = fingerprint(event);
+        let graduation_alert = self.graduation_alert;
+
+        let Some(previous) = self.mints.get_mut(&event.token.mint_address) else {
+            let seen_before = self.seen_mints.as_mut().is_some_and(|seen| seen.insert(&event.token.mint_address));
+            let mut recent_states = VecDeque::with_capacity(RECENT_STATE_HISTORY);
+            recent_states.push_back(fingerprint);
+            let now = Utc::now();
+            // A mint first seen past the threshold never crossed it here, so it is not alerted
+            let graduation_alert_armed = self.below_graduation_threshold(event);
+            self.mints.insert(event.token.mint_address.clone(), MintState {
+                token: event.token.clone(),
+                pump_data: event.pump_data.clone(),
+                last_updated: now,
+                created_at: now,
+                last_seen: Instant::now(),
+                // Whether an evicted mint traded is unknown; assume it did so first_trade cannot repeat
+                traded: seen_before,
+                recent_states,
+                graduation_alert_armed,
+                observed_sol_reserves: event.pump_data.virtual_sol_reserves,
+                volume_lamports: 0,
+                phase: Phase::Active,
+                observed_market_cap_sol: observed_market_cap_sol(event),
+                launch_uncounted: !seen_before && !event.token.creator_known(),
+            });
+            self.index_creator(&event.token);
+            self.evict_if_full();
+            metrics::set_tracked_mints(self.mints.len());
+            if seen_before {
+                metrics::record_mint_readmitted();
+                event.event_type = "trade".to_string();
+                return Observation { emit: true, first_trade_after: None, creator_launches: None, near_graduation: None };
+            }
+            // A placeholder creator would pool every unresolved launch; those are counted on adoption
+            let creator_launches = event.token.creator_known().then(|| self.record_launch(&event.token.creator)).flatten();
+            return Observation { emit: true, first_trade_after: None, creator_launches, near_graduation: None };
+        };
+        previous.last_seen = Instant::now();
+
+        let in_grace = self.resubscribe_grace_until.is_some_and(|until| Instant::now() < until);
+        if in_grace && previous.recent_states.contains(&fingerprint) {
+            metrics::record_replay_suppressed();
+            return Observation { emit: false, first_trade_after: None, creator_launches: None, near_graduation: None };
+        }
+        previous.remember(fingerprint);
+        previous.volume_lamports += event.pump_data.virtual_sol_reserves.abs_diff(previous.observed_sol_reserves);
+        previous.observed_sol_reserves = event.pump_data.virtual_sol_reserves;
+
+        // Alert once per upward crossing; falling back through the hysteresis band re-arms it
+        let near_graduation = graduation_alert.and_then(|alert| {
+            let progress = event_parser::bonding_curve_progress(event.pump_data.virtual_token_reserves);
+            if previous.graduation_alert_armed && progress >= alert.threshold {
+                previous.graduation_alert_armed = false;
+                return Some(progress);
+            }
+            if progress < alert.threshold - alert.hysteresis {
+                previous.graduation_alert_armed = true;
+            }
+            None
+        });
+
+        // Reserves only move when someone buys or sells on the curve
+        let reserves_changed = TriggerField::VirtualSolReserves.changed(previous, event)
+            || TriggerField::VirtualTokenReserves.changed(previous, event);
+        let first_trade_after = (reserves_changed && !previous.traded).then(|| {
+            previous.traded = true;
+            Utc::now() - previous.created_at
+        });
+
+        if !self.trigger_fields.iter().any(|field| field.changed(previous, event)) {
+            return Observation { emit: false, first_trade_after, creator_launches: None, near_graduation };
+        }
+        event.event_type = "trade".to_string();
+        previous.token = event.token.clone();
+        previous.pump_data = event.pump_data.clone();
+        previous.last_updated = Utc::now();
+        Observation { emit: true, first_trade_after, creator_launches: None, near_graduation }
+    }
+
+    /// Moves an observed mint through the transition state machine, returning the
+    /// transitions it made in order; `created` marks a launch sent to clients
+    pub fn transitions(&mut self, event: &TokenEvent, created: bool) -> Vec<Transition> {
+        let Some(market_caps) = self.transition_market_caps.as_ref() else { return Vec::new() };
+        let Some(state) = self.mints.get_mut(&event.token.mint_address) else { return Vec::new() };
+        let market_cap = observed_market_cap_sol(event);
+        let previous = std::mem::replace(&mut state.observed_market_cap_sol, market_cap);
+        if created {
+            return vec![Transition::Created];
+        }
+        let mut transitions = Vec::new();
+        if state.phase == Phase::Graduated {
+            return transitions;
+        }
+        if state.phase == Phase::Stalled {
+            state.phase = Phase::Active;
+            transitions.push(Transition::Resumed);
+        }
+        // Thresholds are ascending, so walk them in the direction the market cap moved
+        if market_cap > previous {
+            transitions.extend(market_caps.iter()
+                .filter(|threshold| previous < **threshold && market_cap >= **threshold)
+                .map(|threshold| Transition::MarketCapCrossed { threshold: *threshold, upward: true }));
+        } else {
+            transitions.extend(market_caps.iter().rev()
+                .filter(|threshold| market_cap < **threshold && previous >= **threshold)
+                .map(|threshold| Transition::MarketCapCrossed { threshold: *threshold, upward: false }));
+        }
+        if event.pump_data.complete || event_parser::bonding_curve_progress(event.pump_data.virtual_token_reserves) >= 100.0 {
+            state.phase = Phase::Graduated;
+            transitions.push(Transition::Graduated);
+        }
+        transitions
+    }
+
+    /// Stalls active mints with no update for `stall_after`, returning each with
+    /// its market cap and how long it has been idle
+    pub fn stall_idle_mints(&mut self, stall_after: Duration) -> Vec<(String, f64, Duration)> {
+        self.mints.iter_mut()
+            .filter(|(_, state)| state.phase == Phase::Active && state.last_seen.elapsed() >= stall_after)
+            .map(|(mint, state)| {
+                state.phase = Phase::Stalled;
+                (mint.clone(), state.observed_market_cap_sol, state.last_seen.elapsed())
+            })
+            .collect()
+    }
+
+    /// Indexes a mint under its creator, once the creator is known; adoption indexes the rest
+    fn index_creator(&mut self, token: &TokenDetails) {
+        if !token.creator_known() {
+            return;
+        }
+        if let Some(creator_mints) = self.creator_mints.as_mut() {
+            creator_mints.entry(token.creator.clone()).or_default().insert(token.mint_address.clone());
+        }
+    }
+
+    fn unindex_creator(&mut self, token: &TokenDetails) {
+        if let Some(creator_mints) = self.creator_mints.as_mut() {
+            if let Some(mints) = creator_mints.get_mut(&token.creator) {
+                mints.remove(&token.mint_address);
+                if mints.is_empty() {
+                    creator_mints.remove(&token.creator);
+                }
+            }
+        }
+    }
+
+    /// Moves a mint tracked under its bonding curve's address, because its first update
+    /// arrived before its `CreateEvent`, to its real mint address and metadata
+    ///
+    /// The launch counts towards its creator's limit from here, since the creator was
+    /// unknown when it was announced. Returns false if no mint was tracked under the curve.
+    pub fn adopt_launch(&mut self, create: &CreateEvent) -> bool {
+        let Some(mut state) = self.mints.remove(&create.bonding_curve) else { return false };
+        self.unindex_creator(&state.token);
+        create.fill(&mut state.token);
+        self.index_creator(&state.token);
+        if std::mem::take(&mut state.launch_uncounted) {
+            self.record_launch(&create.creator);
+        }
+        self.mints.insert(create.mint.clone(), state);
+        true
+    }
+
+    /// Creator of a tracked mint
+    pub fn creator_of(&self, mint: &str) -> Option<&str> {
+        self.mints.get(mint).map(|state| state.token.creator.as_str())
+    }
+
+    /// Launch count and volume over a creator's tracked mints, or `None` without the creator index
+    pub fn creator_summary(&self, creator: &str) -> Option<CreatorSummary> {
+        let creator_mints = self.creator_mints.as_ref()?;
+        let mints = creator_mints.get(creator).into_iter().flatten().filter_map(|mint| self.mints.get(mint));
+        let (tokens_launched, volume_lamports) = mints.fold((0, 0), |(count, volume), state| (count + 1, volume + state.volume_lamports));
+        Some(CreatorSummary { tokens_launched, volume_lamports })
+    }
+
+    /// Index of the volume bucket now, or `None` when volume leaders are disabled
+    fn current_volume_bucket(&self) -> Option<u64> {
+        let width = self.volume_bucket?;
+        Some((self.volume_epoch.elapsed().as_millis() / width.as_millis()) as u64)
+    }
+
+    /// Adds a buy or sell of `lamports` to the mint's rolling volume
+    ///
+    /// Volume is keyed by mint rather than kept with the rest of the mint's state,
+    /// so it counts trades on curves the account subscription has not reported yet.
+    pub fn record_trade(&mut self, mint: &str, lamports: u64) {
+        let Some(index) = self.current_volume_bucket() else { return };
+        let oldest = index.saturating_sub(VOLUME_BUCKETS as u64 - 1);
+        let buckets = match self.recent_volume.get_mut(mint) {
+            Some(buckets) => buckets,
+            None => self.recent_volume.entry(mint.to_string()).or_default(),
+        };
+        while buckets.front().is_some_and(|bucket| bucket.index < oldest) {
+            buckets.pop_front();
+        }
+        match buckets.back_mut() {
+            Some(bucket) if bucket.index == index => {
+                bucket.lamports += lamports;
+                bucket.trades += 1;
+            }
+            _ => buckets.push_back(VolumeBucket { index, lamports, trades: 1 }),
+        }
+    }
+
+    /// The `top` mints by SOL traded over the volume window, highest first
+    ///
+    /// Mints with no trades left in the window are forgotten. Keeps a heap of at
+    /// most `top` entries, so one pass over the traded mints suffices.
+    pub fn volume_leaders(&mut self, top: usize) -> Vec<VolumeLeader> {
+        let Some(current) = self.current_volume_bucket() else { return Vec::new() };
+        let oldest = current.saturating_sub(VOLUME_BUCKETS as u64 - 1);
+        self.recent_volume.retain(|_, buckets| buckets.back().is_some_and(|bucket| bucket.index >= oldest));
+        let mut heap = BinaryHeap::with_capacity(top + 1);
+        for (mint, buckets) in &self.recent_volume {
+            let (lamports, trades) = buckets.iter()
+                .filter(|bucket| bucket.index >= oldest)
+                .fold((0, 0), |(lamports, trades), bucket| (lamports + bucket.lamports, trades + bucket.trades));
+            heap.push(Reverse((lamports, trades, mint)));
+            if heap.len() > top {
+                heap.pop();
+            }
+        }
+        heap.into_sorted_vec().into_iter().map(|Reverse((lamports, trades, mint))| VolumeLeader {
+            mint: mint.clone(),
+            volume_sol: lamports as f64 / event_parser::LAMPORTS_PER_SOL,
+            trades,
+        }).collect()
+    }
+
+    /// Adds a buy or sell to the mint's recent trades, returning the confidence that
+    /// the mint is being wash traded when it is first flagged
+    ///
+    /// A flagged mint is flagged again only after its trading stops looking suspicious.
+    pub fn observe_trade(&mut self, mint: &str, trader: &str, is_buy: bool, lamports: u64) -> Option<f64> {
+        let detection = self.wash_trade_detection?;
+        let now = Instant::now();
+        let recent = |trade: &RecentTrade| now.duration_since(trade.at) < detection.window;
+
+        // Forget mints with no recent trades, less often as the number of traded mints grows
+        if self.recent_trades.len() >= self.next_trade_sweep {
+            self.recent_trades.retain(|_, window| window.trades.back().is_some_and(recent));
+            self.next_trade_sweep = (self.recent_trades.len() * 2).max(WASH_TRADE_SWEEP_THRESHOLD);
+        }
+
+        let window = match self.recent_trades.get_mut(mint) {
+            Some(window) => window,
+            None => self.recent_trades.entry(mint.to_string()).or_default(),
+        };
+        while window.trades.front().is_some_and(|trade| !recent(trade)) || window.trades.len() >= WASH_TRADE_HISTORY {
+            window.trades.pop_front();
+        }
+        window.trades.push_back(RecentTrade { at: now, trader: trader.to_string(), is_buy, lamports });
+
+        let confidence = window.wash_trade_confidence(&detection).filter(|confidence| *confidence >= detection.min_confidence);
+        let first = confidence.is_some() && !window.flagged;
+        window.flagged = confidence.is_some();
+        confidence.filter(|_| first)
+    }
+
+    fn below_graduation_threshold(&self, event: &TokenEvent) -> bool {
+        self.graduation_alert.is_some_and(|alert| {
+            event_parser::bonding_curve_progress(event.pump_data.virtual_token_reserves) < alert.threshold
+        })
+    }
+
+    /// Records a launch by `creator`, returning its launch count if that exceeds the limit
+    fn record_launch(&mut self, creator: &str) -> Option<usize> {
+        let limit = self.creator_limit?;
+        let now = Instant::now();
+        let recent = |launch: &Instant| now.duration_since(*launch) < limit.window;
+
+        // Forget creators with no recent launches, less often as the number of active creators grows
+        if self.creator_launches.len() >= self.next_creator_sweep {
+            self.creator_launches.retain(|_, launches| launches.back().is_some_and(recent));
+            self.next_creator_sweep = (self.creator_launches.len() * 2).max(CREATOR_SWEEP_THRESHOLD);
+        }
+
+        let launches = self.creator_launches.entry(creator.to_string()).or_default();
+        while launches.front().is_some_and(|launch| !recent(launch)) {
+            launches.pop_front();
+        }
+        launches.push_back(now);
+        (launches.len() > limit.max_launches).then_some(launches.len())
+    }
+
+    /// Returns the total number of tracked mints and one page of their summaries
+    pub fn list(&self, sort: MintSort, offset: usize, limit: usize, format: OutputFormat) -> (usize, Vec<MintSummary>) {
+        let mut mints: Vec<&MintState> = self.mints.values().collect();
+        match sort {
+            MintSort::Progress => mints.sort_by(|a, b| b.progress().total_cmp(&a.progress())),
+            MintSort::MarketCap => mints.sort_by(|a, b| b.market_cap_sol().total_cmp(&a.market_cap_sol())),
+            MintSort::Recency => mints.sort_by_key(|m| std::cmp::Reverse(m.last_updated)),
+        }
+        let page = mints.into_iter().skip(offset).take(limit).map(|m| m.summary(format)).collect();
+        (self.mints.len(), page)
+    }
+
+    /// Summaries of at most `max` mints that `keep` accepts given their creator and market cap,
+    /// most recently updated first, with how many it accepted in total
+    pub fn snapshot(&self, max: usize, format: OutputFormat, keep: impl Fn(&str, f64) -> bool) -> (usize, Vec<MintSummary>) {
+        let mut mints: Vec<&MintState> = self.mints.values()
+            .filter(|m| keep(&m.token.creator, m.market_cap_sol()))
+            .collect();
+        let total = mints.len();
+        mints.sort_by_key(|m| std::cmp::Reverse(m.last_updated));
+        let page = mints.into_iter().take(max).map(|m| m.summary(format)).collect();
+        (total, page)
+    }
+
+    /// Drops the least recently seen mints once the index exceeds `max_tracked_mints`
+    ///
+    /// Near-graduation mints go last. Evicts a small batch at a time so the scan is amortized
+    /// over many insertions. An evicted mint's next update is treated as a new token.
+    fn evict_if_full(&mut self) {
+        if self.max_tracked_mints == 0 || self.mints.len() <= self.max_tracked_mints {
+            return;
+        }
+        let excess = self.mints.len() - self.max_tracked_mints;
+        let batch = (excess + self.max_tracked_mints / EVICTION_BATCH_DIVISOR).min(self.mints.len());
+
+        let mut candidates: Vec<(bool, Instant, &String)> = self.mints.iter()
+            .map(|(mint, state)| (state.progress() >= NEAR_GRADUATION_PROGRESS, state.last_seen, mint))
+            .collect();
+        candidates.select_nth_unstable_by_key(batch - 1, |(near_graduation, last_seen, _)| (*near_graduation, *last_seen));
+        let evicted: Vec<String> = candidates[..batch].iter().map(|(_, _, mint)| (*mint).clone()).collect();
+
+        for mint in &evicted {
+            let Some(state) = self.mints.remove(mint) else { continue };
+            self.unindex_creator(&state.token);
+        }
+        metrics::record_mint_evictions(evicted.len() as u64);
+    }
+
+    /// Number of tracked mints updated at or after `since`
+    pub fn active_since(&self, since: DateTime<Utc>) -> usize {
+        self.mints.values().filter(|m| m.last_updated >= since).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+    use serde_json::json;
+    use crate::event_parser::ProgramEvent;
+
+    /// A curve update for `curve`, with placeholder token details as the account carries none
+    fn curve_update(curve: &str, real_sol_reserves: u64, complete: bool) -> TokenEvent {
+        let mut data = vec![23, 183, 248, 55, 96, 216, 172, 96];
+        for amount in [1_073_000_000_000_000, 30_000_000_000, 793_100_000_000_000, real_sol_reserves, 1_000_000_000_000_000u64] {
+            data.extend_from_slice(&amount.to_le_bytes());
+        }
+        data.push(complete as u8);
+        let notification = json!({
+            "jsonrpc": "2.0",
+            "method": "programNotification",
+            "params": {"result": {"context": {"slot": 100}, "value": {"pubkey": curve, "account": {
+                "data": [base64::engine::general_purpose::STANDARD.encode(data), "base64"],
+                "owner": event_parser::PUMP_FUN_PROGRAM_ID,
+            }}}, "subscription": 42},
+        });
+        match event_parser::parse_event(&notification.to_string()) {
+            Some(ProgramEvent::Token(event)) => event,
+            _ => panic!("curve update did not parse"),
+        }
+    }
+
+    fn launch(curve: &str, creator: &str) -> CreateEvent {
+        CreateEvent {
+            name: "Token".to_string(),
+            symbol: "TKN".to_string(),
+            uri: String::new(),
+            mint: format!("mint_of_{}", curve),
+            bonding_curve: curve.to_string(),
+            creator: creator.to_string(),
+        }
+    }
+
+    fn index_with_launch_limit(max_launches: usize) -> StateIndex {
+        let limit = CreatorLimit { max_launches, window: Duration::from_secs(3600) };
+        StateIndex::new(TriggerField::ALL.to_vec(), 0, Some(limit), None, None, false, None, None, None)
+    }
+
+    #[test]
+    fn unknown_creators_are_not_pooled() {
+        let mut index = index_with_launch_limit(1);
+        let first = index.observe(&mut curve_update("CurveA", 0, false));
+        let second = index.observe(&mut curve_update("CurveB", 0, false));
+        assert!(first.emit && second.emit);
+        assert_eq!(first.creator_launches, None);
+        assert_eq!(second.creator_launches, None);
+    }
+
+    #[test]
+    fn adopted_launches_count_towards_their_creator() {
+        let mut index = index_with_launch_limit(1);
+        index.observe(&mut curve_update("CurveA", 0, false));
+        index.observe(&mut curve_update("CurveB", 0, false));
+        assert!(index.adopt_launch(&launch("CurveA", "Creator")));
+        assert!(index.adopt_launch(&launch("CurveB", "Creator")));
+
+        let mut third = curve_update("CurveC", 0, false);
+        launch("CurveC", "Creator").fill(&mut third.token);
+        assert_eq!(index.observe(&mut third).creator_launches, Some(3));
+    }
+
+    #[test]
+    fn mints_are_indexed_once_their_creator_is_known() {
+        let mut index = StateIndex::new(TriggerField::ALL.to_vec(), 0, None, None, None, true, None, None, None);
+        index.observe(&mut curve_update("CurveA", 0, false));
+        index.observe(&mut curve_update("CurveB", 0, false));
+        assert_eq!(index.creator_summary(event_parser::PLACEHOLDER_CREATOR).unwrap().tokens_launched, 0);
+
+        assert!(index.adopt_launch(&launch("CurveA", "Creator")));
+        assert_eq!(index.creator_summary("Creator").unwrap().tokens_launched, 1);
+    }
+
+    #[test]
+    fn completing_the_curve_alone_triggers_an_emit() {
+        let mut index = StateIndex::new(vec![TriggerField::Complete], 0, None, None, None, false, None, None, None);
+        assert!(index.observe(&mut curve_update("CurveA", 0, false)).emit);
+        assert!(!index.observe(&mut curve_update("CurveA", 0, false)).emit);
+        let mut completed = curve_update("CurveA", 0, true);
+        assert!(index.observe(&mut completed).emit);
+        assert_eq!(completed.event_type, "trade");
+    }
+
+    #[test]
+    fn real_reserves_can_trigger_an_emit() {
+        let mut index = StateIndex::new(vec!["real_sol_reserves".parse().unwrap()], 0, None, None, None, false, None, None, None);
+        assert!(index.observe(&mut curve_update("CurveA", 0, false)).emit);
+        assert!(index.observe(&mut curve_update("CurveA", 5_000, false)).emit);
+    }
+}
@@ -0,0 +1,49 @@
+//! Initial load for clients that sync: the current state index as paged `snapshot`
+//! messages, after which live events are numbered on from the snapshot's `seq`
+
+use std::sync::Mutex;
+use serde_json::{json, Value};
+use crate::client_groups;
+use crate::creator_tracking::TrackedCreators;
+use crate::event_parser::OutputFormat;
+use crate::state_index::{MintSummary, StateIndex};
+
+/// How much of the state index one sync sends and how it is split into pages
+#[derive(Debug, Clone, Copy)]
+pub struct SyncLimits {
+    pub max_mints: usize,
+    pub page_size: usize,
+}
+
+/// The `snapshot` pages for one sync, most recently updated mints first
+///
+/// Mints are limited to the client's group market cap bounds and tracked creators.
+/// Every page carries `seq`, the number of the last event sent before the snapshot.
+pub fn snapshot_pages(
+    state: &Mutex<StateIndex>,
+    limits: &SyncLimits,
+    format: OutputFormat,
+    group: Option<&str>,
+    creators: &TrackedCreators,
+    seq: u64,
+) -> Vec<Value> {
+    let (total, mints) = state.lock().unwrap().snapshot(limits.max_mints, format, |creator, market_cap_sol| {
+        group.is_none_or(|group| client_groups::accepts_market_cap(group, market_cap_sol))
+            && (creators.is_empty() || creators.contains(creator))
+    });
+    // An empty index still sends one page, so the client always learns the boundary
+    let pages: Vec<&[MintSummary]> = match mints.is_empty() {
+        true => vec![&[]],
+        false => mints.chunks(limits.page_size).collect(),
+    };
+    let count = pages.len();
+    pages.into_iter().enumerate().map(|(index, page)| json!({
+        "type": "snapshot",
+        "page": index + 1,
+        "pages": count,
+        "total": total,
+        "truncated": total > mints.len(),
+        "seq": seq,
+        "mints": page,
+    })).collect()
+}
@@ -0,0 +1,74 @@
+//! Restarts long-running pipeline tasks that panic instead of leaving a server that accepts clients but sends nothing
+
+use std::future::Future;
+use std::panic::AssertUnwindSafe;
+use std::time::Duration;
+use futures::FutureExt;
+use log::{error, warn};
+use tokio::time::Instant;
+use crate::metrics;
+
+// Delay before restarting a panicked task: doubles from the initial delay up to the cap
+const INITIAL_RESTART_DELAY: Duration = Duration::from_secs(1);
+const MAX_RESTART_DELAY: Duration = Duration::from_secs(60);
+
+/// Runs the task built by `start`, building and running a fresh one each time it panics
+///
+/// The next task is built as soon as the previous one panics, so `start` can repair
+/// shared state before the restart delay. The delay resets once a run lasts longer
+/// than the maximum delay. Returns the output of the first task to finish without
+/// panicking; aborting the supervisor aborts the task.
+pub async fn supervise<F, Fut, T>(name: &'static str, mut start: F) -> T
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = T>,
+{
+    let mut delay = INITIAL_RESTART_DELAY;
+    let mut task = start();
+    loop {
+        let started = Instant::now();
+        let panic = match AssertUnwindSafe(task).catch_unwind().await {
+            Ok(output) => return output,
+            Err(panic) => panic,
+        };
+        let message = panic.downcast_ref::<&str>().copied()
+            .or_else(|| panic.downcast_ref::<String>().map(String::as_str))
+            .unwrap_or("non-string panic payload");
+        let restarts = metrics::record_task_restart();
+        if started.elapsed() > MAX_RESTART_DELAY {
+            delay = INITIAL_RESTART_DELAY;
+        }
+        error!("Task '{}' panicked: {}", name, message);
+        task = start();
+        warn!("Restarting '{}' in {:?} (restart #{})", name, delay, restarts);
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RESTART_DELAY);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[tokio::test]
+    async fn task_that_panics_once_is_restarted() {
+        let restarts_before = metrics::stats()["task_restarts"].as_u64().unwrap();
+        let starts = Arc::new(AtomicUsize::new(0));
+        let runs = starts.clone();
+        let supervised = supervise("panics once", move || {
+            let run = runs.fetch_add(1, Ordering::SeqCst);
+            async move {
+                if run == 0 {
+                    panic!("injected failure");
+                }
+            }
+        });
+        // One restart waits out the initial one-second delay
+        tokio::time::timeout(INITIAL_RESTART_DELAY * 5, supervised).await
+            .expect("supervisor should return once the restarted task finishes");
+        assert_eq!(starts.load(Ordering::SeqCst), 2);
+        assert!(metrics::stats()["task_restarts"].as_u64().unwrap() > restarts_before);
+    }
+}
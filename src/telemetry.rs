@@ -0,0 +1,99 @@
+//! Logging setup with optional OpenTelemetry export
+//!
+//! By default logs go through `env_logger` and the pipeline's `tracing` spans
+//! are no-ops. When built with the `otel` feature and an OTLP endpoint is
+//! configured, a `tracing` subscriber is installed instead: spans are exported
+//! over OTLP and existing `log` records are bridged into the same subscriber.
+
+use log::warn;
+
+/// Initializes logging and, if configured, OTLP span export
+///
+/// Returns true when spans are being exported.
+pub fn init(otlp_endpoint: Option<&str>) -> bool {
+    match otlp_endpoint {
+        #[cfg(feature = "otel")]
+        Some(endpoint) => match otel::init(endpoint) {
+            Ok(()) => true,
+            Err(e) => {
+                init_env_logger();
+                warn!("Failed to start OpenTelemetry export to {}: {}", endpoint, e);
+                false
+            }
+        },
+        #[cfg(not(feature = "otel"))]
+        Some(_) => {
+            init_env_logger();
+            warn!("OTEL_EXPORTER_OTLP_ENDPOINT is set but this build lacks the `otel` feature; spans will not be exported");
+            false
+        }
+        None => {
+            init_env_logger();
+            false
+        }
+    }
+}
+
+/// Flushes any spans still queued for export
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    opentelemetry::global::shutdown_tracer_provider();
+}
+
+/// Returns the (trace id, span id) of the current span when spans are being exported
+pub fn current_trace_context() -> Option<(String, String)> {
+    #[cfg(feature = "otel")]
+    {
+        otel::current_trace_context()
+    }
+    #[cfg(not(feature = "otel"))]
+    {
+        None
+    }
+}
+
+fn init_env_logger() {
+    env_logger::init_from_env(env_logger::Env::default().default_filter_or("info"));
+}
+
+#[cfg(feature = "otel")]
+mod otel {
+    use opentelemetry::trace::{TraceContextExt, TracerProvider as _};
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::Resource;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::layer::SubscriberExt;
+    use tracing_subscriber::util::SubscriberInitExt;
+    use tracing_subscriber::EnvFilter;
+
+    pub fn init(endpoint: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let provider = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+            .with_trace_config(opentelemetry_sdk::trace::Config::default().with_resource(
+                Resource::new(vec![KeyValue::new("service.name", env!("CARGO_PKG_NAME"))]),
+            ))
+            .install_batch(opentelemetry_sdk::runtime::Tokio)?;
+        let tracer = provider.tracer(env!("CARGO_PKG_NAME"));
+        opentelemetry::global::set_tracer_provider(provider);
+
+        // `try_init` also installs the `log` -> `tracing` bridge
+        tracing_subscriber::registry()
+            .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+            .with(tracing_subscriber::fmt::layer())
+            .with(tracing_opentelemetry::layer().with_tracer(tracer))
+            .try_init()?;
+        Ok(())
+    }
+
+    pub fn current_trace_context() -> Option<(String, String)> {
+        let context = tracing::Span::current().context();
+        let span = context.span();
+        let span_context = span.span_context();
+        if !span_context.is_valid() {
+            return None;
+        }
+        Some((span_context.trace_id().to_string(), span_context.span_id().to_string()))
+    }
+}
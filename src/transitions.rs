@@ -0,0 +1,170 @@
+//! Per-mint state machine behind `transitions_only` connections: instead of every
+//! reserve tick, those clients receive one `transition` message each time a mint
+//! is created, crosses a market cap threshold, stalls, resumes or graduates
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use chrono::Utc;
+use serde::Serialize;
+use serde_json::{json, Value};
+use crate::config::Config;
+use crate::event_channels::EventChannels;
+use crate::replay_buffer::ReplayBuffer;
+use crate::solana_client::{self, Cluster};
+use crate::state_index::StateIndex;
+
+/// Thresholds for the transitions computed when `TRANSITIONS=true`
+#[derive(Debug, Clone)]
+pub struct TransitionSettings {
+    /// Market caps in SOL, ascending, whose crossing in either direction is a transition
+    pub market_caps_sol: Vec<f64>,
+    /// Time without updates after which an active mint stalls; `None` never stalls mints
+    pub stall_after: Option<Duration>,
+}
+
+/// Where a mint is in its lifecycle
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Phase {
+    Active,
+    Stalled,
+    Graduated,
+}
+
+impl Phase {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Phase::Active => "active",
+            Phase::Stalled => "stalled",
+            Phase::Graduated => "graduated",
+        }
+    }
+}
+
+/// A discrete change in a mint's state
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transition {
+    Created,
+    MarketCapCrossed { threshold: f64, upward: bool },
+    Stalled { idle: Duration },
+    Resumed,
+    Graduated,
+}
+
+/// One edge of the state machine, as listed in capabilities
+#[derive(Serialize, Debug)]
+pub struct Rule {
+    pub transition: &'static str,
+    /// Phases the transition leaves; empty for a mint's first transition
+    pub from: &'static [&'static str],
+    pub to: &'static str,
+}
+
+/// Every transition a mint can make
+pub const RULES: &[Rule] = &[
+    Rule { transition: "created", from: &[], to: "active" },
+    Rule { transition: "market_cap_crossed", from: &["active"], to: "active" },
+    Rule { transition: "stalled", from: &["active"], to: "stalled" },
+    Rule { transition: "resumed", from: &["stalled"], to: "active" },
+    Rule { transition: "graduated", from: &["active"], to: "graduated" },
+];
+
+/// The state machine and its thresholds, as listed in capabilities
+#[derive(Serialize, Debug)]
+pub struct StateMachine {
+    pub states: &'static [&'static str],
+    pub transitions: &'static [Rule],
+    pub market_caps_sol: Vec<f64>,
+    pub stall_after_secs: Option<u64>,
+}
+
+impl StateMachine {
+    pub fn new(settings: &TransitionSettings) -> Self {
+        StateMachine {
+            states: &["active", "stalled", "graduated"],
+            transitions: RULES,
+            market_caps_sol: settings.market_caps_sol.clone(),
+            stall_after_secs: settings.stall_after.map(|after| after.as_secs()),
+        }
+    }
+}
+
+impl Transition {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Transition::Created => "created",
+            Transition::MarketCapCrossed { .. } => "market_cap_crossed",
+            Transition::Stalled { .. } => "stalled",
+            Transition::Resumed => "resumed",
+            Transition::Graduated => "graduated",
+        }
+    }
+
+    fn phases(&self) -> (Option<Phase>, Phase) {
+        match self {
+            Transition::Created => (None, Phase::Active),
+            Transition::MarketCapCrossed { .. } => (Some(Phase::Active), Phase::Active),
+            Transition::Stalled { .. } => (Some(Phase::Active), Phase::Stalled),
+            Transition::Resumed => (Some(Phase::Stalled), Phase::Active),
+            Transition::Graduated => (Some(Phase::Active), Phase::Graduated),
+        }
+    }
+
+    /// The `transition` message sent to `transitions_only` connections
+    pub fn message(&self, cluster: Cluster, mint: &str, market_cap_sol: f64, slot: Option<u64>) -> Value {
+        let (from, to) = self.phases();
+        let mut message = json!({
+            "type": "transition",
+            "transition": self.name(),
+            "cluster": cluster.to_string(),
+            "mint": mint,
+            "from": from.map(|phase| phase.name()),
+            "to": to.name(),
+            "market_cap_sol": market_cap_sol,
+        });
+        if let Some(slot) = slot {
+            message["slot"] = slot.into();
+        }
+        match self {
+            Transition::MarketCapCrossed { threshold, upward } => {
+                message["threshold"] = (*threshold).into();
+                message["direction"] = if *upward { "up" } else { "down" }.into();
+            }
+            Transition::Stalled { idle } => message["idle_secs"] = idle.as_secs().into(),
+            _ => {}
+        }
+        message
+    }
+}
+
+/// Whether a message reaches a connection, given whether it asked for `transitions_only`
+///
+/// Such connections receive transitions and control messages but no events; all
+/// other connections receive everything except transitions.
+pub fn passes(message: &str, transitions_only: bool) -> bool {
+    #[derive(serde::Deserialize)]
+    struct Kind<'a> {
+        #[serde(borrow)]
+        event_type: Option<&'a str>,
+        #[serde(borrow, rename = "type")]
+        control_type: Option<&'a str>,
+    }
+    let Ok(kind) = serde_json::from_str::<Kind>(message) else { return !transitions_only };
+    match transitions_only {
+        true => kind.event_type.is_none(),
+        false => kind.control_type != Some("transition"),
+    }
+}
+
+/// Stalls mints that have gone `stall_after` without an update, checking ten times per period
+pub async fn run_stall_sweep(stall_after: Duration, config: Arc<Config>, state: Arc<Mutex<StateIndex>>, sender: EventChannels, replay: Arc<Mutex<ReplayBuffer>>) {
+    let mut ticker = tokio::time::interval((stall_after / 10).max(Duration::from_secs(1)));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        let stalled = state.lock().unwrap().stall_idle_mints(stall_after);
+        for (mint, market_cap_sol, idle) in stalled {
+            let message = Transition::Stalled { idle }.message(config.cluster, &mint, market_cap_sol, None);
+            solana_client::broadcast(message.to_string(), Utc::now(), &config, &sender, &replay);
+        }
+    }
+}
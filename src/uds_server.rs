@@ -0,0 +1,103 @@
+//! Newline-delimited JSON event stream over a Unix domain socket
+//!
+//! For consumers on the same host: no TCP, no WebSocket framing, and nothing
+//! exposed on the network. Every connected consumer gets the same broadcast
+//! events as WebSocket clients, one JSON object per line.
+
+use crate::event_channels::EventChannels;
+use crate::output_schema::OutputSchema;
+
+/// Listens on `path` and streams events to local consumers until aborted
+pub async fn start_uds_server(path: String, schema: OutputSchema, sender: EventChannels) {
+    #[cfg(unix)]
+    unix::serve(&path, schema, sender).await;
+
+    #[cfg(not(unix))]
+    {
+        let _ = (schema, sender);
+        log::warn!("UDS_PATH is set to {} but Unix domain sockets are not supported on this platform", path);
+    }
+}
+
+/// Removes the socket file on shutdown
+pub fn cleanup(path: &str) {
+    #[cfg(unix)]
+    unix::remove_stale_socket(path);
+
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+#[cfg(unix)]
+mod unix {
+    use std::os::unix::fs::FileTypeExt;
+    use log::{debug, error, info, warn};
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{UnixListener, UnixStream};
+    use crate::event_channels::{EventChannels, Missed, Subscription};
+    use crate::output_schema::OutputSchema;
+    use crate::metrics;
+
+    pub async fn serve(path: &str, schema: OutputSchema, sender: EventChannels) {
+        // A socket left behind by an unclean exit would make bind fail
+        remove_stale_socket(path);
+
+        let listener = match UnixListener::bind(path) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to bind Unix socket {}: {}", path, e);
+                return;
+            }
+        };
+        info!("Unix socket event stream listening on {}", path);
+
+        let mut consumer_id = 0u64;
+        loop {
+            match listener.accept().await {
+                Ok((stream, _)) => {
+                    consumer_id += 1;
+                    info!("Unix socket consumer #{} connected", consumer_id);
+                    let rx = sender.subscribe();
+                    tokio::spawn(stream_events(stream, rx, schema, consumer_id));
+                }
+                Err(e) => error!("Failed to accept Unix socket connection: {}", e),
+            }
+        }
+    }
+
+    /// Removes `path` if it is a socket; any other kind of file is left alone
+    pub fn remove_stale_socket(path: &str) {
+        let is_socket = std::fs::symlink_metadata(path).is_ok_and(|meta| meta.file_type().is_socket());
+        if is_socket {
+            if let Err(e) = std::fs::remove_file(path) {
+                warn!("Failed to remove Unix socket {}: {}", path, e);
+            }
+        }
+    }
+
+    async fn stream_events(mut stream: UnixStream, mut rx: Subscription, schema: OutputSchema, consumer_id: u64) {
+        let mut message_count = 0u64;
+        loop {
+            let mut line = match rx.recv().await {
+                Ok(message) => schema.render(message),
+                Err(Missed::Lagged { skipped, lossy }) => {
+                    metrics::record_client_lag(skipped);
+                    warn!("Unix socket consumer #{} fell behind and missed {} events", consumer_id, skipped);
+                    // Trades dropped in priority mode are tolerated; anything else disconnects
+                    if lossy {
+                        continue;
+                    }
+                    break;
+                }
+                Err(Missed::Closed) => break,
+            };
+            line.push('\n');
+            if let Err(e) = stream.write_all(line.as_bytes()).await {
+                debug!("Unix socket consumer #{} write failed: {}", consumer_id, e);
+                break;
+            }
+            message_count += 1;
+        }
+        info!("Unix socket consumer #{} disconnected. Total messages sent: {}", consumer_id, message_count);
+    }
+}
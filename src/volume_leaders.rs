@@ -0,0 +1,44 @@
+//! Periodic `volume_leaders` rollup: the mints with the most SOL moved over a rolling window
+
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde_json::json;
+use crate::event_channels::EventChannels;
+use crate::state_index::StateIndex;
+
+/// How the leaderboard is computed and how often it is sent
+#[derive(Debug, Clone, Copy)]
+pub struct LeaderBoard {
+    pub window: Duration,
+    pub top: usize,
+    pub interval: Duration,
+}
+
+/// Short label for a window, such as `5m` or `1h`
+fn window_label(window: Duration) -> String {
+    let secs = window.as_secs();
+    if secs.is_multiple_of(3600) {
+        format!("{}h", secs / 3600)
+    } else if secs.is_multiple_of(60) {
+        format!("{}m", secs / 60)
+    } else {
+        format!("{}s", secs)
+    }
+}
+
+/// Broadcasts the leaderboard every `interval`, first one `interval` after start
+pub async fn run(board: LeaderBoard, state: Arc<Mutex<StateIndex>>, sender: EventChannels) {
+    let mut ticker = tokio::time::interval_at(tokio::time::Instant::now() + board.interval, board.interval);
+    let label = window_label(board.window);
+    loop {
+        ticker.tick().await;
+        let leaders = state.lock().unwrap().volume_leaders(board.top);
+        let message = json!({
+            "type": "volume_leaders",
+            "window": label,
+            "window_secs": board.window.as_secs(),
+            "leaders": leaders,
+        });
+        let _ = sender.send(message.to_string());
+    }
+}
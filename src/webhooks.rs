@@ -0,0 +1,173 @@
+//! Optional webhook sink: POSTs each event to the configured URLs
+//!
+//! Runs as another broadcast consumer next to the WebSocket server. Deliveries
+//! run concurrently up to `WEBHOOK_CONCURRENCY`, failed ones are retried with
+//! exponential backoff, and events that still fail are dead-lettered.
+
+use std::sync::Arc;
+use std::time::Duration;
+use hmac::{Hmac, Mac};
+use hyper::client::HttpConnector;
+use hyper::{Body, Client, Method, Request, Uri};
+use hyper_rustls::HttpsConnector;
+use log::{info, warn, error};
+use serde_json::{json, Value};
+use sha2::Sha256;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Semaphore;
+use crate::config::Config;
+use crate::event_channels::{Missed, Subscription};
+use crate::metrics;
+
+// Backoff between delivery attempts: doubles from the initial delay up to the cap
+const INITIAL_RETRY_DELAY: Duration = Duration::from_millis(500);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30);
+
+// Per-attempt limit so a hung receiver cannot hold a delivery slot forever
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+type HttpsClient = Client<HttpsConnector<HttpConnector>>;
+
+/// A webhook target with an optional event type filter
+#[derive(Debug, Clone)]
+pub struct Webhook {
+    pub url: Uri,
+    /// Event types delivered to this webhook; `None` delivers every event
+    pub event_types: Option<Vec<String>>,
+}
+
+impl Webhook {
+    /// Parses `url` or `url|type+type`, e.g. `https://example.com/hook|token_created+first_trade`
+    pub fn parse(entry: &str) -> Result<Self, String> {
+        let (url, filter) = match entry.split_once('|') {
+            Some((url, filter)) => (url, Some(filter)),
+            None => (entry, None),
+        };
+        let url: Uri = url.parse().map_err(|e| format!("invalid webhook URL '{}': {}", url, e))?;
+        if !matches!(url.scheme_str(), Some("http" | "https")) {
+            return Err(format!("webhook URL '{}' must use http or https", url));
+        }
+        let event_types = filter.map(|filter| {
+            filter.split('+').map(str::trim).filter(|t| !t.is_empty()).map(String::from).collect()
+        });
+        Ok(Webhook { url, event_types })
+    }
+
+    fn accepts(&self, event_type: &str) -> bool {
+        self.event_types.as_ref().is_none_or(|types| types.iter().any(|t| t == event_type))
+    }
+}
+
+/// Delivers broadcast events to every configured webhook until the channel closes
+///
+/// Returns immediately when no webhooks are configured.
+pub async fn run_webhooks(config: Arc<Config>, mut receiver: Subscription) {
+    if config.webhooks.is_empty() {
+        return;
+    }
+    info!("Delivering events to {} webhook(s)", config.webhooks.len());
+
+    let https = hyper_rustls::HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_or_http()
+        .enable_http1()
+        .build();
+    let client: HttpsClient = Client::builder().build(https);
+    let slots = Arc::new(Semaphore::new(config.webhook_concurrency));
+
+    loop {
+        let message = match receiver.recv().await {
+            Ok(message) => message,
+            Err(Missed::Lagged { skipped, .. }) => {
+                metrics::record_webhook_dropped(skipped);
+                warn!("Webhook delivery fell behind; {} events were not delivered", skipped);
+                continue;
+            }
+            Err(Missed::Closed) => break,
+        };
+
+        // Only structured events are delivered, not raw notifications forwarded for debugging
+        let Some(event_type) = serde_json::from_str::<Value>(&message).ok()
+            .and_then(|event| event.get("event_type")?.as_str().map(String::from)) else {
+            continue;
+        };
+
+        for webhook in config.webhooks.iter().filter(|w| w.accepts(&event_type)) {
+            // Waiting here when every slot is busy makes slow receivers show up as broadcast lag
+            let Ok(permit) = slots.clone().acquire_owned().await else { return };
+            let client = client.clone();
+            let config = config.clone();
+            let url = webhook.url.clone();
+            let body = config.webhook_schema.render(message.clone());
+            tokio::spawn(async move {
+                deliver(&client, &config, url, body).await;
+                drop(permit);
+            });
+        }
+    }
+}
+
+/// POSTs one event, retrying with backoff and dead-lettering it if every attempt fails
+async fn deliver(client: &HttpsClient, config: &Config, url: Uri, body: String) {
+    let signature = config.webhook_secret.as_deref().map(|secret| sign(secret, &body));
+    let mut delay = INITIAL_RETRY_DELAY;
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri(url.clone())
+            .header(hyper::header::CONTENT_TYPE, "application/json");
+        if let Some(signature) = &signature {
+            request = request.header("X-Webhook-Signature", format!("sha256={}", signature));
+        }
+        let request = request.body(Body::from(body.clone())).expect("webhook request is well-formed");
+
+        let error = match tokio::time::timeout(REQUEST_TIMEOUT, client.request(request)).await {
+            Ok(Ok(response)) if response.status().is_success() => {
+                metrics::record_webhook_delivered();
+                return;
+            }
+            Ok(Ok(response)) => format!("HTTP {}", response.status()),
+            Ok(Err(e)) => e.to_string(),
+            Err(_) => "request timed out".to_string(),
+        };
+
+        if attempt > config.webhook_max_retries {
+            dead_letter(config, &url, &body, &error).await;
+            return;
+        }
+        warn!("Webhook {} failed (attempt {}): {}; retrying in {:?}", url, attempt, error, delay);
+        tokio::time::sleep(delay).await;
+        delay = (delay * 2).min(MAX_RETRY_DELAY);
+    }
+}
+
+/// Records an event that could not be delivered, appending it to the dead-letter file if one is configured
+async fn dead_letter(config: &Config, url: &Uri, body: &str, error: &str) {
+    metrics::record_webhook_dead_letter();
+    error!("Giving up on webhook {} after {} retries: {}", url, config.webhook_max_retries, error);
+
+    let Some(path) = &config.webhook_dead_letter_file else { return };
+    let entry = json!({
+        "url": url.to_string(),
+        "error": error,
+        "failed_at": chrono::Utc::now().to_rfc3339(),
+        "event": serde_json::from_str::<Value>(body).unwrap_or_else(|_| body.into()),
+    });
+    let written = async {
+        let mut file = tokio::fs::OpenOptions::new().create(true).append(true).open(path).await?;
+        file.write_all(format!("{}\n", entry).as_bytes()).await
+    };
+    if let Err(e) = written.await {
+        error!("Failed to write webhook dead letter to {}: {}", path, e);
+    }
+}
+
+/// Hex-encoded HMAC-SHA256 of the request body
+fn sign(secret: &str, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any length");
+    mac.update(body.as_bytes());
+    mac.finalize().into_bytes().iter().map(|byte| format!("{:02x}", byte)).collect()
+}
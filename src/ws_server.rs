@@ -1,49 +1,139 @@
 use tokio::net::TcpListener;
-use tokio_tungstenite::accept_async;
+use tokio_tungstenite::accept_hdr_async;
+use tungstenite::handshake::server::{ErrorResponse, Request, Response};
 use futures::{SinkExt, StreamExt};
-use tokio::sync::broadcast::Receiver;
+use tungstenite::Message;
 use log::{info, warn, error, debug};
-use std::sync::atomic::{AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use serde_json::Value;
+use crate::client_commands;
+use crate::client_groups;
+use crate::client_options::{ClientOptions, Format, Verbosity};
+use crate::config::Config;
+use crate::connection_registry;
+use crate::csv_format;
+use crate::delivery_hours;
+use crate::delta_encoder::DeltaEncoder;
+use crate::event_channels::{EventChannels, Missed, Subscription};
+use crate::handshake::HandshakeMessage;
+use crate::message_deadline::{DeadlineStream, MessageClock};
+use crate::metrics;
+use crate::projection::Projection;
+use crate::replay_buffer::ReplayBuffer;
+use crate::restart;
+use crate::slot_batcher::SlotBatcher;
+use crate::solana_client;
+use crate::state_index::StateIndex;
+use crate::state_sync;
+use crate::transitions;
 
 // Global connection counter for monitoring
 static CONNECTION_COUNT: AtomicUsize = AtomicUsize::new(0);
 
+// Id for the next connection; ids are never reused within a process
+static NEXT_CONNECTION_ID: AtomicUsize = AtomicUsize::new(0);
+
+// Connections currently paused with the `pause` command
+static PAUSED_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// Set once the server is draining; new handshakes are rejected from then on
+static DRAINING: AtomicBool = AtomicBool::new(false);
+
+/// Counts a connection in `CONNECTION_COUNT`, and in `PAUSED_COUNT` while paused, for as long as it lives
+///
+/// Dropping it undoes both, however the handler ends: a return, a panic or the task being aborted.
+struct ActiveConnection {
+    paused: bool,
+}
+
+impl ActiveConnection {
+    fn new() -> Self {
+        CONNECTION_COUNT.fetch_add(1, Ordering::SeqCst);
+        ActiveConnection { paused: false }
+    }
+
+    fn set_paused(&mut self, paused: bool) {
+        if paused && !self.paused {
+            PAUSED_COUNT.fetch_add(1, Ordering::SeqCst);
+        } else if !paused && self.paused {
+            PAUSED_COUNT.fetch_sub(1, Ordering::SeqCst);
+        }
+        self.paused = paused;
+    }
+}
+
+impl Drop for ActiveConnection {
+    fn drop(&mut self) {
+        self.set_paused(false);
+        CONNECTION_COUNT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
 /// Starts the WebSocket server and handles client connections
 /// 
 /// # Arguments
-/// * `port` - The port number to bind the server to
-/// * `receiver` - Broadcast receiver for incoming events
-pub async fn start_ws_server(port: u16, receiver: Receiver<String>) {
+/// * `config` - Service configuration (server port, replay settings)
+/// * `sender` - Broadcast channel for incoming events; each client subscribes its own receiver
+/// * `replay` - Recent events sent to each client after the welcome message
+/// * `state` - Per-mint state index queried by client commands
+pub async fn start_ws_server(
+    config: Arc<Config>,
+    sender: EventChannels,
+    replay: Arc<Mutex<ReplayBuffer>>,
+    state: Arc<Mutex<StateIndex>>,
+) {
+    let port = config.server_port;
     let addr = format!("0.0.0.0:{}", port);
     
-    // Bind to the specified address
-    let listener = match TcpListener::bind(&addr).await {
-        Ok(listener) => {
-            info!("WebSocket Server running on {}", addr);
-            listener
-        }
-        Err(e) => {
-            error!("Failed to bind port {}: {}", port, e);
-            return;
-        }
+    // Bind to the specified address, or take over the previous process's listener after a restart
+    let inherited = config.graceful_restart
+        .then(|| restart::inherited_listener(restart::WS_LISTENER, port))
+        .flatten()
+        .and_then(|listener| TcpListener::from_std(listener).ok());
+    let listener = match inherited {
+        Some(listener) => listener,
+        None => match TcpListener::bind(&addr).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("Failed to bind port {}: {}", port, e);
+                return;
+            }
+        },
     };
+    info!("WebSocket Server running on {}", addr);
+    if config.graceful_restart {
+        restart::register_listener(restart::WS_LISTENER, &listener);
+    }
 
     info!("Waiting for WebSocket connections...");
 
     loop {
-        // Accept new connections
-        match listener.accept().await {
+        // Accept new connections until the listener is handed to a new process
+        let accepted = tokio::select! {
+            accepted = listener.accept() => accepted,
+            _ = restart::handed_off() => {
+                info!("WebSocket listener handed over, no longer accepting connections");
+                return;
+            }
+        };
+        match accepted {
             Ok((stream, addr)) => {
-                let connection_id = CONNECTION_COUNT.fetch_add(1, Ordering::SeqCst);
-                info!("New connection #{} from {}", connection_id, addr);
-                
-                // Create a new receiver for this client
-                let mut rx = receiver.resubscribe();
+                let connection_id = NEXT_CONNECTION_ID.fetch_add(1, Ordering::SeqCst);
+                // Create a new receiver for this client, on the next shard in turn
+                let rx = sender.subscribe();
+                match sender.shard_count() {
+                    1 => info!("New connection #{} from {}", connection_id, addr),
+                    _ => info!("New connection #{} from {} on broadcast shard {}", connection_id, addr, rx.shard()),
+                }
+                let replay = replay.clone();
+                let state = state.clone();
+                let config = config.clone();
                 
                 // Spawn a new task to handle this client
                 tokio::spawn(async move {
-                    handle_client_connection(stream, rx, connection_id, addr).await;
+                    handle_client_connection(stream, rx, replay, state, config, connection_id, addr).await;
                 });
             }
             Err(e) => {
@@ -56,60 +146,546 @@ pub async fn start_ws_server(port: u16, receiver: Receiver<String>) {
 /// Handles an individual client WebSocket connection
 async fn handle_client_connection(
     stream: tokio::net::TcpStream,
-    mut rx: Receiver<String>,
+    mut rx: Subscription,
+    replay: Arc<Mutex<ReplayBuffer>>,
+    state: Arc<Mutex<StateIndex>>,
+    config: Arc<Config>,
     connection_id: usize,
     addr: std::net::SocketAddr,
 ) {
-    // Accept the WebSocket connection
-    let ws_stream = match accept_async(stream).await {
+    let mut active = ActiveConnection::new();
+
+    // Every message, the upgrade request included, must finish arriving within the timeout
+    let message_timeout = (config.client_message_timeout_ms > 0).then(|| Duration::from_millis(config.client_message_timeout_ms));
+    let clock = MessageClock::default();
+    let stream = DeadlineStream::new(stream, message_timeout, clock.clone());
+
+    // Accept the WebSocket connection, refusing new clients while draining
+    // and capturing the connection options from the request URL
+    let mut options = ClientOptions::default();
+    #[allow(clippy::result_large_err)] // signature required by tungstenite
+    let callback = |request: &Request, response: Response| {
+        let response = reject_if_draining(request, response)?;
+        options = ClientOptions::from_query(request.uri().query()).map_err(bad_request)?;
+        if let Some(group) = options.group.as_ref().filter(|group| !client_groups::exists(group)) {
+            return Err(bad_request(format!("unknown group '{}'", group)));
+        }
+        if options.transitions_only && config.transitions.is_none() {
+            return Err(bad_request("transitions_only is not enabled on this server".to_string()));
+        }
+        if options.sync && config.sync.is_none() {
+            return Err(bad_request("sync is disabled on this server".to_string()));
+        }
+        if options.schema.needs_raw_accounts() && !config.ws_geyser_schema {
+            return Err(bad_request("schema=geyser is not enabled on this server".to_string()));
+        }
+        Ok(response)
+    };
+    let ws_stream = match accept_hdr_async(stream, callback).await {
         Ok(ws_stream) => {
             info!("WebSocket connection #{} established from {}", connection_id, addr);
+            clock.reset();
             ws_stream
         }
         Err(e) => {
-            error!("Failed to accept WebSocket connection #{} from {}: {}", connection_id, addr, e);
+            if is_draining() {
+                info!("Rejected connection #{} from {}: server is draining", connection_id, addr);
+            } else {
+                error!("Failed to accept WebSocket connection #{} from {}: {}", connection_id, addr, e);
+            }
             return;
         }
     };
 
-    let (mut write, _) = ws_stream.split();
-    
-    // Send welcome message
-    let welcome_msg = serde_json::json!({
-        "type": "connection_established",
-        "connection_id": connection_id,
-        "message": "Connected to Pump.fun WebSocket Service"
-    });
-    
-    if let Err(e) = write.send(tungstenite::Message::Text(welcome_msg.to_string())).await {
-        warn!("Failed to send welcome message to connection #{}: {}", connection_id, e);
+    let (mut write, mut read) = ws_stream.split();
+
+    // Process incoming events and send to client, counting them for /admin/connections
+    let stats = connection_registry::register(connection_id, addr, &options);
+
+    // Catch the client up on recent events, skipping any older than the configured max age.
+    // The live receiver was subscribed before this snapshot, so an event broadcast in
+    // between may be delivered twice rather than lost.
+    let max_event_age = config.max_event_age_secs.map(|secs| chrono::Duration::seconds(secs as i64));
+    let mut event_seq = 0u64;
+    let mut session = client_commands::Session {
+        ack_window: options.ack_window.map(u64::from),
+        slot_batches: options.slot_batches,
+        synced: options.sync,
+        ..Default::default()
+    };
+    // A synced connection starts from the state snapshot, which supersedes the replay
+    let buffered = if options.sync { Vec::new() } else { replay.lock().unwrap().snapshot(max_event_age) };
+    let replayed: Vec<String> = buffered.into_iter()
+        .filter(|message| options.group.as_ref().is_none_or(|group| client_groups::accepts(group, message)))
+        .filter(|message| config.transitions.is_none() || transitions::passes(message, options.transitions_only))
+        .map(|message| {
+            let message = shape(message, &options, None, &mut event_seq);
+            match session.ack_window {
+                Some(_) => number_event(message, &mut event_seq).0,
+                None => message,
+            }
+        })
+        .collect();
+    // Replayed events count against the ack window like any others
+    session.last_sent_seq = event_seq;
+    if !replayed.is_empty() {
+        debug!("Replaying {} buffered events to connection #{}", replayed.len(), connection_id);
+    }
+
+    // Send the welcome message with the replay either inside it or queued right behind
+    // it, flushing once so the whole catch-up goes out together
+    let mut welcome_msg = HandshakeMessage::new(connection_id, &options, &config);
+    let mut frames = Vec::new();
+    if options.format == Format::Csv {
+        frames.push(csv_format::header());
+    }
+    // CSV rows are not JSON, so they always follow the welcome message as frames
+    if config.combined_replay && options.format == Format::Json {
+        welcome_msg.replay = Some(replayed.iter()
+            .map(|message| serde_json::from_str(message).unwrap_or_else(|_| message.as_str().into()))
+            .collect());
+    } else {
+        stats.add_sent(replayed.len() as u64);
+        frames.extend(replayed);
     }
+    if let Some(limits) = config.sync.as_ref().filter(|_| options.sync) {
+        let pages = state_sync::snapshot_pages(&state, limits, config.output_format(), options.group.as_deref(), &session.tracked_creators, event_seq);
+        stats.add_sent(pages.len() as u64);
+        frames.extend(pages.iter().map(Value::to_string));
+    }
+    stats.add_sent(welcome_msg.replay.as_ref().map_or(0, Vec::len) as u64);
+    let welcome_json = serde_json::to_string(&welcome_msg).expect("handshake message serializes");
 
-    // Process incoming events and send to client
-    let mut message_count = 0u64;
+    let sent = async {
+        for text in std::iter::once(welcome_json).chain(frames) {
+            write.feed(Message::Text(text)).await?;
+        }
+        write.flush().await
+    };
+    // A connection that cannot take the welcome message is already broken, so drop it here
+    if let Err(e) = sent.await {
+        warn!("Failed to send welcome message and replay to connection #{}, closing it: {}", connection_id, e);
+        return;
+    }
+
+    // Tell clients connecting outside delivery hours why no events are arriving
+    if let Some(schedule) = config.delivery_hours.as_ref().filter(|_| delivery_hours::is_paused()) {
+        let notice = delivery_hours::outside_hours_notice(schedule);
+        if let Err(e) = write.send(Message::Text(notice.to_string())).await {
+            warn!("Failed to send outside-hours notice to connection #{}: {}", connection_id, e);
+        }
+    }
     
-    while let Ok(message) = rx.recv().await {
-        message_count += 1;
-        debug!("Sending message #{} to connection #{}", message_count, connection_id);
-        
-        match write.send(tungstenite::Message::Text(message.clone())).await {
-            Ok(_) => {
-                // Message sent successfully
+    let mut deltas = options.reserve_deltas
+        .then(|| DeltaEncoder::new(config.delta_keyframe_interval, config.max_tracked_mints));
+    let mut batcher = options.slot_batches
+        .then(|| SlotBatcher::new(Duration::from_millis(config.slot_batch_timeout_ms), options.batch_sort));
+
+    // Periodic stream vitals for clients that asked for them; the first tick is skipped
+    let health_period = Duration::from_secs(config.stream_health_interval_secs);
+    let mut health_ticker = tokio::time::interval_at(tokio::time::Instant::now() + health_period, health_period);
+    // Rollups for tracked creators, ticking whether or not any are tracked yet
+    let summary_period = config.creator_tracking.map_or(Duration::from_secs(60), |tracking| tracking.summary_interval);
+    let mut summary_ticker = tokio::time::interval_at(tokio::time::Instant::now() + summary_period, summary_period);
+    summary_ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        let batch_deadline = batcher.as_ref().and_then(SlotBatcher::deadline);
+        stats.set_awaiting_acks(session.awaiting_acks());
+        tokio::select! {
+            // In ack mode a full window stops delivery; events wait in the channel until acks arrive
+            event = rx.recv(), if !session.awaiting_acks() => {
+                let message = match event {
+                    Ok(message) => message,
+                    Err(Missed::Lagged { skipped, lossy }) => {
+                        metrics::record_client_lag(skipped);
+                        warn!("Connection #{} fell behind and missed {} events", connection_id, skipped);
+                        if !lossy {
+                            break;
+                        }
+                        // In priority mode only trades were dropped; tell the client and carry on
+                        let notice = serde_json::json!({"type": "trades_skipped", "skipped": skipped});
+                        if let Err(e) = write.send(Message::Text(notice.to_string())).await {
+                            debug!("Failed to send trades_skipped notice to connection #{}: {}", connection_id, e);
+                            break;
+                        }
+                        continue;
+                    }
+                    Err(Missed::Closed) => break,
+                };
+                stats.set_queued_events(rx.len());
+                // A paused client keeps draining the channel so it doesn't lag out while idle
+                if session.paused {
+                    session.skipped_while_paused += 1;
+                    continue;
+                }
+                if options.group.as_ref().is_some_and(|group| !client_groups::accepts(group, &message)) {
+                    continue;
+                }
+                if config.transitions.is_some() && !transitions::passes(&message, options.transitions_only) {
+                    continue;
+                }
+                if !session.tracked_creators.is_empty() && !session.tracked_creators.accepts(&message, &state) {
+                    continue;
+                }
+
+                let message = shape(message, &options, session.projection.as_ref(), &mut event_seq);
+
+                // In delta mode, replace trades with the change in reserves since the last update
+                let message = match deltas.as_mut() {
+                    Some(deltas) => match serde_json::from_str::<Value>(&message) {
+                        Ok(event) => deltas.encode(event).to_string(),
+                        Err(_) => message,
+                    },
+                    None => message,
+                };
+
+                // In slot batch mode, hold back events that carry a slot until it completes
+                let message = match batcher.as_mut() {
+                    Some(batcher) => match slot_of(&message) {
+                        Some((slot, event)) => match batcher.push(slot, event) {
+                            Some(batch) => batch.to_string(),
+                            None => continue,
+                        },
+                        None => message,
+                    },
+                    None => message,
+                };
+
+                let message = if session.ack_window.is_some() || session.synced {
+                    let (message, seq) = number_event(message, &mut event_seq);
+                    if let Some(seq) = seq {
+                        session.last_sent_seq = seq;
+                    }
+                    message
+                } else {
+                    message
+                };
+
+                let message_count = stats.add_sent(1);
+                debug!("Sending message #{} to connection #{}", message_count, connection_id);
+
+                // A client that stops reading leaves the send waiting; shedding must still end it
+                let sent = tokio::select! {
+                    sent = write.send(Message::Text(message)) => Some(sent),
+                    _ = stats.shed_requested() => None,
+                };
+                let Some(sent) = sent else {
+                    warn!("Disconnecting connection #{}: stalled behind a saturated broadcast buffer", connection_id);
+                    break;
+                };
+                if let Err(e) = sent {
+                    warn!("Failed to send message to connection #{}: {}", connection_id, e);
+                    break;
+                }
             }
-            Err(e) => {
-                warn!("Failed to send message to connection #{}: {}", connection_id, e);
+            _ = tokio::time::sleep_until(batch_deadline.unwrap_or_else(tokio::time::Instant::now)), if batch_deadline.is_some() => {
+                let Some(batch) = batcher.as_mut().and_then(SlotBatcher::flush) else { continue };
+                if session.paused {
+                    continue;
+                }
+                stats.add_sent(1);
+                if let Err(e) = write.send(Message::Text(batch.to_string())).await {
+                    warn!("Failed to send slot batch to connection #{}: {}", connection_id, e);
+                    break;
+                }
+            }
+            _ = health_ticker.tick(), if options.stream_health => {
+                if session.paused {
+                    continue;
+                }
+                let health = stream_health(&state);
+                if let Err(e) = write.send(Message::Text(health.to_string())).await {
+                    warn!("Failed to send stream health to connection #{}: {}", connection_id, e);
+                    break;
+                }
+            }
+            _ = stats.shed_requested() => {
+                warn!("Disconnecting connection #{}: {} events behind a saturated broadcast buffer", connection_id, rx.len());
                 break;
             }
+            _ = summary_ticker.tick(), if !session.tracked_creators.is_empty() => {
+                if session.paused {
+                    continue;
+                }
+                let summaries = session.tracked_creators.summaries(&state, config.units);
+                let sent = async {
+                    for summary in summaries {
+                        write.feed(Message::Text(summary.to_string())).await?;
+                    }
+                    write.flush().await
+                };
+                if let Err(e) = sent.await {
+                    warn!("Failed to send creator summaries to connection #{}: {}", connection_id, e);
+                    break;
+                }
+            }
+            incoming = read.next() => match incoming.inspect(|message| {
+                if let Ok(message) = message {
+                    clock.reset();
+                    metrics::record_client_message(message.len());
+                }
+            }) {
+                Some(Ok(Message::Text(text))) => {
+                    // Client control message: reply directly on this connection
+                    let was_paused = session.paused;
+                    let reply = client_commands::handle_command(&text, &state, &config, &mut session);
+                    if std::mem::take(&mut session.sync_requested) {
+                        let limits = config.sync.as_ref().expect("checked by handle_command");
+                        let pages = state_sync::snapshot_pages(&state, limits, config.output_format(), options.group.as_deref(), &session.tracked_creators, event_seq);
+                        session.synced = true;
+                        stats.add_sent(pages.len() as u64);
+                        let sent = async {
+                            for page in pages {
+                                write.feed(Message::Text(page.to_string())).await?;
+                            }
+                            write.flush().await
+                        };
+                        if let Err(e) = sent.await {
+                            warn!("Failed to send snapshot to connection #{}: {}", connection_id, e);
+                            break;
+                        }
+                    }
+                    let Some(reply) = reply else {
+                        continue;
+                    };
+                    match (was_paused, session.paused) {
+                        (false, true) => {
+                            active.set_paused(true);
+                            stats.set_paused(true);
+                            info!("Connection #{} paused", connection_id);
+                        }
+                        (true, false) => {
+                            active.set_paused(false);
+                            stats.set_paused(false);
+                            info!("Connection #{} resumed", connection_id);
+                        }
+                        _ => {}
+                    }
+                    if let Err(e) = write.send(Message::Text(reply.to_string())).await {
+                        warn!("Failed to send reply to connection #{}: {}", connection_id, e);
+                        break;
+                    }
+                }
+                Some(Ok(Message::Close(_))) | None => break,
+                Some(Ok(_)) => {
+                    // Ignore binary frames; pings are answered by tungstenite
+                }
+                Some(Err(tungstenite::Error::Io(e))) if e.kind() == std::io::ErrorKind::TimedOut => {
+                    metrics::record_client_message_timeout();
+                    warn!("Closing connection #{}: message not completed within {:?}", connection_id, message_timeout.unwrap_or_default());
+                    break;
+                }
+                Some(Err(e)) => {
+                    warn!("Failed to read from connection #{}: {}", connection_id, e);
+                    break;
+                }
+            },
         }
     }
 
-    // Update connection count
-    CONNECTION_COUNT.fetch_sub(1, Ordering::SeqCst);
     info!("Connection #{} from {} disconnected. Total messages sent: {}", 
-          connection_id, addr, message_count);
+          connection_id, addr, stats.messages_sent());
+}
+
+/// Handshake callback that answers `503 server_draining` once draining has started
+#[allow(clippy::result_large_err)] // signature required by tungstenite
+fn reject_if_draining(_request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+    if !is_draining() {
+        return Ok(response);
+    }
+    let mut rejection = ErrorResponse::new(Some("server_draining".to_string()));
+    *rejection.status_mut() = tungstenite::http::StatusCode::SERVICE_UNAVAILABLE;
+    Err(rejection)
+}
+
+/// Builds the `stream_health` summary from the service counters and state index
+fn stream_health(state: &Mutex<StateIndex>) -> Value {
+    let minute_ago = chrono::Utc::now() - chrono::Duration::minutes(1);
+    serde_json::json!({
+        "type": "stream_health",
+        "events_last_min": metrics::events_last_minute(),
+        "active_mints": state.lock().unwrap().active_since(minute_ago),
+        "upstream_connected": solana_client::is_upstream_connected(),
+        "slot": solana_client::latest_slot(),
+    })
+}
+
+/// Cuts an event down to its type, cluster, mint, price, slot and timestamp, numbering it with `seq`
+///
+/// Messages without an `event_type`, such as `stream_stalled`, are returned unchanged.
+fn minimal(message: String, seq: &mut u64) -> String {
+    let Ok(event) = serde_json::from_str::<Value>(&message) else { return message };
+    let Some(event_type) = event.get("event_type") else { return message };
+    *seq += 1;
+    let mint_address = event.get("mint_address").or_else(|| event.get("token")?.get("mint_address"));
+    let price_sol = event.get("pump_data").and_then(|pump_data| pump_data.get("price_sol"));
+    let mut reduced = serde_json::json!({
+        "event_type": event_type,
+        "cluster": event["cluster"],
+        "seq": *seq,
+        "slot": event["slot"],
+        "timestamp": event["timestamp"],
+    });
+    if let Some(mint_address) = mint_address {
+        reduced["mint_address"] = mint_address.clone();
+    }
+    if let Some(price_sol) = price_sol {
+        reduced["price_sol"] = price_sol.clone();
+    }
+    reduced.to_string()
+}
+
+/// Gives an event the `seq` that ack mode clients acknowledge and synced clients order by, returning it with that seq
+///
+/// Keeps the `seq` an event already has from `verbosity=minimal` or a projection.
+/// Messages without an `event_type`, such as `stream_stalled`, are not numbered.
+fn number_event(message: String, seq: &mut u64) -> (String, Option<u64>) {
+    let Ok(mut event) = serde_json::from_str::<Value>(&message) else { return (message, None) };
+    if event.get("event_type").is_none() {
+        return (message, None);
+    }
+    if let Some(existing) = event.get("seq").and_then(Value::as_u64) {
+        return (message, Some(existing));
+    }
+    *seq += 1;
+    event["seq"] = (*seq).into();
+    (event.to_string(), Some(*seq))
+}
+
+/// Applies the client's projection, or its verbosity, schema and format, to a broadcast message
+///
+/// `seq` numbers the events on this connection. CSV leaves non-event messages as JSON,
+/// and a projection always produces JSON in place of the schema.
+fn shape(message: String, options: &ClientOptions, projection: Option<&Projection>, seq: &mut u64) -> String {
+    if let Some(projection) = projection {
+        return projection.apply(message, seq);
+    }
+    let message = match options.verbosity {
+        Verbosity::Minimal => minimal(message, seq),
+        Verbosity::Standard | Verbosity::Full => message,
+    };
+    let message = options.schema.render(message);
+    match options.format {
+        Format::Json => message,
+        Format::Csv => csv_format::to_row(&message, seq).unwrap_or(message),
+    }
+}
+
+/// Handshake rejection for malformed connection parameters
+fn bad_request(message: String) -> ErrorResponse {
+    let mut rejection = ErrorResponse::new(Some(message));
+    *rejection.status_mut() = tungstenite::http::StatusCode::BAD_REQUEST;
+    rejection
+}
+
+/// Parses a broadcast event, returning it with its slot if it has one
+fn slot_of(message: &str) -> Option<(u64, Value)> {
+    let event: Value = serde_json::from_str(message).ok()?;
+    let slot = event.get("slot")?.as_u64()?;
+    Some((slot, event))
 }
 
 /// Returns the current number of active connections
 pub fn get_active_connections() -> usize {
     CONNECTION_COUNT.load(Ordering::SeqCst)
-}
\ No newline at end of file
+}
+
+/// Returns the number of connections currently paused by their client
+pub fn get_paused_connections() -> usize {
+    PAUSED_COUNT.load(Ordering::SeqCst)
+}
+
+/// Returns true once the server has been put into draining mode
+pub fn is_draining() -> bool {
+    DRAINING.load(Ordering::SeqCst)
+}
+
+/// Stops accepting new clients while existing connections keep receiving events
+///
+/// Logs once every client has disconnected or `timeout` elapses, at which point
+/// the process can be stopped. Returns false if the server was already draining.
+pub fn start_draining(timeout: Duration) -> bool {
+    if DRAINING.swap(true, Ordering::SeqCst) {
+        return false;
+    }
+    info!("Draining: rejecting new connections, {} still active", get_active_connections());
+
+    tokio::spawn(async move {
+        let deadline = tokio::time::Instant::now() + timeout;
+        while get_active_connections() > 0 && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+        }
+        match get_active_connections() {
+            0 => info!("Drain complete: all connections closed, safe to stop"),
+            remaining => warn!("Drain timeout reached with {} connections still active, safe to stop", remaining),
+        }
+    });
+    true
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::SocketAddr;
+    use tokio::net::TcpStream;
+    use tokio::task::JoinHandle;
+
+    // The active connection count is process-wide, so tests that check it take turns
+    static SERIAL: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+    /// Accepts `connections` clients on a local port and handles each as the server does,
+    /// finishing once every handler has returned
+    async fn serve(connections: usize) -> (SocketAddr, JoinHandle<()>) {
+        let config = Arc::new(Config::for_tests());
+        let sender = EventChannels::new(16, 16, None, 1);
+        let replay = Arc::new(Mutex::new(ReplayBuffer::new(16, None)));
+        let state = Arc::new(Mutex::new(StateIndex::new(config.trigger_fields.clone(), 0, None, None, None, false, None, None, None)));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let mut handlers = Vec::new();
+            for connection_id in 0..connections {
+                let (stream, peer) = listener.accept().await.unwrap();
+                let handler = handle_client_connection(stream, sender.subscribe(), replay.clone(), state.clone(), config.clone(), connection_id, peer);
+                handlers.push(tokio::spawn(handler));
+            }
+            for handler in handlers {
+                handler.await.expect("connection handler panicked");
+            }
+        });
+        (addr, server)
+    }
+
+    async fn finish(server: JoinHandle<()>) {
+        tokio::time::timeout(Duration::from_secs(10), server).await
+            .expect("connection handlers should return once their clients are gone")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn active_count_returns_to_zero_after_connections_churn() {
+        let _serial = SERIAL.lock().await;
+        let (addr, server) = serve(40).await;
+        for _ in 0..20 {
+            // Gone before the WebSocket handshake
+            drop(TcpStream::connect(addr).await.unwrap());
+        }
+        for _ in 0..20 {
+            // Gone right after it, without a close frame
+            let (ws_stream, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+            drop(ws_stream);
+        }
+        finish(server).await;
+        assert_eq!(get_active_connections(), 0);
+    }
+
+    #[tokio::test]
+    async fn client_closing_right_after_the_handshake_is_cleaned_up() {
+        let _serial = SERIAL.lock().await;
+        let (addr, server) = serve(1).await;
+        let (mut ws_stream, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        ws_stream.close(None).await.unwrap();
+        finish(server).await;
+        assert_eq!(get_active_connections(), 0);
+    }
+}